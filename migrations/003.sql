@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS fetch_cache (
+     url TEXT PRIMARY KEY,
+     html TEXT NOT NULL,
+     fetched_at DATETIME NOT NULL
+);
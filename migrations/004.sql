@@ -0,0 +1 @@
+ALTER TABLE link ADD COLUMN archived_at DATETIME NULL;
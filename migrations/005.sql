@@ -0,0 +1 @@
+ALTER TABLE link ADD COLUMN read_at DATETIME NULL;
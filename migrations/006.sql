@@ -0,0 +1 @@
+ALTER TABLE link ADD COLUMN is_favorite BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1 @@
+ALTER TABLE link ADD COLUMN content_sha256 TEXT NULL;
@@ -0,0 +1 @@
+ALTER TABLE tag ADD COLUMN description TEXT NULL;
@@ -0,0 +1 @@
+ALTER TABLE tag ADD COLUMN parent_id BLOB NULL REFERENCES tag(id) ON DELETE SET NULL;
@@ -0,0 +1,5 @@
+CREATE VIRTUAL TABLE note_content
+USING FTS5(note_id, content);
+
+INSERT INTO note_content(note_id, content)
+SELECT id, content FROM note;
@@ -0,0 +1 @@
+ALTER TABLE link ADD COLUMN pinned_order INTEGER NULL;
@@ -0,0 +1 @@
+ALTER TABLE link ADD COLUMN meta TEXT NULL;
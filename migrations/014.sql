@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS operation_log (
+     id BLOB CHECK(length(id) = 16) PRIMARY KEY,
+     operation TEXT NOT NULL,
+     item_id BLOB CHECK(length(item_id) = 16) NOT NULL,
+     item_type TEXT NOT NULL,
+     payload TEXT,
+     created_at DATETIME NOT NULL
+);
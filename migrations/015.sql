@@ -0,0 +1 @@
+ALTER TABLE link ADD COLUMN is_reading_list BOOLEAN NOT NULL DEFAULT FALSE;
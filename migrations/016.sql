@@ -0,0 +1 @@
+ALTER TABLE link ADD COLUMN local_archive_path TEXT NULL;
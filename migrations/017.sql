@@ -0,0 +1 @@
+UPDATE link SET is_primary = TRUE WHERE archived_at IS NOT NULL;
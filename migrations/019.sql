@@ -0,0 +1 @@
+ALTER TABLE link ADD COLUMN refreshed_at DATETIME NULL;
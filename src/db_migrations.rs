@@ -1,12 +1,83 @@
 use anyhow::Result;
-use rusqlite::Connection;
+use rusqlite::{params, Connection, Transaction};
 use rusqlite_migration::{Migrations, M};
+use std::collections::HashMap;
 
-pub fn migrate(mut conn: Connection) -> Result<()> {
+pub fn migrate(conn: &mut Connection) -> Result<()> {
     let migrations = Migrations::new(vec![
         M::up(include_str!("../migrations/001.sql")),
         M::up(include_str!("../migrations/002.sql")),
+        M::up(include_str!("../migrations/003.sql")),
+        M::up(include_str!("../migrations/004.sql")),
+        M::up(include_str!("../migrations/005.sql")),
+        M::up(include_str!("../migrations/006.sql")),
+        M::up(include_str!("../migrations/007.sql")),
+        M::up(include_str!("../migrations/008.sql")),
+        M::up(include_str!("../migrations/009.sql")),
+        M::up(include_str!("../migrations/010.sql")),
+        M::up(include_str!("../migrations/011.sql")),
+        M::up(include_str!("../migrations/012.sql")),
+        M::up(include_str!("../migrations/013.sql")),
+        M::up(include_str!("../migrations/014.sql")),
+        M::up(include_str!("../migrations/015.sql")),
+        M::up(include_str!("../migrations/016.sql")),
+        M::up(include_str!("../migrations/017.sql")),
+        M::up_with_hook("", |tx: &Transaction| Ok(reslug_tags(tx)?)),
+        M::up(include_str!("../migrations/019.sql")),
     ]);
-    migrations.to_latest(&mut conn)?;
+    migrations.to_latest(conn)?;
+    Ok(())
+}
+
+// Slugs used to keep non-ASCII letters, and are now transliterated (so
+// "Café" is `cafe`), which SQL alone can't do. Where two tags end up with the
+// same slug, the older one keeps it and takes over the other's items and
+// children.
+pub fn reslug_tags(tx: &Transaction) -> rusqlite::Result<()> {
+    let tags = tx
+        .prepare("SELECT id, name FROM tag ORDER BY created_at, id")?
+        .query_map([], |row| {
+            Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut kept: HashMap<String, Vec<u8>> = HashMap::new();
+    for (id, name) in tags {
+        // A name that no longer makes a valid slug keeps the one it has.
+        let Ok(slug) = crate::util::slugify(&name) else {
+            continue;
+        };
+        let Some(into) = kept.get(&slug) else {
+            tx.execute("UPDATE tag SET slug = ?1 WHERE id = ?2", params![slug, id])?;
+            kept.insert(slug, id);
+            continue;
+        };
+        // item_tag's uniqueness constraint doesn't apply when an ID is NULL,
+        // so drop the items both tags have before moving the rest.
+        tx.execute(
+            "DELETE FROM item_tag
+            WHERE tag_id = ?1
+            AND EXISTS (
+                SELECT 1 FROM item_tag AS other
+                WHERE other.tag_id = ?2
+                AND other.link_id IS item_tag.link_id
+                AND other.note_id IS item_tag.note_id
+            )",
+            params![id, into],
+        )?;
+        tx.execute(
+            "UPDATE item_tag SET tag_id = ?2 WHERE tag_id = ?1",
+            params![id, into],
+        )?;
+        tx.execute(
+            "UPDATE tag SET parent_id = NULLIF(?2, id) WHERE parent_id = ?1",
+            params![id, into],
+        )?;
+        tx.execute(
+            "UPDATE tag SET description = (SELECT description FROM tag WHERE id = ?1)
+            WHERE id = ?2 AND description IS NULL",
+            params![id, into],
+        )?;
+        tx.execute("DELETE FROM tag WHERE id = ?1", params![id])?;
+    }
     Ok(())
 }
@@ -0,0 +1,28 @@
+//! Error types shared by meowpad's storage layer and commands.
+
+use thiserror::Error;
+
+/// Errors that callers may want to tell apart, rather than just report.
+#[derive(Debug, Error)]
+pub enum MeowpadError {
+    /// What wasn't found, such as "Tag `rust`" or "Link <https://…>"
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("<{0}> is already saved")]
+    DuplicateUrl(String),
+    /// The URL as given, and why it was rejected
+    #[error("`{0}` is not a valid URL; {1}")]
+    InvalidUrl(String, String),
+    #[error(transparent)]
+    DatabaseError(#[from] rusqlite::Error),
+    #[error("Invalid timestamp: {0}")]
+    InvalidTimestamp(#[from] jiff::Error),
+    #[error("{0}")]
+    InvalidArgument(String),
+    #[error("Unable to fetch page: {0}")]
+    FetchError(String),
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
+}
+
+pub type Result<T, E = MeowpadError> = std::result::Result<T, E>;
@@ -1,12 +1,20 @@
 use anyhow::{anyhow, Context, Result};
 use clap::{Parser, Subcommand, ValueEnum};
-use comfy_table::Table;
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::style::{Print, PrintStyledContent, Stylize};
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::{cursor, queue};
 use dom_smoothie::{Article, Readability};
-use jiff::{Timestamp, Unit, Zoned};
-use rusqlite::{Connection, Transaction};
-use serde::Deserialize;
-use std::path::PathBuf;
-use ureq::Agent;
+use indicatif::{ProgressBar, ProgressStyle};
+use jiff::{Span, Timestamp, Unit, Zoned};
+use meowpad::MeowpadError;
+use rusqlite::{Connection, OpenFlags, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use ureq::{Agent, ResponseExt};
 use url::Url;
 use uuid::Uuid;
 
@@ -19,12 +27,74 @@ type TableId = Uuid;
 static APP_NAME: &str = env!("CARGO_PKG_NAME");
 static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
 
+const EXPORT_HTML_TEMPLATE: &str = include_str!("../templates/export.html");
+const IMPORT_TOML_TEMPLATE: &str = include_str!("../templates/import.toml");
+
 #[derive(Clone, Debug, Default, ValueEnum)]
 enum ListOutputFormat {
     #[default]
     Table,
+    Json,
+    Yaml,
+    /// Tab-separated rows with no header or quoting, for cut and awk
+    Tsv,
+}
+
+/// The output formats of `list` and `search`, which can also be read as a feed
+#[derive(Clone, Debug, Default, ValueEnum)]
+enum LinkListFormat {
+    #[default]
+    Table,
+    Json,
+    Yaml,
+    /// Tab-separated rows with no header or quoting, for cut and awk
+    Tsv,
+    /// An Atom feed of the links
+    Atom,
+}
+
+/// The output formats of `show`, which can also be read as a spreadsheet
+#[derive(Clone, Debug, Default, ValueEnum)]
+enum ShowFormat {
+    #[default]
+    Table,
+    Json,
+    Yaml,
+    /// Tab-separated rows with no header or quoting, for cut and awk
+    Tsv,
+    /// A header row and a row of every field
+    Csv,
+}
+
+/// A column of the link table shown by `list`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ListColumn {
+    Id,
+    Url,
+    Title,
+    Created,
+    Modified,
+    Description,
+    Tags,
+}
+
+impl ListColumn {
+    fn header(self) -> &'static str {
+        match self {
+            ListColumn::Id => "ID",
+            ListColumn::Url => "URL",
+            ListColumn::Title => "Title",
+            ListColumn::Created => "Created",
+            ListColumn::Modified => "Modified",
+            ListColumn::Description => "Description",
+            ListColumn::Tags => "Tags",
+        }
+    }
 }
 
+const DEFAULT_LIST_COLUMNS: [ListColumn; 3] =
+    [ListColumn::Url, ListColumn::Title, ListColumn::Created];
+
 // NB See https://rust-cli-recommendations.sunshowers.io/handling-arguments.html
 // for advice on structuring the subcommands
 #[derive(Debug, Parser)]
@@ -37,15 +107,187 @@ struct Cli {
     /// Path to the config file to use
     #[clap(long, global = true)]
     config: Option<PathBuf>,
-    /// Path to the database to use
+    /// Path to the database to use (overrides MEOWPAD_DB and the config file)
     #[clap(long, global = true)]
     db: Option<PathBuf>,
+    /// Write output to this file instead of stdout, replacing its contents
+    #[clap(short, long, global = true)]
+    output: Option<PathBuf>,
+    /// Append to the --output file rather than replacing it
+    #[clap(long, global = true, requires = "output")]
+    append: bool,
+    /// Pretty-print JSON output (the default when writing to a terminal)
+    #[clap(long, global = true)]
+    json_pretty: bool,
+    /// Don't color output, even if the config file asks for it
+    #[clap(long, global = true)]
+    no_color: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 struct Config {
     #[serde(default)]
     database: PathBuf,
+    #[serde(default)]
+    fetch: FetchConfig,
+    #[serde(default)]
+    ui: UiConfig,
+    #[serde(default)]
+    add: AddConfig,
+    /// Starting content for new notes, chosen with `note --template`
+    #[serde(default, rename = "note_template")]
+    note_templates: Vec<NoteTemplate>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct NoteTemplate {
+    name: String,
+    /// May include {{date}}, {{title}}, and {{url}} placeholders
+    content: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct FetchConfig {
+    /// Global timeout for fetching a page, in seconds
+    #[serde(default = "default_fetch_timeout")]
+    timeout_secs: u64,
+    /// How long fetched pages are reused before fetching again, in seconds;
+    /// 0 disables the cache
+    #[serde(default = "default_cache_ttl")]
+    cache_ttl_secs: u64,
+    /// How much of a page to extract when adding a link
+    #[serde(default)]
+    mode: FetchMode,
+    /// How to retry fetches that fail for transient reasons
+    #[serde(default)]
+    retry: RetryConfig,
+    /// The most of a page to download, in bytes; longer pages are cut short
+    #[serde(default = "default_max_download_bytes")]
+    max_download_bytes: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct RetryConfig {
+    /// How many times to retry after a dropped connection, timeout, 429, or
+    /// 502-504 response; 0 disables retries
+    #[serde(default = "default_max_retries")]
+    max_retries: u8,
+    /// Delay before the first retry, in milliseconds; it doubles each time
+    #[serde(default = "default_retry_delay")]
+    base_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_retries: default_max_retries(),
+            base_delay_ms: default_retry_delay(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct UiConfig {
+    /// How to draw tables; when unset, lists get borders only and single
+    /// items get a full grid
+    table_style: Option<TableStyle>,
+    /// Color tags in tables by namespace (the part before any `:`); NO_COLOR
+    /// or --no-color turns this off
+    #[serde(default)]
+    tag_colors: bool,
+    /// How dates are shown in tables, as a strftime format (e.g. "%F %T" or
+    /// "%d %b %Y")
+    #[serde(default = "default_date_format")]
+    date_format: String,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        UiConfig {
+            table_style: None,
+            tag_colors: false,
+            date_format: default_date_format(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct AddConfig {
+    /// How to capitalize titles taken from pages; titles given with --title
+    /// are always kept as they are
+    #[serde(default)]
+    title_case: TitleCase,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum TitleCase {
+    /// Keep the page's capitalization
+    #[default]
+    Preserve,
+    /// Capitalize each word except short articles, conjunctions, and
+    /// prepositions
+    Title,
+    /// Capitalize only the first word
+    Sentence,
+}
+
+impl TitleCase {
+    fn apply(&self, title: &str) -> String {
+        match self {
+            TitleCase::Preserve => title.to_string(),
+            TitleCase::Title => util::to_title_case(title),
+            TitleCase::Sentence => util::to_sentence_case(title),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum TableStyle {
+    /// Box-drawing characters with lines between every cell
+    Utf8Full,
+    /// Box-drawing characters around the table and under the header
+    Utf8Borders,
+    /// Plain ASCII lines between every cell
+    Ascii,
+    /// No lines at all, just aligned columns
+    Minimal,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum FetchMode {
+    /// Run readability over the page, storing its text for search
+    #[default]
+    Full,
+    /// Only read the page's title and meta description
+    Title,
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        FetchConfig {
+            timeout_secs: default_fetch_timeout(),
+            cache_ttl_secs: default_cache_ttl(),
+            mode: FetchMode::default(),
+            retry: RetryConfig::default(),
+            max_download_bytes: default_max_download_bytes(),
+        }
+    }
+}
+
+impl FetchConfig {
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    // Pages are cut to exactly the limit (give or take a split character),
+    // so one that reaches it was almost certainly cut short. Checking the
+    // length rather than remembering also works for cached pages.
+    fn is_truncated(&self, html: &str) -> bool {
+        html.len() as u64 >= self.max_download_bytes
+    }
 }
 
 impl Config {
@@ -58,25 +300,76 @@ impl Default for Config {
     fn default() -> Self {
         Config {
             database: default_db_location(),
+            fetch: FetchConfig::default(),
+            ui: UiConfig::default(),
+            add: AddConfig::default(),
+            note_templates: vec![],
         }
     }
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 struct Link {
     id: TableId,
     url: Url,
     title: Option<String>,
     description: Option<String>,
+    // Article text can be enormous, so it is never included in serialized output.
+    #[serde(skip)]
     content: Option<String>,
     is_primary: bool,
     created_at: Timestamp,
     modified_at: Timestamp,
+    archived_at: Option<Timestamp>,
+    read_at: Option<Timestamp>,
+    is_favorite: bool,
+    // Saved to read later, rather than to keep; cleared once it's read.
+    is_reading_list: bool,
+    content_sha256: Option<String>,
+    pinned_order: Option<i64>,
+    // Identifiers and such from the link's source, e.g. {"doi": "10.1000/xyz"}.
+    meta: Option<serde_json::Value>,
+    // How the link was saved, e.g. "manual", "ingest", or "import-wallabag".
+    source: Option<String>,
+    // Where `link archive-to` last saved the page.
+    local_archive_path: Option<String>,
+}
+
+/// Well-known ways one link can relate to another. Stored in the database as
+/// the lowercase name; rows written before these existed may hold anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum RelationshipType {
+    /// Where the primary link was found
+    Via,
+    /// Discussion of the primary link, such as a comment thread
+    Discussion,
+    /// A copy of the primary link hosted elsewhere
+    Mirror,
+    /// The original that the primary link is based on
+    Source,
+    /// An archived snapshot of the primary link
+    Archive,
+    /// Anything else worth following up
+    SeeAlso,
+}
+
+impl RelationshipType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RelationshipType::Via => "via",
+            RelationshipType::Discussion => "discussion",
+            RelationshipType::Mirror => "mirror",
+            RelationshipType::Source => "source",
+            RelationshipType::Archive => "archive",
+            RelationshipType::SeeAlso => "see-also",
+        }
+    }
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 struct Note {
     id: TableId,
     content: String,
@@ -86,20 +379,203 @@ struct Note {
     modified_at: Timestamp,
 }
 
+// A row of the operation log, which `undo` reverses and `history` lists.
+#[derive(Debug, Serialize)]
+struct Operation {
+    id: TableId,
+    operation: String,
+    item_id: TableId,
+    item_type: String,
+    // The state before the operation, for undoing it.
+    payload: Option<serde_json::Value>,
+    created_at: Timestamp,
+}
+
+impl Operation {
+    // The URL or title of the item, for messages.
+    fn label(&self) -> String {
+        let payload = self.payload.as_ref();
+        payload
+            .and_then(|p| p.get("url").or_else(|| p.get("title")))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+// Enough of a removed link to put it back with `undo`; it gets a new ID.
+#[derive(Debug, Deserialize, Serialize)]
+struct LinkSnapshot {
+    url: String,
+    title: Option<String>,
+    description: Option<String>,
+    content: Option<String>,
+    created_at: Timestamp,
+    read_at: Option<Timestamp>,
+    is_favorite: bool,
+    // Missing from operations logged before the reading list existed.
+    #[serde(default)]
+    is_reading_list: bool,
+    #[serde(default)]
+    local_archive_path: Option<String>,
+    meta: Option<serde_json::Value>,
+    source: Option<String>,
+    tags: Vec<String>,
+    note: Option<NoteSnapshot>,
+    related_links: Vec<(String, Option<String>)>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct NoteSnapshot {
+    title: String,
+    content: String,
+    created_at: Timestamp,
+    tags: Vec<String>,
+    // The link the note was attached to, if any.
+    link_url: Option<String>,
+}
+
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 struct Tag {
     id: TableId,
     name: String,
     slug: String,
+    description: Option<String>,
+    parent_id: Option<TableId>,
+    created_at: Timestamp,
+    modified_at: Timestamp,
+}
+
+// A row of the related_link table, with both ends resolved to URLs.
+#[derive(Debug, Clone, Serialize)]
+struct RelatedLink {
+    url: Url,
+    relation: Option<String>,
+    primary_url: Url,
+}
+
+// A link with everything an export might want to show alongside it.
+struct ExportItem {
+    link: Link,
+    tags: Vec<Tag>,
+    note: Option<Note>,
+    related_links: Vec<(String, Option<String>)>,
+}
+
+// Front matter for notes exported as Markdown files.
+#[derive(Debug, Serialize)]
+struct NoteFrontMatter<'a> {
+    title: &'a str,
+    tags: Vec<&'a str>,
     created_at: Timestamp,
     modified_at: Timestamp,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    link: Option<&'a str>,
+}
+
+// What `note import` reads from a file's front matter. `link` is what
+// `note export` writes; `url` is more common elsewhere.
+#[derive(Debug, Default, Deserialize)]
+struct ImportedNoteFrontMatter {
+    title: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(alias = "link")]
+    url: Option<String>,
+    created_at: Option<String>,
+}
+
+// Serializable views used by the JSON and YAML output formats.
+#[derive(Debug, Serialize)]
+struct LinkDetail<'a> {
+    #[serde(flatten)]
+    link: &'a Link,
+    tags: Vec<&'a str>,
+    note: Option<NoteDetail<'a>>,
+    related_links: Vec<RelatedLinkDetail<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct LinkBrief<'a> {
+    id: TableId,
+    url: &'a Url,
+    title: Option<&'a str>,
+    tags: Vec<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct NoteDetail<'a> {
+    #[serde(flatten)]
+    note: &'a Note,
+    word_count: usize,
+    char_count: usize,
+    line_count: usize,
+}
+
+impl<'a> NoteDetail<'a> {
+    fn new(note: &'a Note) -> Self {
+        let stats = util::text_stats(note.content.trim());
+        NoteDetail {
+            note,
+            word_count: stats.words,
+            char_count: stats.chars,
+            line_count: stats.lines,
+        }
+    }
+}
+
+// `show --expand-related` output, where related links are full link objects.
+#[derive(Debug, Serialize)]
+struct ExpandedLinkDetail<'a> {
+    #[serde(flatten)]
+    link: &'a Link,
+    tags: Vec<&'a str>,
+    note: Option<NoteDetail<'a>>,
+    related_links: Vec<ExpandedRelatedLink<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct ExpandedRelatedLink<'a> {
+    #[serde(flatten)]
+    link: &'a Link,
+    relation: Option<&'a str>,
+    tags: Vec<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct NoteShowDetail<'a> {
+    #[serde(flatten)]
+    note: NoteDetail<'a>,
+    tags: Vec<&'a str>,
+    link: Option<&'a Link>,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportDetail<'a> {
+    links: Vec<LinkDetail<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<Vec<NoteDetail<'a>>>,
+}
+
+#[derive(Debug, Serialize)]
+struct RelatedLinkDetail<'a> {
+    url: &'a str,
+    relation: Option<&'a str>,
+    // Only filled in by `show --all-related`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    related_links: Option<Vec<RelatedLinkDetail<'a>>>,
 }
 
 #[derive(Parser, Debug, Default)]
 struct AddArgs {
-    /// The URL to add
-    link: String,
+    /// The URL to add; DOIs (doi:10.1000/xyz or https://doi.org/10.1000/xyz)
+    /// are resolved to the publisher's URL
+    #[arg(required_unless_present = "doi")]
+    link: Option<String>,
+    /// Add the article with this DOI
+    #[arg(long, conflicts_with = "link")]
+    doi: Option<String>,
     /// Tag for the link; multiple are allowed
     #[arg(short, long, num_args = 1..)]
     tag: Vec<String>,
@@ -115,120 +591,1024 @@ struct AddArgs {
     /// Add a short note directly from the command line
     #[arg(short, long, conflicts_with = "note")]
     message: Option<String>,
+    /// Put the link on the reading list, to read later
+    #[arg(short, long)]
+    save_for_later: bool,
     /// An optional related link (such as discussion of the primary link, or the
-    /// site where the link was found)
+    /// site where the link was found); multiple are allowed
+    #[arg(long, num_args = 1..)]
+    related_link: Vec<String>,
+    /// How each related link relates to this one, given in the same order as
+    /// the related links
+    #[arg(long, num_args = 1.., requires = "related_link")]
+    relation: Vec<RelationshipType>,
+    /// Timeout for fetching the link, in seconds (overrides the config file)
     #[arg(long)]
-    related_link: Option<String>,
-    /// Optional context for the related link (e.g. "via" or "lobsters")
-    #[arg(long, requires = "related_link")]
-    relation: Option<String>,
+    timeout: Option<u64>,
+    /// The most of the page to download, in bytes (overrides the config file)
+    #[arg(long)]
+    max_content_bytes: Option<u64>,
+    /// Don't fetch the page; only the given title and description are stored
+    #[arg(long)]
+    no_fetch: bool,
+    /// Fetch only the page's title and description, without storing its text
+    /// (overrides the config file)
+    #[arg(long, conflicts_with = "no_fetch")]
+    title_only_fetch: bool,
+    /// Use this plain-text file as the article text instead of fetching the
+    /// page; without --title, its first non-empty line is the title
+    #[arg(long, conflicts_with = "title_only_fetch")]
+    content_file: Option<PathBuf>,
+    /// Like --content-file, but read the article text from stdin
+    #[arg(long, conflicts_with_all = ["content_file", "title_only_fetch"])]
+    content_stdin: bool,
+    /// Creation date to record instead of now, for imports from elsewhere
+    #[arg(long, hide = true, value_parser = util::parse_date)]
+    created_at: Option<Timestamp>,
+    // Set by imports that carry the page itself, which is used instead of
+    // fetching it.
+    #[arg(skip)]
+    html: Option<String>,
+    // Set by imports that carry starred or favorite flags.
+    #[arg(skip)]
+    favorite: bool,
+    // Where the link came from, if not added by hand.
+    #[arg(skip)]
+    source: Option<String>,
 }
 
-#[derive(Parser, Debug, Default)]
-struct ListArgs {
-    /// Format of the output
-    #[arg(long, value_enum, default_value_t=ListOutputFormat::Table)]
-    format: ListOutputFormat,
-    /// Show only links matching one or more tags
-    #[arg(short, long, num_args = 1..)]
+impl AddArgs {
+    // What was asked for, for messages; the stored URL may differ.
+    fn target(&self) -> String {
+        match (&self.link, &self.doi) {
+            (Some(link), _) => link.clone(),
+            (None, Some(doi)) => format!("doi:{doi}"),
+            (None, None) => "".to_string(),
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+struct LinkAddFileArgs {
+    /// The local file to index; PDFs have their text extracted, anything else
+    /// is read as plain text
+    path: PathBuf,
+    /// URL to save the file under (defaults to its file:// URL)
+    #[arg(long)]
+    url: Option<String>,
+    /// Title for the link (defaults to the file name)
+    #[arg(long)]
+    title: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+struct LinkArchiveArgs {
+    /// The link to archive
+    link: String,
+}
+
+#[derive(Parser, Debug)]
+struct LinkArchiveToArgs {
+    /// The link to download
+    link: String,
+    /// The file to save the page to
+    path: PathBuf,
+    /// Replace the file, or the link's earlier archive, if there is one
+    #[arg(long)]
+    overwrite: bool,
+}
+
+#[derive(Parser, Debug)]
+struct LinkBulkTagArgs {
+    /// File with one URL per line; blank lines and lines starting with `#`
+    /// are skipped
+    file: PathBuf,
+    /// Tags to add to each link
+    #[arg(required = true)]
     tag: Vec<String>,
+    /// Save URLs that aren't saved yet (without fetching them), rather than
+    /// skipping them
+    #[arg(long)]
+    create_missing: bool,
 }
 
-#[derive(Parser, Debug, Default)]
-struct NoteArgs {
-    /// Tag for the note; multiple are allowed
+#[derive(Parser, Debug)]
+struct LinkImportArchiveOrgArgs {
+    /// The (probably dead) URL to save; if it's already saved, its text is
+    /// replaced
+    url: String,
+    /// Tag for the link; multiple are allowed
     #[arg(short, long, num_args = 1..)]
     tag: Vec<String>,
-    /// Title for the note
+}
+
+#[derive(Parser, Debug)]
+struct LinkExportOpmlArgs {
+    #[clap(flatten)]
+    filter: LinkFilterArgs,
+}
+
+#[derive(Parser, Debug)]
+struct LinkCopyArgs {
+    /// The link to copy
+    link: String,
+    /// Copy the link's title instead of its URL
     #[arg(long)]
-    title: Option<String>,
-    /// Add a short note directly from the command line
-    #[arg(short, long)]
-    message: Option<String>,
+    title: bool,
 }
 
-#[derive(Parser, Debug, Default)]
-struct RemoveArgs {
-    /// The note or link to remove
-    item: String,
+#[derive(Parser, Debug)]
+struct LinkShowContentArgs {
+    /// The link whose content to print
+    link: String,
 }
 
-#[derive(Parser, Debug, Default)]
-struct SearchArgs {
-    /// The term to search
-    term: String,
-    /// Format of the output
-    #[arg(long, value_enum, default_value_t=ListOutputFormat::Table)]
-    format: ListOutputFormat,
+#[derive(Parser, Debug)]
+struct LinkFavoriteArgs {
+    /// The link to favorite or unfavorite
+    link: String,
 }
 
-#[derive(Parser, Debug, Default)]
-struct ShowArgs {
-    /// The link or note to display in detail
-    term: String,
-    /// Format of the output
-    #[arg(long, value_enum, default_value_t=ListOutputFormat::Table)]
-    format: ListOutputFormat,
+#[derive(Parser, Debug)]
+struct LinkPinArgs {
+    /// The link to pin
+    link: String,
+    /// Where to pin the link; lower positions come first (defaults to after
+    /// every other pinned link)
+    #[arg(long)]
+    position: Option<i64>,
 }
 
 #[derive(Parser, Debug)]
-struct UpdateArgs {
-    /// The link to update
+struct LinkUnpinArgs {
+    /// The link to unpin
     link: String,
-    // Subcommand
-    #[command(subcommand)]
-    command: UpdateCommands,
 }
 
 #[derive(Parser, Debug)]
-struct UpdateAddRelatedLinkArgs {
-    /// The new related link
-    related_link: String,
-    #[arg(long, requires = "related_link")]
-    relation: Option<String>,
+struct LinkReadArgs {
+    /// The link to mark
+    link: String,
 }
 
 #[derive(Parser, Debug)]
-struct UpdateAddTagArgs {
-    /// The tag or tags to add
-    #[arg(num_args = 1..)]
-    tags: Vec<String>,
+struct LinkRelateArgs {
+    /// The link to relate from
+    primary: String,
+    /// The URL to relate to; saved as a secondary link if it isn't stored yet
+    related: String,
+    /// How the related link relates to the primary one
+    #[arg(long)]
+    relation: Option<RelationshipType>,
 }
 
 #[derive(Parser, Debug)]
-struct UpdateRefreshArgs {}
+struct LinkUnrelateArgs {
+    /// The link to remove the relationship from
+    primary: String,
+    /// The related link
+    related: String,
+}
 
 #[derive(Parser, Debug)]
-struct UpdateRemoveRelatedLinkArgs {
-    /// The related link to remove
-    related_link: String,
+struct LinkRelationsArgs {
+    /// The link to show relationships for
+    link: String,
+    /// Only show relationships of this type
+    #[arg(long)]
+    relation: Option<RelationshipType>,
+    /// Format of the output
+    #[arg(long, value_enum, default_value_t=ListOutputFormat::Table)]
+    format: ListOutputFormat,
 }
 
 #[derive(Parser, Debug)]
-struct UpdateRemoveTagArgs {
-    /// The tag or tags to add
-    #[arg(num_args = 1..)]
-    tags: Vec<String>,
+struct LinkRemoveArgs {
+    /// The link to remove
+    link: String,
 }
 
-#[derive(Debug, Subcommand)]
-enum Commands {
-    /// Add a link
-    Add {
-        #[clap(flatten)]
-        add_args: AddArgs,
-    },
-    /// Show all links
-    #[clap(alias = "ls")]
+#[derive(Parser, Debug)]
+struct LinkRestoreArgs {
+    /// The archived link to restore
+    link: String,
+}
+
+// Filters shared by every command that operates on a set of links.
+#[derive(Parser, Debug, Default)]
+struct LinkFilterArgs {
+    /// Only include links matching one or more tags
+    #[arg(short, long, num_args = 1..)]
+    tag: Vec<String>,
+    /// Also match links tagged with any descendant of the given tags
+    #[arg(long, requires = "tag")]
+    include_subtags: bool,
+    /// Only include links with every one of the tags, rather than any of them
+    #[arg(long, requires = "tag", conflicts_with = "include_subtags")]
+    match_all_tags: bool,
+    #[clap(flatten)]
+    dates: DateRangeArgs,
+    /// Only include archived links
+    #[arg(long)]
+    archived: bool,
+    /// Only include links added after the link with this ID
+    #[arg(long)]
+    since_id: Option<TableId>,
+    /// Only include links that have been read
+    #[arg(long, conflicts_with = "unread")]
+    read: bool,
+    /// Only include links that haven't been read yet
+    #[arg(long)]
+    unread: bool,
+    /// Only include favorite links
+    #[arg(long)]
+    favorites: bool,
+    /// Only include links on the reading list
+    #[arg(long)]
+    reading_list: bool,
+    /// Only include links on this host (e.g. example.com)
+    #[arg(long)]
+    domain: Option<String>,
+    /// Only include links saved this way (manual, ingest, import-toml,
+    /// import-wallabag, or import-browser)
+    #[arg(long)]
+    source: Option<String>,
+}
+
+impl LinkFilterArgs {
+    fn tag_slugs(&self) -> Result<Vec<String>> {
+        self.tag.iter().map(|t| util::slugify(t)).collect()
+    }
+
+    fn link_filter(&self) -> db::LinkFilter {
+        db::LinkFilter {
+            archived: self.archived,
+            after_id: self.since_id,
+            read: match (self.read, self.unread) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            },
+            favorites_only: self.favorites,
+            reading_list_only: self.reading_list,
+            include_subtags: self.include_subtags,
+            match_all_tags: self.match_all_tags,
+            domain: self.domain.as_ref().map(|d| d.to_lowercase()),
+            source: self.source.clone(),
+            ..self.dates.link_filter()
+        }
+    }
+}
+
+#[derive(Parser, Clone, Debug, Default)]
+struct DateRangeArgs {
+    /// Only include links added before this date (e.g. 2024-06-01)
+    #[arg(long, value_parser = util::parse_date)]
+    before: Option<Timestamp>,
+    /// Only include links added on or after this date (e.g. 2024-06-01)
+    #[arg(long, value_parser = util::parse_date)]
+    after: Option<Timestamp>,
+    /// Only include links added within this long ago (e.g. "2 weeks" or 30d)
+    #[arg(long, value_parser = util::parse_since, conflicts_with = "after")]
+    since: Option<Timestamp>,
+}
+
+impl DateRangeArgs {
+    fn link_filter(&self) -> db::LinkFilter {
+        db::LinkFilter {
+            after: self.since.or(self.after),
+            before: self.before,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+struct LinkTagArgs {
+    /// The link to tag or untag
+    link: String,
+    /// The tag or tags to add or remove
+    #[arg(num_args = 1.., required = true)]
+    tags: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+struct LinkUpdateUrlArgs {
+    /// The link's current URL
+    old_url: String,
+    /// The URL to change it to
+    new_url: String,
+    /// Re-fetch the title, description, and content from the new URL
+    #[arg(long)]
+    fetch: bool,
+    /// If the new URL is already stored, merge this link into it
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+enum ExportFormat {
+    /// One row per link, for spreadsheets
+    Csv,
+    /// A self-contained, searchable HTML page
+    Html,
+    /// Links with their tags and related links, for backups
+    Json,
+    /// A Markdown document with a section per link
+    Markdown,
+    /// An Org mode document with a heading per link
+    Org,
+    /// A commented example file for `import --format toml`
+    TomlTemplate,
+    /// Links with their tags and related links, for backups
+    Yaml,
+}
+
+#[derive(Clone, Debug, Default, ValueEnum)]
+enum NoteShowFormat {
+    #[default]
+    Table,
+    Json,
+    Yaml,
+    /// The note as a Markdown document with YAML front matter
+    Markdown,
+}
+
+#[derive(Parser, Debug)]
+struct CheckArgs {
+    #[clap(flatten)]
+    filter: LinkFilterArgs,
+    /// Print each result as soon as it's checked, rather than at the end
+    #[arg(short, long)]
+    verbose: bool,
+    /// Replace the text of dead links with the Wayback Machine's closest
+    /// snapshot
+    #[arg(long)]
+    archive_org: bool,
+}
+
+#[derive(Parser, Debug)]
+struct DbRepairArgs {
+    /// Also delete tags that label nothing and have no child tags, such as
+    /// those created by `tags import` and not yet used
+    #[arg(long)]
+    unused_tags: bool,
+}
+
+#[derive(Parser, Debug)]
+struct ExportArgs {
+    /// Format of the export
+    #[arg(long, value_enum)]
+    format: ExportFormat,
+    #[clap(flatten)]
+    filter: LinkFilterArgs,
+    /// Include each link's note, and (except in CSV and HTML) standalone notes
+    #[arg(long)]
+    include_notes: bool,
+    /// Add a QR code of each link's URL, shown on click (HTML only)
+    #[arg(long)]
+    qr_codes: bool,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+enum ImportFormat {
+    /// A CSV file; see the --*-col options for how its columns are read
+    Csv,
+    /// A list of links; see `export --format toml-template`
+    Toml,
+    /// A Wallabag JSON export
+    Wallabag,
+}
+
+#[derive(Parser, Debug)]
+struct ImportArgs {
+    /// The file to import
+    file: PathBuf,
+    /// Format of the file
+    #[arg(long, value_enum)]
+    format: ImportFormat,
+    /// Don't fetch the pages; only the given titles and descriptions are stored
+    #[arg(long)]
+    no_fetch: bool,
+    /// Import archived entries too (Wallabag only)
+    #[arg(long)]
+    include_archived: bool,
+    #[clap(flatten)]
+    csv: CsvImportArgs,
+}
+
+// Which CSV columns hold what. Columns are named by header, or with
+// --no-header, numbered from 1; columns that aren't mapped are ignored.
+#[derive(Parser, Debug)]
+#[command(next_help_heading = "CSV columns")]
+struct CsvImportArgs {
+    /// Column with the URL [default: url]
+    #[arg(long)]
+    url_col: Option<String>,
+    /// Column with the title
+    #[arg(long)]
+    title_col: Option<String>,
+    /// Column with the description
+    #[arg(long)]
+    description_col: Option<String>,
+    /// Column with the tags, separated by --tags-sep
+    #[arg(long)]
+    tags_col: Option<String>,
+    /// What separates tags in the tags column [default: ,]
+    #[arg(long, value_parser = clap::builder::NonEmptyStringValueParser::new())]
+    tags_sep: Option<String>,
+    /// Column with the date the link was saved
+    #[arg(long)]
+    created_at_col: Option<String>,
+    /// The first row is data, not headers; columns are given by number
+    #[arg(long)]
+    no_header: bool,
+}
+
+impl CsvImportArgs {
+    fn is_set(&self) -> bool {
+        self.url_col.is_some()
+            || self.title_col.is_some()
+            || self.description_col.is_some()
+            || self.tags_col.is_some()
+            || self.tags_sep.is_some()
+            || self.created_at_col.is_some()
+            || self.no_header
+    }
+}
+
+// The `import --format toml` file; the fields mirror those of `AddArgs`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ImportFile {
+    #[serde(default)]
+    links: Vec<ImportLink>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ImportLink {
+    url: String,
+    title: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    note: Option<String>,
+    #[serde(default)]
+    related_links: Vec<String>,
+    #[serde(default)]
+    relations: Vec<RelationshipType>,
+}
+
+// An entry of a Wallabag JSON export; other fields are ignored.
+#[derive(Debug, Deserialize)]
+struct WallabagEntry {
+    url: String,
+    title: Option<String>,
+    // The saved article, as HTML.
+    content: Option<String>,
+    created_at: Option<String>,
+    #[serde(default)]
+    tags: Vec<WallabagTag>,
+    #[serde(default)]
+    is_archived: WallabagFlag,
+    #[serde(default)]
+    is_starred: WallabagFlag,
+}
+
+#[derive(Debug, Deserialize)]
+struct WallabagTag {
+    label: String,
+}
+
+// Wallabag has written these flags both as booleans and as 0 or 1.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum WallabagFlag {
+    Bool(bool),
+    Int(i64),
+}
+
+impl Default for WallabagFlag {
+    fn default() -> Self {
+        WallabagFlag::Bool(false)
+    }
+}
+
+impl WallabagFlag {
+    fn is_set(&self) -> bool {
+        match self {
+            WallabagFlag::Bool(b) => *b,
+            WallabagFlag::Int(i) => *i != 0,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+struct IngestArgs {
+    /// Tag for every ingested link; multiple are allowed
+    #[arg(short, long, num_args = 1..)]
+    tag: Vec<String>,
+    /// Don't fetch the pages; only the URLs are stored
+    #[arg(long)]
+    no_fetch: bool,
+    /// Stop at the first URL that can't be added
+    #[arg(long)]
+    fail_fast: bool,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Browser {
+    Chrome,
+    Firefox,
+}
+
+// Chrome counts microseconds from 1601-01-01; Firefox from the Unix epoch.
+const CHROME_EPOCH_OFFSET_MICROS: i64 = 11_644_473_600_000_000;
+
+impl Browser {
+    fn history_file(&self) -> &'static str {
+        match self {
+            Browser::Chrome => "History",
+            Browser::Firefox => "places.sqlite",
+        }
+    }
+
+    fn history_query(&self) -> &'static str {
+        match self {
+            Browser::Chrome => {
+                "SELECT url, title, last_visit_time FROM urls
+                WHERE last_visit_time >= ?
+                ORDER BY last_visit_time DESC LIMIT 1000"
+            }
+            Browser::Firefox => {
+                "SELECT url, title, last_visit_date FROM moz_places
+                WHERE last_visit_date >= ?
+                ORDER BY last_visit_date DESC LIMIT 1000"
+            }
+        }
+    }
+
+    fn visit_time(&self, value: i64) -> Option<Timestamp> {
+        if value <= 0 {
+            return None;
+        }
+        let micros = match self {
+            Browser::Chrome => value - CHROME_EPOCH_OFFSET_MICROS,
+            Browser::Firefox => value,
+        };
+        Timestamp::from_microsecond(micros).ok()
+    }
+
+    fn history_time(&self, timestamp: Timestamp) -> i64 {
+        let micros = timestamp.as_microsecond();
+        match self {
+            Browser::Chrome => micros + CHROME_EPOCH_OFFSET_MICROS,
+            Browser::Firefox => micros,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+struct BrowserHistoryArgs {
+    /// The browser the history comes from
+    #[arg(long, value_enum)]
+    browser: Browser,
+    /// The browser profile directory, or the history database itself
+    #[arg(long)]
+    profile: PathBuf,
+    /// Only import pages visited in the last this many days
+    #[arg(long)]
+    since_days: Option<u32>,
+}
+
+#[derive(Parser, Debug, Default)]
+struct ListArgs {
+    /// Format of the output
+    #[arg(long, value_enum, default_value_t=LinkListFormat::Table)]
+    format: LinkListFormat,
+    /// Print only the number of matching links
+    #[arg(long)]
+    count: bool,
+    /// List favorite links before all others
+    #[arg(long)]
+    favorites_first: bool,
+    /// List pinned links, in pinned order, before all others
+    #[arg(long)]
+    pinned_first: bool,
+    /// Also list links that were only ever saved as related links
+    #[arg(long, conflicts_with = "archived")]
+    include_secondary: bool,
+    /// Comma-separated columns to show in the table
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values = ["url", "title", "created"]
+    )]
+    columns: Vec<ListColumn>,
+    /// Show descriptions as well, with each text column cut to a fixed length
+    /// (table output only)
+    #[arg(long, conflicts_with = "columns")]
+    wide: bool,
+    /// Show links in groups, each under its own heading
+    #[arg(long, value_enum)]
+    group_by: Option<GroupBy>,
+    #[clap(flatten)]
+    filter: LinkFilterArgs,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum GroupBy {
+    /// The host in the link's URL
+    Domain,
+    /// The link's first tag, by slug
+    Tag,
+    /// The year and month the link was added
+    Month,
+}
+
+#[derive(Parser, Debug, Default)]
+struct NoteArgs {
+    /// Tag for the note; multiple are allowed
+    #[arg(short, long, num_args = 1..)]
+    tag: Vec<String>,
+    /// Title for the note
+    #[arg(long)]
+    title: Option<String>,
+    /// Add a short note directly from the command line
+    #[arg(short, long)]
+    message: Option<String>,
+    /// Start a new note from a template in the config file
+    #[arg(long, conflicts_with = "message")]
+    template: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+struct NoteDeleteArgs {
+    /// The title of the note to delete
+    title: String,
+}
+
+#[derive(Parser, Debug)]
+struct NoteEditArgs {
+    /// The title of the note to edit
+    title: String,
+}
+
+#[derive(Parser, Debug)]
+struct NoteExportArgs {
+    /// Directory to write the Markdown files to
+    #[arg(long, default_value = ".")]
+    output_dir: PathBuf,
+    /// Only export notes matching one or more tags
+    #[arg(short, long, num_args = 1..)]
+    tag: Vec<String>,
+    /// Overwrite files that already exist with different contents
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Parser, Debug)]
+struct NoteImportArgs {
+    /// Directory of .md files, such as an Obsidian vault
+    dir: PathBuf,
+    /// Also import files in subdirectories (other than hidden ones)
+    #[arg(short, long)]
+    recursive: bool,
+}
+
+#[derive(Parser, Debug)]
+struct NoteShowArgs {
+    /// The title of the note to show
+    title: String,
+    /// Format of the output
+    #[arg(long, value_enum, default_value_t=NoteShowFormat::Table)]
+    format: NoteShowFormat,
+}
+
+#[derive(Parser, Debug)]
+struct NoteTagArgs {
+    /// The title of the note to tag or untag
+    title: String,
+    /// The tag or tags to add or remove
+    #[arg(num_args = 1.., required = true)]
+    tags: Vec<String>,
+}
+
+#[derive(Parser, Debug, Default)]
+struct NoteListArgs {
+    /// Format of the output
+    #[arg(long, value_enum, default_value_t=ListOutputFormat::Table)]
+    format: ListOutputFormat,
+    /// Show only notes matching one or more tags
+    #[arg(short, long, num_args = 1..)]
+    tag: Vec<String>,
+    /// Show only notes attached to a link, along with the link's title
+    #[arg(long, conflicts_with = "standalone")]
+    linked: bool,
+    /// Show only notes that aren't attached to a link
+    #[arg(long)]
+    standalone: bool,
+}
+
+#[derive(Parser, Debug)]
+struct HistoryArgs {
+    /// Format of the output
+    #[arg(long, value_enum, default_value_t=ListOutputFormat::Table)]
+    format: ListOutputFormat,
+    /// How many operations to show, newest first
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+}
+
+#[derive(Parser, Debug)]
+struct TagsImportArgs {
+    /// The file to read; blank lines and lines starting with `#` are skipped
+    file: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+struct TagsListArgs {
+    /// Format of the output
+    #[arg(long, value_enum, default_value_t=ListOutputFormat::Table)]
+    format: ListOutputFormat,
+}
+
+#[derive(Parser, Debug)]
+struct TagsOrphansArgs {
+    /// Delete the orphaned tags
+    #[arg(long)]
+    delete: bool,
+    /// Show which tags would be deleted without deleting them
+    #[arg(long, requires = "delete")]
+    dry_run: bool,
+}
+
+#[derive(Parser, Debug)]
+struct TagsShowArgs {
+    /// The tag to show, by name or slug
+    tag: String,
+    /// Format of the output
+    #[arg(long, value_enum, default_value_t=ListOutputFormat::Table)]
+    format: ListOutputFormat,
+}
+
+#[derive(Parser, Debug)]
+struct TagsSetParentArgs {
+    /// The tag to move, by name or slug
+    tag: String,
+    /// The new parent tag; omit to make the tag top-level
+    parent: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+struct TagsSetDescriptionArgs {
+    /// The tag to describe, by name or slug
+    tag: String,
+    /// The new description; an empty string clears it
+    description: String,
+}
+
+#[derive(Parser, Debug)]
+struct RelatedLinksArgs {
+    /// Only show links related to this primary link
+    #[arg(long = "for")]
+    primary: Option<String>,
+    /// Format of the output
+    #[arg(long, value_enum, default_value_t=ListOutputFormat::Table)]
+    format: ListOutputFormat,
+}
+
+#[derive(Parser, Debug, Default)]
+struct RemoveArgs {
+    /// The note or link to remove
+    #[arg(required_unless_present = "interactive")]
+    item: Option<String>,
+    /// Step through links one at a time, choosing which to delete
+    #[arg(short, long, conflicts_with = "item")]
+    interactive: bool,
+    /// If the item isn't found and several link titles match it, use the
+    /// oldest
+    #[arg(long, conflicts_with = "interactive")]
+    first: bool,
+    #[clap(flatten)]
+    filter: LinkFilterArgs,
+}
+
+#[derive(Parser, Debug, Default)]
+struct SearchArgs {
+    /// The term to search
+    #[arg(required_unless_present = "regex")]
+    term: Option<String>,
+    /// Format of the output
+    #[arg(long, value_enum, default_value_t=LinkListFormat::Table)]
+    format: LinkListFormat,
+    /// Match the term against link titles only, rather than full content
+    #[arg(long, conflicts_with = "all")]
+    title_only: bool,
+    /// Match the term against titles, descriptions, and URLs as well as content
+    #[arg(long)]
+    all: bool,
+    /// Print only the number of matches
+    #[arg(long)]
+    count: bool,
+    /// Search notes only, skipping links
+    #[arg(long, conflicts_with_all = ["links_only", "title_only", "all"])]
+    notes_only: bool,
+    /// Search links only, skipping notes
+    #[arg(long)]
+    links_only: bool,
+    /// Find links matching this regular expression instead of a term
+    #[arg(long, conflicts_with_all = ["term", "title_only", "all", "notes_only"])]
+    regex: Option<String>,
+    /// Match the regular expression against only this field, rather than the
+    /// URL, title, and description
+    #[arg(long, value_enum, requires = "regex")]
+    regex_field: Option<RegexField>,
+    /// How many links, and how many notes, to show per page
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+    /// Skip this many links, and this many notes, before the first shown
+    #[arg(long, conflicts_with = "page")]
+    offset: Option<usize>,
+    /// Show this page of links and notes (the first is 1)
+    #[arg(long)]
+    page: Option<usize>,
+    /// Add a column with each link's full-text relevance score
+    #[arg(short, long, conflicts_with_all = ["title_only", "all", "regex", "notes_only"])]
+    verbose: bool,
+    #[clap(flatten)]
+    dates: DateRangeArgs,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum RegexField {
+    Url,
+    Title,
+    Description,
+    /// The stored article text
+    Content,
+}
+
+#[derive(Parser, Debug)]
+struct ServeArgs {
+    /// Port to listen on
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+    /// Address to bind to
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+}
+
+#[derive(Parser, Debug, Default)]
+struct ShowArgs {
+    /// The link or note to display in detail
+    term: String,
+    /// Format of the output
+    #[arg(long, value_enum, default_value_t=ShowFormat::Table)]
+    format: ShowFormat,
+    /// If the URL isn't found and several link titles match it, use the oldest
+    #[arg(long)]
+    first: bool,
+    /// Show only the title, URL, date, and tags, on a single line (or, for
+    /// JSON, YAML, TSV, and CSV, only the ID, URL, title, and tags)
+    #[arg(long)]
+    brief: bool,
+    /// Also show the related links of related links, and so on
+    #[arg(long, conflicts_with = "brief")]
+    all_related: bool,
+    /// How many levels of related links --all-related follows
+    #[arg(long, default_value_t = 3, requires = "all_related")]
+    depth: usize,
+    /// Show the title, description, and tags of each related link
+    #[arg(long, conflicts_with_all = ["brief", "all_related"])]
+    expand_related: bool,
+}
+
+#[derive(Parser, Debug, Default)]
+struct WatchArgs {
+    /// Optional term to search for; otherwise all links are listed
+    term: Option<String>,
+    /// Number of seconds to wait between refreshes
+    #[arg(long, default_value_t = 5)]
+    interval: u64,
+    #[clap(flatten)]
+    list_args: ListArgs,
+}
+
+#[derive(Parser, Debug)]
+struct UpdateArgs {
+    /// The link to update
+    link: String,
+    // Subcommand
+    #[command(subcommand)]
+    command: UpdateCommands,
+}
+
+#[derive(Parser, Debug)]
+struct UpdateAddRelatedLinkArgs {
+    /// The new related link
+    related_link: String,
+    /// How the related link relates to this one
+    #[arg(long, requires = "related_link")]
+    relation: Option<RelationshipType>,
+}
+
+#[derive(Parser, Debug)]
+struct UpdateAddTagArgs {
+    /// The tag or tags to add
+    #[arg(num_args = 1..)]
+    tags: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+struct UpdateRefreshArgs {}
+
+#[derive(Parser, Debug)]
+struct UpdateRemoveRelatedLinkArgs {
+    /// The related link to remove
+    related_link: String,
+}
+
+#[derive(Parser, Debug)]
+struct UpdateRemoveTagArgs {
+    /// The tag or tags to add
+    #[arg(num_args = 1..)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Add a link
+    Add {
+        #[clap(flatten)]
+        add_args: AddArgs,
+    },
+    /// Check that links still resolve, reporting dead ones
+    Check {
+        #[clap(flatten)]
+        check_args: CheckArgs,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Inspect the database
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
+    /// Export all links to a file
+    Export {
+        #[clap(flatten)]
+        export_args: ExportArgs,
+    },
+    /// Show recent adds, notes, and removals, which `undo` can reverse
+    History {
+        #[clap(flatten)]
+        history_args: HistoryArgs,
+    },
+    /// Add links from a file
+    Import {
+        #[clap(flatten)]
+        import_args: ImportArgs,
+    },
+    /// Add links from stdin, one URL per line
+    Ingest {
+        #[clap(flatten)]
+        ingest_args: IngestArgs,
+    },
+    /// Show all links
+    #[clap(alias = "ls")]
     List {
         #[clap(flatten)]
         list_args: ListArgs,
     },
-    /// Add a freeform note
+    /// Manage links
+    Link {
+        #[command(subcommand)]
+        command: LinkCommands,
+    },
+    /// Add links from a Chrome or Firefox browsing history
+    MigrateFromBrowserHistory {
+        #[clap(flatten)]
+        history_args: BrowserHistoryArgs,
+    },
+    /// Add a freeform note, or manage existing notes
+    #[command(args_conflicts_with_subcommands = true)]
     Note {
+        #[command(subcommand)]
+        command: Option<NoteCommands>,
         #[clap(flatten)]
         note_args: NoteArgs,
     },
+    /// Show related links and the primary links they belong to
+    RelatedLinks {
+        #[clap(flatten)]
+        related_links_args: RelatedLinksArgs,
+    },
     /// Remove a link or note
     #[clap(alias = "rm")]
     Remove {
@@ -240,555 +1620,5487 @@ enum Commands {
         #[clap(flatten)]
         search_args: SearchArgs,
     },
+    /// Serve a read-only JSON API over HTTP
+    Serve {
+        #[clap(flatten)]
+        serve_args: ServeArgs,
+    },
     /// Show link details
     Show {
         #[clap(flatten)]
         show_args: ShowArgs,
     },
+    /// Show how many links are saved, on the reading list, and read
+    Stats,
+    /// List and describe tags
+    Tags {
+        #[command(subcommand)]
+        command: TagsCommands,
+    },
+    /// List the note templates in the config file
+    Templates {
+        #[command(subcommand)]
+        command: TemplatesCommands,
+    },
+    /// Reverse the most recent add, note, or removal
+    Undo,
     /// Update an existing link
     Update {
         #[clap(flatten)]
         update_args: UpdateArgs,
     },
+    /// Continuously re-run list or search
+    Watch {
+        #[clap(flatten)]
+        watch_args: WatchArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommands {
+    /// Print one setting's effective value, e.g. `fetch.timeout_secs`
+    Get {
+        #[clap(flatten)]
+        get_args: ConfigGetArgs,
+    },
+    /// Print the path of the config file in use
+    Path,
+    /// Change one setting in the config file, creating the file if needed
+    Set {
+        #[clap(flatten)]
+        set_args: ConfigSetArgs,
+    },
+    /// Print the effective configuration, including defaults and CLI overrides
+    Show,
+    /// Remove one setting from the config file, so that its default applies
+    Unset {
+        #[clap(flatten)]
+        unset_args: ConfigGetArgs,
+    },
+    /// Check the config file for unknown keys and invalid values
+    Validate,
+}
+
+#[derive(Parser, Debug)]
+struct ConfigGetArgs {
+    /// The setting, as a dotted path such as `ui.table_style`
+    key: String,
+}
+
+#[derive(Parser, Debug)]
+struct ConfigSetArgs {
+    /// The setting, as a dotted path such as `ui.table_style`
+    key: String,
+    /// The new value
+    value: String,
+}
+
+// The settings `config set` can change; note templates are left to the
+// config file itself.
+const CONFIG_KEYS: [&str; 11] = [
+    "database",
+    "fetch.timeout_secs",
+    "fetch.cache_ttl_secs",
+    "fetch.mode",
+    "fetch.max_download_bytes",
+    "fetch.retry.max_retries",
+    "fetch.retry.base_delay_ms",
+    "ui.table_style",
+    "ui.tag_colors",
+    "ui.date_format",
+    "add.title_case",
+];
+
+#[derive(Debug, Subcommand)]
+enum DbCommands {
+    /// Show the SQLite version, database size, schema version, and row counts
+    Info,
+    /// Delete rows left pointing at deleted links, notes, or tags
+    Repair {
+        #[clap(flatten)]
+        repair_args: DbRepairArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TagsCommands {
+    /// Create tags from a text file with one tag name per line
+    Import {
+        #[clap(flatten)]
+        import_args: TagsImportArgs,
+    },
+    /// Show all tags
+    #[clap(alias = "ls")]
+    List {
+        #[clap(flatten)]
+        list_args: TagsListArgs,
+    },
+    /// Show tags that aren't attached to any link or note
+    Orphans {
+        #[clap(flatten)]
+        orphans_args: TagsOrphansArgs,
+    },
+    /// Set or clear a tag's description
+    SetDescription {
+        #[clap(flatten)]
+        set_description_args: TagsSetDescriptionArgs,
+    },
+    /// Make a tag a child of another tag
+    SetParent {
+        #[clap(flatten)]
+        set_parent_args: TagsSetParentArgs,
+    },
+    /// Show a tag's details
+    Show {
+        #[clap(flatten)]
+        show_args: TagsShowArgs,
+    },
+    /// Show the tag hierarchy
+    Tree,
+}
+
+#[derive(Debug, Subcommand)]
+enum TemplatesCommands {
+    /// Show all configured note templates
+    #[clap(alias = "ls")]
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum LinkCommands {
+    /// Save a local file as a link, indexing its text content
+    #[clap(alias = "import-file")]
+    AddFile {
+        #[clap(flatten)]
+        add_file_args: LinkAddFileArgs,
+    },
+    /// Archive a link, hiding it from lists and searches without deleting it
+    Archive {
+        #[clap(flatten)]
+        archive_args: LinkArchiveArgs,
+    },
+    /// Download a link's page, byte for byte, to a local file
+    ArchiveTo {
+        #[clap(flatten)]
+        archive_to_args: LinkArchiveToArgs,
+    },
+    /// Add tags to every link listed in a file
+    BulkTag {
+        #[clap(flatten)]
+        bulk_tag_args: LinkBulkTagArgs,
+    },
+    /// Show how much article text is stored, and for which links
+    ContentStats,
+    /// Copy a link's URL to the clipboard
+    Copy {
+        #[clap(flatten)]
+        copy_args: LinkCopyArgs,
+    },
+    /// Write links as an OPML outline, for feed readers, grouped by first tag
+    ExportOpml {
+        #[clap(flatten)]
+        export_opml_args: LinkExportOpmlArgs,
+    },
+    /// Toggle whether a link is a favorite
+    Favorite {
+        #[clap(flatten)]
+        favorite_args: LinkFavoriteArgs,
+    },
+    /// Save a link with its text from the Wayback Machine's closest snapshot
+    ImportArchiveOrg {
+        #[clap(flatten)]
+        import_archive_org_args: LinkImportArchiveOrgArgs,
+    },
+    /// Pin a link, so that `list --pinned-first` shows it at the top
+    Pin {
+        #[clap(flatten)]
+        pin_args: LinkPinArgs,
+    },
+    /// Mark a link as read
+    Read {
+        #[clap(flatten)]
+        read_args: LinkReadArgs,
+    },
+    /// Relate a link to another URL, saving the other URL if needed
+    Relate {
+        #[clap(flatten)]
+        relate_args: LinkRelateArgs,
+    },
+    /// Show a link's outgoing and incoming relationships
+    Relations {
+        #[clap(flatten)]
+        relations_args: LinkRelationsArgs,
+    },
+    /// Remove a link, leaving any note with the same title alone
+    #[clap(alias = "rm")]
+    Remove {
+        #[clap(flatten)]
+        remove_args: LinkRemoveArgs,
+    },
+    /// Restore an archived link
+    Restore {
+        #[clap(flatten)]
+        restore_args: LinkRestoreArgs,
+    },
+    /// Print a link's stored article text, without any decoration
+    ShowContent {
+        #[clap(flatten)]
+        show_content_args: LinkShowContentArgs,
+    },
+    /// Add one or more tags to a link
+    Tag {
+        #[clap(flatten)]
+        tag_args: LinkTagArgs,
+    },
+    /// Unpin a pinned link
+    Unpin {
+        #[clap(flatten)]
+        unpin_args: LinkUnpinArgs,
+    },
+    /// Remove the relationship between two links
+    Unrelate {
+        #[clap(flatten)]
+        unrelate_args: LinkUnrelateArgs,
+    },
+    /// Remove one or more tags from a link
+    Untag {
+        #[clap(flatten)]
+        untag_args: LinkTagArgs,
+    },
+    /// Mark a link as unread
+    Unread {
+        #[clap(flatten)]
+        unread_args: LinkReadArgs,
+    },
+    /// Change the URL of an existing link
+    UpdateUrl {
+        #[clap(flatten)]
+        update_url_args: LinkUpdateUrlArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum NoteCommands {
+    /// Delete a note, leaving any link with the same URL alone
+    Delete {
+        #[clap(flatten)]
+        delete_args: NoteDeleteArgs,
+    },
+    /// Open an existing note in the editor
+    Edit {
+        #[clap(flatten)]
+        edit_args: NoteEditArgs,
+    },
+    /// Write each note to a Markdown file with YAML front matter
+    Export {
+        #[clap(flatten)]
+        export_args: NoteExportArgs,
+    },
+    /// Add each Markdown file in a directory as a note, reading its YAML
+    /// front matter for the title, tags, link, and creation date
+    Import {
+        #[clap(flatten)]
+        import_args: NoteImportArgs,
+    },
+    /// Show all notes
+    #[clap(alias = "ls")]
+    List {
+        #[clap(flatten)]
+        list_args: NoteListArgs,
+    },
+    /// Show a note's content and details
+    Show {
+        #[clap(flatten)]
+        show_args: NoteShowArgs,
+    },
+    /// Add one or more tags to a note
+    Tag {
+        #[clap(flatten)]
+        tag_args: NoteTagArgs,
+    },
+    /// Remove one or more tags from a note
+    Untag {
+        #[clap(flatten)]
+        untag_args: NoteTagArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum UpdateCommands {
+    Refresh {
+        #[clap(flatten)]
+        refresh_args: UpdateRefreshArgs,
+    },
+    #[clap(alias = "add-related")]
+    AddRelatedLink {
+        #[clap(flatten)]
+        add_related_link_args: UpdateAddRelatedLinkArgs,
+    },
+    #[clap(alias = "add-tags")]
+    AddTag {
+        #[clap(flatten)]
+        add_tag_args: UpdateAddTagArgs,
+    },
+    #[clap(alias = "remove-related")]
+    RemoveRelatedLink {
+        #[clap(flatten)]
+        remove_related_link_args: UpdateRemoveRelatedLinkArgs,
+    },
+    #[clap(alias = "remove-tags")]
+    RemoveTag {
+        #[clap(flatten)]
+        remove_tag_args: UpdateRemoveTagArgs,
+    },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut writer: Box<dyn Write> = match &cli.output {
+        Some(path) => {
+            let mut path = path.clone();
+            expand_tilde(&mut path);
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(cli.append)
+                .truncate(!cli.append)
+                .open(&path)
+                .with_context(|| format!("Unable to open {} for output", path.display()))?;
+            Box::new(file)
+        }
+        None => Box::new(std::io::stdout()),
+    };
+    let out = writer.as_mut();
+    let json_pretty = cli.json_pretty || (cli.output.is_none() && std::io::stdout().is_terminal());
+    if let Commands::Config { command } = &cli.command {
+        // Config commands must work even when the database can't be opened,
+        // and `validate` even when the config can't be loaded.
+        return config_cmd(&cli, command, out);
+    }
+    let (mut config, _) = load_config(&cli)?;
+    // Colors are only for terminals, and https://no-color.org says NO_COLOR
+    // with any value but empty turns them off.
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    if cli.no_color || no_color_env || cli.output.is_some() || !std::io::stdout().is_terminal() {
+        config.ui.tag_colors = false;
+    }
+    if let Commands::Templates { command } = &cli.command {
+        return templates_cmd(command, &config, out);
+    }
+    if let Commands::Db {
+        command: DbCommands::Info,
+    } = &cli.command
+    {
+        // Opened without migrating, so that older databases can be inspected
+        // as they are.
+        return db_info_cmd(&config, out);
+    }
+    if let Some(parent) = config.database.parent() {
+        std::fs::create_dir_all(parent).with_context(|| {
+            format!(
+                "Unable to create database at {}",
+                config.database.to_string_lossy()
+            )
+        })?;
+    }
+    let mut conn = Connection::open(&config.database)
+        .with_context(|| format!("Unable to open database at {:?}", &config.database))?;
+    db_migrations::migrate(&mut conn)
+        .with_context(|| format!("Unable to upgrade database at {:?}", &config.database))?;
+
+    if let Commands::Serve { serve_args } = &cli.command {
+        return serve_cmd(&config.database, serve_args, out).with_context(|| "Unable to serve");
+    }
+    let mut conn = Connection::open(&config.database)?;
+    db::create_url_host_function(&conn)?;
+    if let Commands::Watch { watch_args } = &cli.command {
+        // Watching outlives any single transaction, so it gets the connection.
+        return watch_cmd(&mut conn, watch_args, &config.ui)
+            .with_context(|| "Unable to watch items");
+    }
+    if let Commands::Import { import_args } = &cli.command {
+        return import_cmd(&mut conn, import_args, &config, out)
+            .with_context(|| format!("Unable to import {}", import_args.file.to_string_lossy()));
+    }
+    if let Commands::MigrateFromBrowserHistory { history_args } = &cli.command {
+        return migrate_from_browser_history_cmd(&mut conn, history_args, &config, out)
+            .with_context(|| {
+                format!(
+                    "Unable to import history from {}",
+                    history_args.profile.to_string_lossy()
+                )
+            });
+    }
+    if let Commands::Ingest { ingest_args } = &cli.command {
+        // Each URL is committed separately, so one failure doesn't undo the rest.
+        return ingest_cmd(&mut conn, ingest_args, &config, out)
+            .with_context(|| "Unable to ingest links");
+    }
+    let tx = conn.transaction()?;
+
+    match &cli.command {
+        Commands::Add { add_args } => {
+            add_cmd(&tx, add_args, &config, out)
+                .with_context(|| format!("Unable to add <{}>", add_args.target()))?;
+            tx.commit()?;
+        }
+        Commands::Check { check_args } => {
+            check_cmd(&tx, check_args, &config, out).with_context(|| "Unable to check links")?;
+            // Only --archive-org changes anything.
+            tx.commit()?;
+        }
+        Commands::Config { command: _ }
+        | Commands::Db {
+            command: DbCommands::Info,
+        }
+        | Commands::Templates { command: _ } => {
+            // Handled above, before the database is opened.
+        }
+        Commands::Db {
+            command: DbCommands::Repair { repair_args },
+        } => {
+            db_repair_cmd(&tx, repair_args, out)
+                .with_context(|| "Unable to repair the database")?;
+            tx.commit()?;
+        }
+        Commands::Export { export_args } => {
+            export_cmd(&tx, export_args, json_pretty, out)
+                .with_context(|| "Unable to export links")?;
+        }
+        Commands::History { history_args } => {
+            history_cmd(&tx, history_args, json_pretty, &config.ui, out)
+                .with_context(|| "Unable to show history")?;
+        }
+        Commands::Import { import_args: _ }
+        | Commands::Ingest { ingest_args: _ }
+        | Commands::MigrateFromBrowserHistory { history_args: _ } => {
+            // Handled above, with a transaction per link.
+        }
+        Commands::List { list_args } => {
+            list_cmd(&tx, list_args, json_pretty, &config.ui, out)
+                .with_context(|| "Unable to list items")?;
+        }
+        Commands::Link { command } => match command {
+            LinkCommands::AddFile { add_file_args } => {
+                link_add_file_cmd(&tx, add_file_args, out)
+                    .with_context(|| format!("Unable to add {}", add_file_args.path.display()))?;
+                tx.commit()?;
+            }
+            LinkCommands::Archive { archive_args } => {
+                link_archive_cmd(&tx, archive_args, out)
+                    .with_context(|| format!("Unable to archive <{}>", archive_args.link))?;
+                tx.commit()?;
+            }
+            LinkCommands::ArchiveTo { archive_to_args } => {
+                link_archive_to_cmd(&tx, archive_to_args, &config.fetch, out).with_context(
+                    || format!("Unable to save a copy of <{}>", archive_to_args.link),
+                )?;
+                tx.commit()?;
+            }
+            LinkCommands::BulkTag { bulk_tag_args } => {
+                link_bulk_tag_cmd(&tx, bulk_tag_args, &config, out).with_context(|| {
+                    format!(
+                        "Unable to tag the links in {}",
+                        bulk_tag_args.file.display()
+                    )
+                })?;
+                tx.commit()?;
+            }
+            LinkCommands::ContentStats => {
+                link_content_stats_cmd(&tx, &config.ui, out)
+                    .with_context(|| "Unable to show content statistics")?;
+            }
+            LinkCommands::Copy { copy_args } => {
+                link_copy_cmd(&tx, copy_args, out)
+                    .with_context(|| format!("Unable to copy <{}>", copy_args.link))?;
+            }
+            LinkCommands::ExportOpml { export_opml_args } => {
+                link_export_opml_cmd(&tx, export_opml_args, out)
+                    .with_context(|| "Unable to export links as OPML")?;
+            }
+            LinkCommands::Favorite { favorite_args } => {
+                link_favorite_cmd(&tx, favorite_args, out)
+                    .with_context(|| format!("Unable to favorite <{}>", favorite_args.link))?;
+                tx.commit()?;
+            }
+            LinkCommands::ImportArchiveOrg {
+                import_archive_org_args,
+            } => {
+                link_import_archive_org_cmd(&tx, import_archive_org_args, &config, out)
+                    .with_context(|| {
+                        format!(
+                            "Unable to import <{}> from the Wayback Machine",
+                            import_archive_org_args.url
+                        )
+                    })?;
+                tx.commit()?;
+            }
+            LinkCommands::Pin { pin_args } => {
+                link_pin_cmd(&tx, pin_args, out)
+                    .with_context(|| format!("Unable to pin <{}>", pin_args.link))?;
+                tx.commit()?;
+            }
+            LinkCommands::Read { read_args } => {
+                link_read_cmd(&tx, read_args, true, out)
+                    .with_context(|| format!("Unable to mark <{}> read", read_args.link))?;
+                tx.commit()?;
+            }
+            LinkCommands::Relate { relate_args } => {
+                link_relate_cmd(&tx, relate_args, out).with_context(|| {
+                    format!(
+                        "Unable to relate <{}> to <{}>",
+                        relate_args.primary, relate_args.related
+                    )
+                })?;
+                tx.commit()?;
+            }
+            LinkCommands::Relations { relations_args } => {
+                link_relations_cmd(&tx, relations_args, json_pretty, &config.ui, out)
+                    .with_context(|| {
+                        format!("Unable to show relations for <{}>", relations_args.link)
+                    })?;
+            }
+            LinkCommands::Remove { remove_args } => {
+                link_remove_cmd(&tx, remove_args, out)
+                    .with_context(|| format!("Unable to remove <{}>", remove_args.link))?;
+                tx.commit()?;
+            }
+            LinkCommands::Restore { restore_args } => {
+                link_restore_cmd(&tx, restore_args, out)
+                    .with_context(|| format!("Unable to restore <{}>", restore_args.link))?;
+                tx.commit()?;
+            }
+            LinkCommands::ShowContent { show_content_args } => {
+                link_show_content_cmd(&tx, show_content_args, out).with_context(|| {
+                    format!("Unable to show content for <{}>", show_content_args.link)
+                })?;
+            }
+            LinkCommands::Tag { tag_args } => {
+                link_tag_cmd(&tx, tag_args, out)
+                    .with_context(|| format!("Unable to tag <{}>", tag_args.link))?;
+                tx.commit()?;
+            }
+            LinkCommands::Unpin { unpin_args } => {
+                link_unpin_cmd(&tx, unpin_args, out)
+                    .with_context(|| format!("Unable to unpin <{}>", unpin_args.link))?;
+                tx.commit()?;
+            }
+            LinkCommands::Unrelate { unrelate_args } => {
+                link_unrelate_cmd(&tx, unrelate_args, out).with_context(|| {
+                    format!(
+                        "Unable to unrelate <{}> from <{}>",
+                        unrelate_args.primary, unrelate_args.related
+                    )
+                })?;
+                tx.commit()?;
+            }
+            LinkCommands::Untag { untag_args } => {
+                link_untag_cmd(&tx, untag_args, out)
+                    .with_context(|| format!("Unable to untag <{}>", untag_args.link))?;
+                tx.commit()?;
+            }
+            LinkCommands::Unread { unread_args } => {
+                link_read_cmd(&tx, unread_args, false, out)
+                    .with_context(|| format!("Unable to mark <{}> unread", unread_args.link))?;
+                tx.commit()?;
+            }
+            LinkCommands::UpdateUrl { update_url_args } => {
+                link_update_url_cmd(&tx, update_url_args, &config, out)
+                    .with_context(|| format!("Unable to update <{}>", update_url_args.old_url))?;
+                tx.commit()?;
+            }
+        },
+        Commands::Note { command, note_args } => {
+            match command {
+                Some(NoteCommands::Delete { delete_args }) => {
+                    note_delete_cmd(&tx, delete_args, out)
+                        .with_context(|| format!("Unable to delete <{}>", delete_args.title))?;
+                }
+                Some(NoteCommands::Edit { edit_args }) => {
+                    note_edit_cmd(&tx, edit_args, out)
+                        .with_context(|| format!("Unable to edit <{}>", edit_args.title))?;
+                }
+                Some(NoteCommands::Export { export_args }) => {
+                    note_export_cmd(&tx, export_args, out)
+                        .with_context(|| "Unable to export notes")?;
+                }
+                Some(NoteCommands::Import { import_args }) => {
+                    note_import_cmd(&tx, import_args, out).with_context(|| {
+                        format!("Unable to import notes from {}", import_args.dir.display())
+                    })?;
+                }
+                Some(NoteCommands::List { list_args }) => {
+                    note_list_cmd(&tx, list_args, json_pretty, &config.ui, out)
+                        .with_context(|| "Unable to list notes")?;
+                }
+                Some(NoteCommands::Show { show_args }) => {
+                    note_show_cmd(&tx, show_args, json_pretty, &config.ui, out)
+                        .with_context(|| format!("Unable to show <{}>", show_args.title))?;
+                }
+                Some(NoteCommands::Tag { tag_args }) => {
+                    note_tag_cmd(&tx, tag_args, out)
+                        .with_context(|| format!("Unable to tag <{}>", tag_args.title))?;
+                }
+                Some(NoteCommands::Untag { untag_args }) => {
+                    note_untag_cmd(&tx, untag_args, out)
+                        .with_context(|| format!("Unable to untag <{}>", untag_args.title))?;
+                }
+                None => {
+                    note_cmd(&tx, note_args, &config, out).with_context(|| "Unable to add note")?;
+                }
+            }
+            tx.commit()?;
+        }
+        Commands::RelatedLinks { related_links_args } => {
+            related_links_cmd(&tx, related_links_args, json_pretty, &config.ui, out)
+                .with_context(|| "Unable to list related links")?;
+        }
+        Commands::Remove { remove_args } => {
+            remove_cmd(&tx, remove_args, out).with_context(|| "Unable to remove item")?;
+            tx.commit()?;
+        }
+        Commands::Search { search_args } => {
+            search_cmd(&tx, search_args, json_pretty, &config.ui, out)
+                .with_context(|| "Unable to search")?;
+        }
+        Commands::Serve { serve_args: _ } => {
+            // Handled above, with its own read-only connection.
+        }
+        Commands::Show { show_args } => {
+            show_cmd(&tx, show_args, json_pretty, &config.ui, out)
+                .with_context(|| format!("Unable to show <{}>", show_args.term))?;
+        }
+        Commands::Stats => {
+            stats_cmd(&tx, &config.ui, out).with_context(|| "Unable to show stats")?;
+        }
+        Commands::Tags { command } => match command {
+            TagsCommands::Import { import_args } => {
+                tags_import_cmd(&tx, import_args, out).with_context(|| {
+                    format!("Unable to import tags from {}", import_args.file.display())
+                })?;
+                tx.commit()?;
+            }
+            TagsCommands::List { list_args } => {
+                tags_list_cmd(&tx, list_args, json_pretty, &config.ui, out)
+                    .with_context(|| "Unable to list tags")?;
+            }
+            TagsCommands::Orphans { orphans_args } => {
+                tags_orphans_cmd(&tx, orphans_args, &config.ui, out)
+                    .with_context(|| "Unable to find orphaned tags")?;
+                tx.commit()?;
+            }
+            TagsCommands::SetDescription {
+                set_description_args,
+            } => {
+                tags_set_description_cmd(&tx, set_description_args, out).with_context(|| {
+                    format!("Unable to describe tag `{}`", set_description_args.tag)
+                })?;
+                tx.commit()?;
+            }
+            TagsCommands::SetParent { set_parent_args } => {
+                tags_set_parent_cmd(&tx, set_parent_args, out).with_context(|| {
+                    format!("Unable to set parent of tag `{}`", set_parent_args.tag)
+                })?;
+                tx.commit()?;
+            }
+            TagsCommands::Show { show_args } => {
+                tags_show_cmd(&tx, show_args, json_pretty, &config.ui, out)
+                    .with_context(|| format!("Unable to show tag `{}`", show_args.tag))?;
+            }
+            TagsCommands::Tree => {
+                tags_tree_cmd(&tx, out).with_context(|| "Unable to show tag tree")?;
+            }
+        },
+        Commands::Undo => {
+            undo_cmd(&tx, out).with_context(|| "Unable to undo")?;
+            tx.commit()?;
+        }
+        Commands::Update { update_args } => {
+            let link = db::get_link(
+                &tx,
+                db::TermOrId::Term(&update_args.link),
+                db::IsPrimary::PrimaryOnly,
+            )?;
+            if let Some(link) = link {
+                let command = match &update_args.command {
+                    UpdateCommands::AddRelatedLink {
+                        add_related_link_args,
+                    } => update_add_related_link_cmd(
+                        &tx,
+                        &link,
+                        &add_related_link_args.related_link,
+                        add_related_link_args.relation,
+                    ),
+                    UpdateCommands::AddTag { add_tag_args } => {
+                        update_add_tag_cmd(&tx, &link, &add_tag_args.tags)
+                    }
+                    UpdateCommands::Refresh { refresh_args: _ } => {
+                        let mut writeable = link.clone();
+                        update_refresh_cmd(&tx, &mut writeable, &config, out)
+                    }
+                    UpdateCommands::RemoveRelatedLink {
+                        remove_related_link_args,
+                    } => update_remove_related_link_cmd(
+                        &tx,
+                        &link,
+                        &remove_related_link_args.related_link,
+                        out,
+                    ),
+                    UpdateCommands::RemoveTag { remove_tag_args } => {
+                        update_remove_tag_cmd(&tx, &link, &remove_tag_args.tags)
+                    }
+                };
+                command.with_context(|| format!("Unable to update <{}>", &update_args.link))?;
+                tx.commit()?;
+                writeln!(out, "<{}> updated", update_args.link)?;
+            } else {
+                writeln!(out, "Unknown link <{}>", update_args.link)?;
+            }
+        }
+        Commands::Watch { watch_args: _ } => {
+            // Handled above, before we open a transaction.
+        }
+    }
+
+    Ok(())
+}
+
+fn home_dir() -> Option<PathBuf> {
+    // NB: The state of std::env::home_dir() and its replacements is a mess.
+    // See <https://doc.rust-lang.org/std/env/fn.home_dir.html> and
+    // <https://github.com/rust-lang/libs-team/issues/372>. Notably, `home`
+    // is not recommended for use outside of Cargo. Hopefully `env_home` will
+    // end up in standard library and we can go ahead and use that.
+    env_home::env_home_dir()
+}
+
+fn expand_tilde(path: &mut PathBuf) {
+    if let Some(home) = home_dir() {
+        *path = util::expand_tilde(path, &home);
+    }
+}
+
+fn default_fetch_timeout() -> u64 {
+    5
+}
+
+fn default_cache_ttl() -> u64 {
+    60 * 60
+}
+
+fn default_max_download_bytes() -> u64 {
+    2 * 1024 * 1024
+}
+
+fn default_max_retries() -> u8 {
+    3
+}
+
+fn default_retry_delay() -> u64 {
+    1000
+}
+
+fn default_date_format() -> String {
+    "%F".to_string()
+}
+
+fn default_db_location() -> PathBuf {
+    let app_dirs = platform_dirs::AppDirs::new(Some(APP_NAME), true);
+    match app_dirs {
+        Some(app_dirs) => app_dirs.data_dir.join("meowpad.db"),
+        None => match home_dir() {
+            Some(mut home_dir) => {
+                home_dir.push(".meowpad.db");
+                home_dir
+            }
+            None => ".meowpad.db".into(),
+        },
+    }
+}
+
+// Returns the config file location, and whether it was explicitly requested
+// (in which case failing to read it is an error).
+fn config_path(cli: &Cli) -> (PathBuf, bool) {
+    if let Some(cli_config) = &cli.config {
+        let mut cli_config = cli_config.clone();
+        expand_tilde(&mut cli_config);
+        (cli_config, true)
+    } else {
+        // It may make sense at some point to switch from `platform_dirs` to
+        // `etcetera` or `xdg` to reduce the number of dependencies that get
+        // pulled in. We're using `platform_dirs` for now because it handles
+        // Windows (less important) and lets us specify that Macs should
+        // follow XDG locations (important).
+        let app_dirs = platform_dirs::AppDirs::new(Some(APP_NAME), true);
+        match app_dirs {
+            Some(app_dirs) => (app_dirs.config_dir.join("config.toml"), false),
+            // This will error out, which is fine!
+            None => (PathBuf::new(), false),
+        }
+    }
+}
+
+fn load_config(cli: &Cli) -> Result<(Config, PathBuf)> {
+    // Defaults will be overwritten by the TOML config file, which in turn will
+    // be overwritten by environment variables and then CLI arguments, if
+    // available.
+    let mut config = Config::new();
+    let (config_path, error_on_load_failure) = config_path(cli);
+    let config_path = &config_path;
+    if let Ok(config_str) = std::fs::read_to_string(config_path) {
+        config = toml::from_str(&config_str)
+            .map_err(|e| MeowpadError::ConfigError(e.to_string()))
+            .with_context(|| {
+                format!(
+                    "Unable to parse config file at {}",
+                    config_path.to_string_lossy()
+                )
+            })?;
+    } else {
+        // If we are just using a default config path and there is no config present,
+        // we'll treat it as a noop and stick with the default config.
+        if error_on_load_failure {
+            return Err(anyhow!(
+                "Unable to open config file at {}",
+                config_path.to_string_lossy()
+            ));
+        }
+    }
+    // Environment variables come next; then any values that can be
+    // overwritten from the CLI should go last.
+    let env_db = std::env::var_os("MEOWPAD_DB")
+        .filter(|db| !db.is_empty())
+        .map(PathBuf::from);
+    config.database = util::database_path(cli.db.clone(), env_db, config.database);
+    if let Commands::Add { add_args } = &cli.command {
+        if let Some(timeout) = add_args.timeout {
+            config.fetch.timeout_secs = timeout;
+        }
+        if let Some(max_bytes) = add_args.max_content_bytes {
+            config.fetch.max_download_bytes = max_bytes;
+        }
+        if add_args.title_only_fetch {
+            config.fetch.mode = FetchMode::Title;
+        }
+    }
+    // Finally, let's do tilde expansion on file paths, wherever they came from.
+    expand_tilde(&mut config.database);
+    Ok((config, config_path.to_path_buf()))
+}
+
+// CONFIG
+fn config_cmd(cli: &Cli, command: &ConfigCommands, out: &mut dyn Write) -> Result<()> {
+    match command {
+        ConfigCommands::Get { get_args } => config_get_cmd(cli, get_args, out)?,
+        ConfigCommands::Path => {
+            let (path, _) = config_path(cli);
+            let path = std::path::absolute(&path).unwrap_or(path);
+            writeln!(out, "{}", path.to_string_lossy())?;
+        }
+        ConfigCommands::Set { set_args } => config_set_cmd(cli, set_args, out)?,
+        ConfigCommands::Show => {
+            let (config, _) = load_config(cli)?;
+            write!(out, "{}", toml::to_string(&config)?)?;
+        }
+        ConfigCommands::Unset { unset_args } => config_unset_cmd(cli, unset_args, out)?,
+        ConfigCommands::Validate => config_validate_cmd(cli, out)?,
+    }
+    Ok(())
+}
+
+fn config_get_cmd(cli: &Cli, args: &ConfigGetArgs, out: &mut dyn Write) -> Result<()> {
+    let key = config_key(&args.key)?;
+    let (config, _) = load_config(cli)?;
+    let mut value = Some(toml::Value::try_from(&config)?);
+    for part in key.split('.') {
+        value = value.and_then(|v| v.get(part).cloned());
+    }
+    match value {
+        Some(toml::Value::String(s)) => writeln!(out, "{s}")?,
+        Some(value) => writeln!(out, "{value}")?,
+        None => writeln!(out, "`{key}` is not set")?,
+    }
+    Ok(())
+}
+
+fn config_set_cmd(cli: &Cli, args: &ConfigSetArgs, out: &mut dyn Write) -> Result<()> {
+    let key = config_key(&args.key)?;
+    let value = config_value(key, &args.value)?;
+    let (path, mut doc) = editable_config(cli)?;
+    let (tables, name) = split_config_key(key);
+    let mut table: &mut dyn toml_edit::TableLike = doc.as_table_mut();
+    for (i, table_name) in tables.iter().enumerate() {
+        // New tables get a [header] of their own (only once they have keys),
+        // rather than an inline table.
+        let entry = table.entry(table_name).or_insert_with(|| {
+            let mut new_table = toml_edit::Table::new();
+            new_table.set_implicit(true);
+            toml_edit::Item::Table(new_table)
+        });
+        table = entry.as_table_like_mut().with_context(|| {
+            format!(
+                "`{}` in {} isn't a table, so `{key}` can't be set",
+                tables[..=i].join("."),
+                path.display()
+            )
+        })?;
+    }
+    table.insert(name, toml_edit::value(value));
+    // Check that the result loads before writing it, so that a bad value
+    // (say, an unknown table style) doesn't break the next run.
+    toml::from_str::<Config>(&doc.to_string())
+        .with_context(|| format!("Invalid value for `{key}`"))?;
+    write_config(&path, &doc)?;
+    writeln!(out, "Set `{key}` in {}", path.display())?;
+    Ok(())
+}
+
+fn config_unset_cmd(cli: &Cli, args: &ConfigGetArgs, out: &mut dyn Write) -> Result<()> {
+    let key = config_key(&args.key)?;
+    let (path, mut doc) = editable_config(cli)?;
+    let (tables, name) = split_config_key(key);
+    let mut item = Some(doc.as_item_mut());
+    for table in tables {
+        item = item.and_then(|i| i.get_mut(table));
+    }
+    let removed = item
+        .and_then(|i| i.as_table_like_mut())
+        .and_then(|t| t.remove(name));
+    if removed.is_none() {
+        writeln!(out, "`{key}` isn't set in {}", path.display())?;
+        return Ok(());
+    }
+    write_config(&path, &doc)?;
+    writeln!(out, "Unset `{key}` in {}", path.display())?;
+    Ok(())
+}
+
+// `fetch.retry.max_retries` is the key `max_retries` in tables `fetch` and
+// `retry`.
+fn split_config_key(key: &str) -> (Vec<&str>, &str) {
+    match key.rsplit_once('.') {
+        Some((tables, name)) => (tables.split('.').collect(), name),
+        None => (vec![], key),
+    }
+}
+
+fn config_key(key: &str) -> Result<&str> {
+    if !CONFIG_KEYS.contains(&key) {
+        anyhow::bail!(
+            "`{key}` can't be changed with `config`; use one of {}",
+            CONFIG_KEYS.join(", ")
+        );
+    }
+    Ok(key)
+}
+
+// The TOML value for a setting, typed to match `Config`.
+fn config_value(key: &str, raw: &str) -> Result<toml_edit::Value> {
+    Ok(match key {
+        "ui.tag_colors" => raw
+            .parse::<bool>()
+            .with_context(|| format!("`{key}` must be true or false"))?
+            .into(),
+        "fetch.timeout_secs"
+        | "fetch.cache_ttl_secs"
+        | "fetch.max_download_bytes"
+        | "fetch.retry.max_retries"
+        | "fetch.retry.base_delay_ms" => raw
+            .parse::<i64>()
+            .with_context(|| format!("`{key}` must be a whole number"))?
+            .into(),
+        _ => raw.into(),
+    })
+}
+
+// The config file, parsed with its comments and layout, or an empty one if
+// there's no file yet.
+fn editable_config(cli: &Cli) -> Result<(PathBuf, toml_edit::DocumentMut)> {
+    let (path, _) = config_path(cli);
+    let config_str = match std::fs::read_to_string(&path) {
+        Ok(config_str) => config_str,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Unable to open config file at {}", path.display()));
+        }
+    };
+    let doc = config_str
+        .parse::<toml_edit::DocumentMut>()
+        .with_context(|| format!("Unable to parse config file at {}", path.display()))?;
+    Ok((path, doc))
+}
+
+fn write_config(path: &Path, doc: &toml_edit::DocumentMut) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, doc.to_string())
+        .with_context(|| format!("Unable to write config file at {}", path.display()))
+}
+
+fn db_info_cmd(config: &Config, out: &mut dyn Write) -> Result<()> {
+    let conn = Connection::open_with_flags(&config.database, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Unable to open database at {:?}", &config.database))?;
+    let info = db::database_info(&conn).with_context(|| "Unable to read database info")?;
+    let mut table = ui::new_table(&config.ui, TableStyle::Utf8Full);
+    table.add_row(vec![
+        "Path".to_string(),
+        config.database.to_string_lossy().to_string(),
+    ]);
+    table.add_row(vec!["SQLite version".to_string(), info.sqlite_version]);
+    table.add_row(vec![
+        "Size".to_string(),
+        util::format_bytes(info.size_bytes),
+    ]);
+    table.add_row(vec![
+        "Schema version".to_string(),
+        info.schema_version.to_string(),
+    ]);
+    for (name, count) in info.row_counts {
+        table.add_row(vec![
+            format!("Rows in {name}"),
+            count.map_or("(missing)".to_string(), |c| c.to_string()),
+        ]);
+    }
+    writeln!(out, "{table}")?;
+    Ok(())
+}
+
+fn db_repair_cmd(tx: &Transaction, args: &DbRepairArgs, out: &mut dyn Write) -> Result<()> {
+    let steps = db::repair(tx, args.unused_tags)?;
+    for (step, count) in &steps {
+        writeln!(out, "{step}: {count}")?;
+    }
+    let total: usize = steps.iter().map(|(_, count)| count).sum();
+    if total == 0 {
+        writeln!(out, "Nothing to repair")?;
+    }
+    Ok(())
+}
+
+fn templates_cmd(command: &TemplatesCommands, config: &Config, out: &mut dyn Write) -> Result<()> {
+    match command {
+        TemplatesCommands::List => {
+            if config.note_templates.is_empty() {
+                writeln!(out, "No note templates configured")?;
+                return Ok(());
+            }
+            let mut table = ui::new_table(&config.ui, TableStyle::Utf8Borders);
+            table.set_header(vec!["Name", "Content"]);
+            for template in &config.note_templates {
+                table.add_row(vec![template.name.as_str(), template.content.trim_end()]);
+            }
+            writeln!(out, "{table}")?;
+        }
+    }
+    Ok(())
+}
+
+fn config_validate_cmd(cli: &Cli, out: &mut dyn Write) -> Result<()> {
+    let (path, explicit) = config_path(cli);
+    let config_str = match std::fs::read_to_string(&path) {
+        Ok(config_str) => config_str,
+        Err(e) if explicit || e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(e)
+                .with_context(|| format!("Unable to open config file at {}", path.display()));
+        }
+        Err(_) => {
+            writeln!(out, "No config file at {}; using defaults", path.display())?;
+            return Ok(());
+        }
+    };
+    // Unknown keys are silently ignored when loading, so collect them here.
+    let mut unknown_keys = vec![];
+    let deserializer = toml::Deserializer::new(&config_str);
+    let parsed: Result<Config, _> =
+        serde_ignored::deserialize(deserializer, |key| unknown_keys.push(key.to_string()));
+    let mut problems: Vec<String> = unknown_keys
+        .iter()
+        .map(|key| format!("Unknown key `{key}`"))
+        .collect();
+    if let Err(e) = parsed {
+        problems.push(e.to_string().trim_end().to_string());
+    }
+    if problems.is_empty() {
+        writeln!(out, "{} is valid", path.display())?;
+        return Ok(());
+    }
+    for problem in &problems {
+        writeln!(out, "{problem}")?;
+    }
+    Err(anyhow!(
+        "Found {} problem(s) in {}",
+        problems.len(),
+        path.display()
+    ))
+}
+
+// UTIL
+fn now() -> Result<String, jiff::Error> {
+    let zoned = Zoned::now().round(Unit::Second)?;
+    Ok(zoned.timestamp().to_string())
+}
+
+// LINK
+fn readability(
+    tx: &Transaction,
+    url: &str,
+    fetch_config: &FetchConfig,
+    skip_cache: bool,
+) -> Result<Article> {
+    let html = fetch_html(tx, url, fetch_config, skip_cache)?;
+    article_from_html(&html, url)
+}
+
+fn article_from_html(html: &str, url: &str) -> Result<Article> {
+    // TODO: We should test to see if we believe that the readability score is
+    // high enough to make this worthwhile, or if we should instead just
+    // extract the title (and maybe excerpt?).
+    let mut readability = Readability::new(html, Some(url), None)?;
+    Ok(readability.parse()?)
+}
+
+// What `add` keeps from a fetched page.
+struct PageInfo {
+    title: String,
+    description: Option<String>,
+    text_content: Option<String>,
+    // The download was cut short at `max_download_bytes`.
+    truncated: bool,
+}
+
+fn fetch_page_info(
+    tx: &Transaction,
+    url: &str,
+    fetch_config: &FetchConfig,
+    skip_cache: bool,
+) -> Result<PageInfo> {
+    match fetch_config.mode {
+        FetchMode::Full => {
+            let html = fetch_html(tx, url, fetch_config, skip_cache)?;
+            let article = article_from_html(&html, url)?;
+            Ok(PageInfo {
+                title: article.title,
+                description: article.excerpt,
+                text_content: Some(article.text_content.trim().to_string()),
+                truncated: fetch_config.is_truncated(&html),
+            })
+        }
+        FetchMode::Title => {
+            let html = fetch_html(tx, url, fetch_config, skip_cache)?;
+            let (title, description) = util::extract_title_and_description(&html);
+            Ok(PageInfo {
+                title,
+                description,
+                text_content: None,
+                truncated: fetch_config.is_truncated(&html),
+            })
+        }
+    }
+}
+
+fn http_agent(fetch_config: &FetchConfig) -> Agent {
+    Agent::config_builder()
+        .user_agent(APP_USER_AGENT)
+        .timeout_global(Some(fetch_config.timeout()))
+        .build()
+        .into()
+}
+
+fn fetch_html(
+    tx: &Transaction,
+    url: &str,
+    fetch_config: &FetchConfig,
+    skip_cache: bool,
+) -> Result<String> {
+    // Each invocation is its own process, so the cache lives in the database
+    // rather than in memory.
+    let use_cache = fetch_config.cache_ttl_secs > 0;
+    let now = now()?;
+    let cutoff = if use_cache {
+        let ttl = Span::new().seconds(i64::try_from(fetch_config.cache_ttl_secs)?);
+        let cutoff = now.parse::<Timestamp>()?.checked_sub(ttl)?;
+        if !skip_cache {
+            if let Some(html) = db::get_cached_html(tx, url, &cutoff.to_string())? {
+                util::log_event("DEBUG", "using cached page", &[("url", url)]);
+                return Ok(html);
+            }
+        }
+        Some(cutoff)
+    } else {
+        None
+    };
+    util::log_event("INFO", "fetching url", &[("url", url)]);
+    let response = get_with_retries(&http_agent(fetch_config), url, fetch_config);
+    let html = match response {
+        Ok(html) => {
+            util::log_event(
+                "INFO",
+                "fetched url",
+                &[("url", url), ("bytes", &html.len().to_string())],
+            );
+            html
+        }
+        Err(e) => {
+            util::log_event(
+                "ERROR",
+                "fetch failed",
+                &[("url", url), ("error", &e.to_string())],
+            );
+            return Err(MeowpadError::FetchError(e.to_string()).into());
+        }
+    };
+    if let Some(cutoff) = cutoff {
+        db::cache_html(tx, url, &html, &now, &cutoff.to_string())?;
+    }
+    Ok(html)
+}
+
+// Longest we'll honor a Retry-After header for; past that, give up instead.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+// Retries transient failures with exponential backoff; anything else, such
+// as a 404 or 403, fails straight away. Only the first `max_download_bytes`
+// of the page are read.
+fn get_with_retries(
+    agent: &Agent,
+    url: &str,
+    fetch_config: &FetchConfig,
+) -> Result<String, ureq::Error> {
+    let retry = &fetch_config.retry;
+    let mut attempt = 0;
+    loop {
+        let response = agent
+            .get(url)
+            .config()
+            .http_status_as_error(false)
+            .build()
+            .call();
+        let (error, transient, retry_after) = match response {
+            Ok(mut response) if response.status().is_success() => {
+                use std::io::Read;
+                let limit = fetch_config.max_download_bytes;
+                let mut bytes = vec![];
+                response
+                    .body_mut()
+                    .with_config()
+                    .lossy_utf8(true)
+                    .reader()
+                    .take(limit.saturating_add(1))
+                    .read_to_end(&mut bytes)?;
+                if bytes.len() as u64 > limit {
+                    bytes.truncate(limit as usize);
+                    util::log_event(
+                        "WARN",
+                        "page truncated",
+                        &[("url", url), ("bytes", &limit.to_string())],
+                    );
+                }
+                // The cut may have split a character.
+                return Ok(String::from_utf8_lossy(&bytes).into_owned());
+            }
+            Ok(response) => {
+                let status = response.status().as_u16();
+                let retry_after = response
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse::<u64>().ok())
+                    .map(Duration::from_secs);
+                (
+                    ureq::Error::StatusCode(status),
+                    matches!(status, 429 | 502 | 503 | 504),
+                    retry_after,
+                )
+            }
+            Err(e) => {
+                let transient = match &e {
+                    ureq::Error::Io(io) => matches!(
+                        io.kind(),
+                        std::io::ErrorKind::ConnectionReset
+                            | std::io::ErrorKind::ConnectionAborted
+                            | std::io::ErrorKind::BrokenPipe
+                            | std::io::ErrorKind::UnexpectedEof
+                            | std::io::ErrorKind::TimedOut
+                            | std::io::ErrorKind::Interrupted
+                    ),
+                    ureq::Error::Timeout(_)
+                    | ureq::Error::ConnectionFailed
+                    | ureq::Error::BodyStalled => true,
+                    _ => false,
+                };
+                (e, transient, None)
+            }
+        };
+        if !transient || attempt >= retry.max_retries {
+            return Err(error);
+        }
+        let delay = match retry_after {
+            Some(retry_after) if retry_after > MAX_RETRY_AFTER => return Err(error),
+            Some(retry_after) => retry_after,
+            None => util::backoff_delay(retry.base_delay_ms, attempt),
+        };
+        util::log_event(
+            "WARN",
+            "retrying fetch",
+            &[
+                ("url", url),
+                ("error", &error.to_string()),
+                ("delay_ms", &delay.as_millis().to_string()),
+            ],
+        );
+        std::thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+// UTIL
+fn get_tag_id(tx: &Transaction, tag_name: &str) -> Result<TableId> {
+    let now = now()?;
+    let slug = util::slugify(tag_name)?;
+    let id = db::require_tag(tx, tag_name, &slug, &now)?;
+    Ok(id)
+}
+
+fn add_cmd(tx: &Transaction, args: &AddArgs, config: &Config, out: &mut dyn Write) -> Result<()> {
+    let url = add_link(tx, args, config)?;
+    log_link_added(tx, &url)?;
+    writeln!(out, "Added bookmark for <{url}>")?;
+    Ok(())
+}
+
+// Records the add so that `undo` can remove the link again.
+fn log_link_added(tx: &Transaction, url: &Url) -> Result<()> {
+    if let Some(link) = db::get_link(tx, db::TermOrId::Term(url.as_str()), db::IsPrimary::Either)? {
+        let payload = serde_json::json!({ "url": url });
+        db::log_operation(tx, "add", "link", &link.id, &payload, &now()?)?;
+    }
+    Ok(())
+}
+
+// Adds the link, returning the URL it was stored under.
+fn add_link(tx: &Transaction, args: &AddArgs, config: &Config) -> Result<Url> {
+    let doi = match &args.doi {
+        Some(doi) => {
+            Some(util::parse_doi(doi).ok_or_else(|| anyhow!("`{doi}` is not a valid DOI"))?)
+        }
+        None => args.link.as_deref().and_then(util::parse_doi),
+    };
+    let given_content = if let Some(path) = &args.content_file {
+        Some(
+            std::fs::read_to_string(path)
+                .with_context(|| format!("Unable to read {}", path.display()))?,
+        )
+    } else if args.content_stdin {
+        Some(std::io::read_to_string(std::io::stdin())?)
+    } else {
+        None
+    };
+    // Text given up front stands in for the page, so there's nothing to fetch.
+    let no_fetch = args.no_fetch || given_content.is_some();
+    let arxiv_id = doi.as_deref().and_then(util::arxiv_id_from_doi);
+    let url = match (&doi, &arxiv_id) {
+        (_, Some(arxiv_id)) => Url::parse(&format!("https://arxiv.org/abs/{arxiv_id}"))?,
+        // Without fetching, the DOI's own URL is the best we can do.
+        (Some(doi), None) if no_fetch => Url::parse(&format!("https://doi.org/{doi}"))?,
+        (Some(doi), None) => util::validate_url(&resolve_doi(doi, &config.fetch)?, false)?,
+        (None, None) => util::validate_url(&args.target(), false)?,
+    };
+    if args.relation.len() > args.related_link.len() {
+        anyhow::bail!("More relations given than related links");
+    }
+    let arxiv_info = match &arxiv_id {
+        Some(arxiv_id) if !no_fetch => Some(fetch_arxiv_info(arxiv_id, &config.fetch)?),
+        _ => None,
+    };
+    let now = now()?;
+    let created_at = args.created_at.map(|t| t.to_string());
+    // TODO: We should be able to disable fetch everywhere via config, or on a
+    // per-domain or per-tag basis.
+    let page_info = if let Some(html) = &args.html {
+        let article = article_from_html(html, url.as_str())?;
+        Some(PageInfo {
+            title: article.title,
+            description: article.excerpt,
+            text_content: Some(article.text_content.trim().to_string()),
+            // Imported pages weren't downloaded, so nothing was cut.
+            truncated: false,
+        })
+    } else if let Some(content) = &given_content {
+        Some(PageInfo {
+            title: content
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .unwrap_or_default()
+                .to_string(),
+            description: None,
+            text_content: Some(content.trim().to_string()),
+            truncated: false,
+        })
+    } else if no_fetch {
+        None
+    } else {
+        Some(fetch_page_info(tx, url.as_str(), &config.fetch, false)?)
+    };
+    let found_title = if let Some((title, _)) = &arxiv_info {
+        Some(title.as_str())
+    } else {
+        page_info
+            .as_ref()
+            .map(|p| p.title.as_str())
+            .filter(|t| !t.is_empty())
+    }
+    .map(|t| config.add.title_case.apply(t));
+    let title = args.title.as_deref().or(found_title.as_deref());
+    let description = if args.description.is_some() {
+        args.description.as_deref()
+    } else if let Some((_, summary)) = &arxiv_info {
+        Some(summary.as_str())
+    } else {
+        page_info.as_ref().and_then(|p| p.description.as_deref())
+    };
+    let text_content = page_info.as_ref().and_then(|p| p.text_content.as_deref());
+
+    let link_insert_args = db::LinkInsert {
+        url: url.as_str(),
+        title,
+        description,
+        content: text_content,
+        is_primary: true,
+        timestamp: &now,
+        created_at: created_at.as_deref(),
+        source: Some(args.source.as_deref().unwrap_or("manual")),
+    };
+
+    let link_result = db::insert_link(tx, &link_insert_args, false);
+
+    let link_id = match link_result {
+        Ok(new_link) => {
+            util::log_event(
+                "INFO",
+                "inserted link",
+                &[("url", url.as_str()), ("id", &new_link.to_string())],
+            );
+            new_link
+        }
+        Err(duplicate @ MeowpadError::DuplicateUrl(_)) => {
+            // Let's see if we have an existing *secondary* link that we are changing
+            // to a primary (so it can have its own tags, notes, etc.)
+            let existing =
+                db::get_link(tx, db::TermOrId::Term(url.as_str()), db::IsPrimary::Either)?;
+            if existing.as_ref().is_some_and(|l| l.archived_at.is_some()) {
+                anyhow::bail!("<{url}> is archived; use `link restore` to bring it back");
+            }
+            let mut secondary_link = existing.filter(|l| !l.is_primary);
+            if let Some(ref mut secondary_link) = secondary_link {
+                secondary_link.title = link_insert_args.title.map(|s| s.to_string());
+                secondary_link.description = link_insert_args.description.map(|s| s.to_string());
+                secondary_link.is_primary = true;
+                db::update_link(tx, secondary_link)?;
+                // A secondary link should never have attached content.
+                if let Some(text_content) = text_content {
+                    db::insert_content(tx, &secondary_link.id, text_content)?;
+                }
+            } else {
+                return Err(duplicate.into());
+            };
+            secondary_link.unwrap().id
+        }
+        Err(e) => {
+            util::log_event(
+                "ERROR",
+                "database error",
+                &[("url", url.as_str()), ("error", &e.to_string())],
+            );
+            return Err(e.into());
+        }
+    };
+
+    let mut meta = serde_json::Map::new();
+    if let Some(doi) = &doi {
+        meta.insert("doi".to_string(), serde_json::json!(doi));
+        if let Some(arxiv_id) = &arxiv_id {
+            meta.insert("arxiv_id".to_string(), serde_json::json!(arxiv_id));
+        }
+    }
+    if page_info.as_ref().is_some_and(|p| p.truncated) {
+        meta.insert("content_truncated".to_string(), serde_json::json!(true));
+    }
+    if !meta.is_empty() {
+        db::set_link_meta(tx, &link_id, &serde_json::Value::Object(meta))?;
+    }
+
+    for tag_name in &args.tag {
+        let tag_id = get_tag_id(tx, tag_name)?;
+        db::tag_link(tx, link_id, tag_id)?;
+    }
+    if args.favorite {
+        db::set_link_favorite(tx, &link_id, true)?;
+    }
+    if args.save_for_later {
+        db::set_link_reading_list(tx, &link_id, true)?;
+    }
+
+    // NB: We don't currently need to do any kind of checking on note existence
+    // or updating a note, because we don't currently allow link editing/--force,
+    // but when that changes, this should chage as well.
+    let note = if let Some(message) = &args.message {
+        Some(message.clone())
+    } else if args.note {
+        Some(edit::edit("")?)
+    } else {
+        None
+    };
+
+    if let Some(note_text) = note {
+        let note_id = db::upsert_note(
+            tx,
+            &note_text,
+            url.as_str(),
+            Some(&link_id),
+            &now,
+            created_at.as_deref(),
+        )?;
+        for tag_name in &args.tag {
+            let tag_id = get_tag_id(tx, tag_name)?;
+            db::tag_note(tx, note_id, tag_id)?;
+        }
+    }
+
+    let relations = args.relation.iter().map(|r| Some(*r));
+    let padded_relations = relations.chain(std::iter::repeat(None));
+    for (related_link, relation) in args.related_link.iter().zip(padded_relations) {
+        let related_url = util::validate_url(related_link, false)?;
+        // TODO: We should I think grab title using Readability, even if we don't
+        // need or want description or contents.
+        let insert_vals = db::LinkInsert {
+            url: related_url.as_str(),
+            title: None,
+            description: None,
+            content: None,
+            is_primary: false,
+            timestamp: &now,
+            created_at: created_at.as_deref(),
+            source: None,
+        };
+        let related_link_id = db::insert_link(tx, &insert_vals, true)?;
+        db::relate_links(tx, link_id, related_link_id, relation)?;
+    }
+    Ok(url)
+}
+
+// Follows the DOI proxy's redirects to the publisher's page.
+fn resolve_doi(doi: &str, fetch_config: &FetchConfig) -> Result<String> {
+    let doi_url = format!("https://doi.org/{doi}");
+    util::log_event("INFO", "resolving doi", &[("doi", doi)]);
+    let response = http_agent(fetch_config)
+        .get(&doi_url)
+        .call()
+        .with_context(|| format!("Unable to resolve DOI {doi}"))?;
+    Ok(response.get_uri().to_string())
+}
+
+// An arXiv paper's title and abstract, from the arXiv API.
+fn fetch_arxiv_info(arxiv_id: &str, fetch_config: &FetchConfig) -> Result<(String, String)> {
+    let api_url = format!("https://export.arxiv.org/api/query?id_list={arxiv_id}");
+    util::log_event("INFO", "fetching arxiv metadata", &[("url", &api_url)]);
+    let xml = http_agent(fetch_config)
+        .get(&api_url)
+        .call()?
+        .body_mut()
+        .read_to_string()?;
+    util::extract_arxiv_info(&xml).ok_or_else(|| anyhow!("arXiv has no paper {arxiv_id}"))
+}
+
+// The closest Wayback Machine snapshot of a URL, if there is one.
+fn find_wayback_snapshot(url: &str, fetch_config: &FetchConfig) -> Result<Option<String>> {
+    let mut api_url = Url::parse("https://archive.org/wayback/available")?;
+    api_url.query_pairs_mut().append_pair("url", url);
+    util::log_event("INFO", "looking up wayback snapshot", &[("url", url)]);
+    let json = http_agent(fetch_config)
+        .get(api_url.as_str())
+        .call()?
+        .body_mut()
+        .read_to_string()?;
+    Ok(util::extract_wayback_snapshot(&json))
+}
+
+// Saves the link with its text taken from the closest Wayback Machine
+// snapshot, or if it's already saved, replaces its text. The snapshot's URL
+// is kept in the link's meta as `archive_url`. Returns the snapshot's URL,
+// or None if there isn't one.
+fn import_from_archive_org(
+    tx: &Transaction,
+    url: &Url,
+    tags: &[String],
+    config: &Config,
+) -> Result<Option<String>> {
+    let Some(snapshot) = find_wayback_snapshot(url.as_str(), &config.fetch)? else {
+        return Ok(None);
+    };
+    let html = fetch_html(tx, &snapshot, &config.fetch, false)?;
+    let existing = db::get_link(
+        tx,
+        db::TermOrId::Term(url.as_str()),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    let link_id = match existing {
+        Some(mut link) => {
+            let article = article_from_html(&html, url.as_str())?;
+            if link.title.is_none() && !article.title.is_empty() {
+                link.title = Some(article.title);
+            }
+            if link.description.is_none() {
+                link.description = article.excerpt;
+            }
+            db::update_link(tx, &link)?;
+            db::delete_content(tx, &link.id)?;
+            db::insert_content(tx, &link.id, article.text_content.trim())?;
+            for tag_name in tags {
+                let tag_id = get_tag_id(tx, tag_name)?;
+                db::tag_link(tx, link.id, tag_id)?;
+            }
+            link.id
+        }
+        None => {
+            let add_args = AddArgs {
+                link: Some(url.to_string()),
+                tag: tags.to_vec(),
+                html: Some(html),
+                source: Some("import-archive-org".to_string()),
+                ..Default::default()
+            };
+            let url = add_link(tx, &add_args, config)?;
+            db::get_link(tx, db::TermOrId::Term(url.as_str()), db::IsPrimary::Either)?
+                .ok_or_else(|| anyhow!("<{url}> wasn't saved"))?
+                .id
+        }
+    };
+    let link = db::get_link(tx, db::TermOrId::Id(link_id), db::IsPrimary::Either)?;
+    let mut meta = link
+        .and_then(|l| l.meta)
+        .and_then(|m| m.as_object().cloned())
+        .unwrap_or_default();
+    meta.insert("archive_url".to_string(), serde_json::json!(snapshot));
+    db::set_link_meta(tx, &link_id, &serde_json::Value::Object(meta))?;
+    Ok(Some(snapshot))
+}
+
+fn link_import_archive_org_cmd(
+    tx: &Transaction,
+    args: &LinkImportArchiveOrgArgs,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let url = util::validate_url(&args.url, false)?;
+    match import_from_archive_org(tx, &url, &args.tag, config)? {
+        Some(snapshot) => writeln!(out, "Saved <{url}> from <{snapshot}>")?,
+        None => writeln!(out, "The Wayback Machine has no snapshot of <{url}>")?,
+    }
+    Ok(())
+}
+
+fn check_cmd(
+    tx: &Transaction,
+    args: &CheckArgs,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let filter = args.filter.link_filter();
+    // Saved files and the like have nothing to request.
+    let links: Vec<Link> = db::get_links(tx, args.filter.tag_slugs()?, None, &filter, false)?
+        .into_iter()
+        .filter(|link| matches!(link.url.scheme(), "http" | "https"))
+        .collect();
+    let agent = http_agent(&config.fetch);
+    let total = links.len() as u64;
+    let bar = if std::io::stdout().is_terminal() {
+        ProgressBar::new(total).with_style(ProgressStyle::with_template(
+            "{bar:30} ({pos}/{len}) ETA {eta} {wide_msg}",
+        )?)
+    } else {
+        ProgressBar::hidden()
+    };
+    let mut results = vec![];
+    let mut dead = 0;
+    for link in &links {
+        bar.set_message(link.url.to_string());
+        let request = agent
+            .get(link.url.as_str())
+            .config()
+            .http_status_as_error(false)
+            .build();
+        let mut result = match request.call() {
+            Ok(response) if response.status().is_success() => "OK".to_string(),
+            Ok(response) => {
+                dead += 1;
+                response.status().as_u16().to_string()
+            }
+            Err(e) => {
+                dead += 1;
+                format!("Error: {e}")
+            }
+        };
+        if args.archive_org && result != "OK" {
+            match import_from_archive_org(tx, &link.url, &[], config) {
+                Ok(Some(snapshot)) => result.push_str(&format!("; restored from <{snapshot}>")),
+                Ok(None) => result.push_str("; no Wayback Machine snapshot"),
+                Err(e) => result.push_str(&format!("; Wayback Machine lookup failed: {e}")),
+            }
+        }
+        let line = format!("<{}> {result}", link.url);
+        if args.verbose {
+            bar.suspend(|| writeln!(out, "{line}"))?;
+        } else {
+            results.push(line);
+        }
+        bar.inc(1);
+    }
+    bar.finish_and_clear();
+    for line in results {
+        writeln!(out, "{line}")?;
+    }
+    writeln!(
+        out,
+        "Checked {total} links: {} OK, {dead} dead",
+        total - dead
+    )?;
+    Ok(())
+}
+
+fn export_cmd(
+    tx: &Transaction,
+    args: &ExportArgs,
+    json_pretty: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    if let ExportFormat::TomlTemplate = args.format {
+        write!(out, "{IMPORT_TOML_TEMPLATE}")?;
+        return Ok(());
+    }
+    let filter = args.filter.link_filter();
+    let mut items = vec![];
+    for link in db::get_links(tx, args.filter.tag_slugs()?, None, &filter, false)? {
+        let note = if args.include_notes {
+            db::get_note_by_link_id(tx, &link.id)?
+        } else {
+            None
+        };
+        items.push(ExportItem {
+            tags: db::tags_for_item(tx, &link.id)?,
+            related_links: db::related_links(tx, &link.id)?,
+            note,
+            link,
+        });
+    }
+    // Standalone notes have no link to hang off, so they're listed separately.
+    let notes: Vec<Note> = if args.include_notes {
+        db::get_notes(tx, args.filter.tag_slugs()?, None, None)?
+            .into_iter()
+            .filter(|n| n.link_id.is_none())
+            .filter(|n| filter.after.is_none_or(|after| n.created_at >= after))
+            .filter(|n| filter.before.is_none_or(|before| n.created_at < before))
+            .collect()
+    } else {
+        vec![]
+    };
+    let export_detail = || ExportDetail {
+        links: items
+            .iter()
+            .map(|i| link_detail(&i.link, &i.tags, &i.note, &i.related_links))
+            .collect(),
+        notes: args
+            .include_notes
+            .then(|| notes.iter().map(NoteDetail::new).collect()),
+    };
+    let output = match args.format {
+        ExportFormat::Csv => export_as_csv(&items, args.include_notes)?,
+        ExportFormat::Html => export_as_html(&items, args.qr_codes)?,
+        ExportFormat::Json => as_json(&export_detail(), json_pretty)? + "\n",
+        ExportFormat::Yaml => as_yaml(&export_detail())? + "\n",
+        ExportFormat::Markdown => export_as_markdown(&items, &notes),
+        ExportFormat::Org => export_as_org(&items, &notes),
+        ExportFormat::TomlTemplate => unreachable!("handled above"),
+    };
+    write!(out, "{output}")?;
+    Ok(())
+}
+
+fn export_as_csv(items: &[ExportItem], include_notes: bool) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    let mut header = vec!["url", "title", "description", "tags", "created_at"];
+    if include_notes {
+        header.push("note");
+    }
+    writer.write_record(&header)?;
+    for item in items {
+        let tags: Vec<&str> = item.tags.iter().map(|t| t.name.as_str()).collect();
+        let mut record = vec![
+            item.link.url.to_string(),
+            item.link.title.clone().unwrap_or_default(),
+            item.link.description.clone().unwrap_or_default(),
+            tags.join(", "),
+            item.link.created_at.to_string(),
+        ];
+        if include_notes {
+            record.push(
+                item.note
+                    .as_ref()
+                    .map(|n| n.content.trim().to_string())
+                    .unwrap_or_default(),
+            );
+        }
+        writer.write_record(&record)?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+fn export_as_html(items: &[ExportItem], qr_codes: bool) -> Result<String> {
+    use base64::Engine;
+    use util::escape_html;
+    let data_uri = |mime: &str, data: &str| {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+        format!("data:{mime};base64,{encoded}")
+    };
+    let mut rows = String::new();
+    for item in items {
+        let link = &item.link;
+        let url = escape_html(link.url.as_str());
+        let title = escape_html(link.title.as_deref().unwrap_or(link.url.as_str()));
+        let tag_slugs: Vec<&str> = item.tags.iter().map(|t| t.slug.as_str()).collect();
+        rows.push_str(&format!(
+            "<tr data-tags=\"{}\">\n<td class=\"date\">{}</td>\n<td>\n",
+            escape_html(&tag_slugs.join(" ")),
+            link.created_at.strftime("%F"),
+        ));
+        rows.push_str(&format!(
+            "<a href=\"{url}\">{title}</a>\n<div class=\"url\">{url}</div>\n"
+        ));
+        // URLs too long to encode just go without.
+        if let Some(svg) = qr_codes
+            .then(|| util::url_to_qr_svg(link.url.as_str()))
+            .flatten()
+        {
+            rows.push_str(&format!(
+                "<details class=\"qr\"><summary>QR code</summary><img src=\"{}\" alt=\"QR code for {url}\"></details>\n",
+                data_uri("image/svg+xml", &svg)
+            ));
+        }
+        if let Some(description) = &link.description {
+            rows.push_str(&format!(
+                "<div class=\"description\">{}</div>\n",
+                escape_html(description)
+            ));
+        }
+        for (related_url, relation) in &item.related_links {
+            let related_url = escape_html(related_url);
+            let relation = relation
+                .as_deref()
+                .map(|r| format!(" ({})", escape_html(r)))
+                .unwrap_or_default();
+            rows.push_str(&format!(
+                "<div class=\"related\">Related: <a href=\"{related_url}\">{related_url}</a>{relation}</div>\n"
+            ));
+        }
+        if let Some(note) = &item.note {
+            rows.push_str(&format!(
+                "<div class=\"note\">{}</div>\n",
+                escape_html(note.content.trim())
+            ));
+        }
+        rows.push_str("</td>\n<td class=\"tags\">");
+        for tag in &item.tags {
+            rows.push_str(&format!(
+                "<span class=\"tag\" data-tag=\"{}\">{}</span>",
+                escape_html(&tag.slug),
+                escape_html(&tag.name)
+            ));
+        }
+        rows.push_str("</td>\n</tr>\n");
+    }
+    // Inlined, like everything else, so the page stays a single file.
+    let manifest = serde_json::json!({
+        "name": APP_NAME,
+        "short_name": APP_NAME,
+        "start_url": ".",
+        "display": "standalone",
+    });
+    Ok(EXPORT_HTML_TEMPLATE
+        .replace(
+            "{{manifest}}",
+            &data_uri("application/manifest+json", &manifest.to_string()),
+        )
+        .replace("{{title}}", APP_NAME)
+        .replace("{{rows}}", rows.trim_end()))
+}
+
+fn export_as_markdown(items: &[ExportItem], notes: &[Note]) -> String {
+    let escape = |s: &str| s.replace('[', "\\[").replace(']', "\\]");
+    let mut doc = format!("# {APP_NAME}\n");
+    for item in items {
+        let link = &item.link;
+        let title = escape(link.title.as_deref().unwrap_or(link.url.as_str()));
+        doc.push_str(&format!("\n## [{title}](<{}>)\n\n", link.url));
+        if let Some(description) = &link.description {
+            doc.push_str(&format!("{}\n\n", description.trim()));
+        }
+        doc.push_str(&format!("- Added: {}\n", link.created_at.strftime("%F")));
+        if !item.tags.is_empty() {
+            let tags: Vec<&str> = item.tags.iter().map(|t| t.name.as_str()).collect();
+            doc.push_str(&format!("- Tags: {}\n", tags.join(", ")));
+        }
+        for (related_url, relation) in &item.related_links {
+            match relation {
+                Some(relation) => {
+                    doc.push_str(&format!("- Related: <{related_url}> ({relation})\n"))
+                }
+                None => doc.push_str(&format!("- Related: <{related_url}>\n")),
+            }
+        }
+        if let Some(note) = &item.note {
+            doc.push_str(&format!("\n{}\n", note.content.trim()));
+        }
+    }
+    if !notes.is_empty() {
+        doc.push_str("\n# Notes\n");
+        for note in notes {
+            doc.push_str(&format!("\n## {}\n\n{}\n", note.title, note.content.trim()));
+        }
+    }
+    doc
+}
+
+fn export_as_org(items: &[ExportItem], notes: &[Note]) -> String {
+    // A leading `*` would start a new heading, so escape it with a comma.
+    let body = |s: &str| {
+        s.trim()
+            .lines()
+            .map(|l| {
+                if l.starts_with('*') {
+                    format!(",{l}\n")
+                } else {
+                    format!("{l}\n")
+                }
+            })
+            .collect::<String>()
+    };
+    let mut doc = format!("#+TITLE: {APP_NAME}\n");
+    for item in items {
+        let link = &item.link;
+        let title = link.title.as_deref().unwrap_or(link.url.as_str());
+        doc.push_str(&format!(
+            "\n* [[{}][{}]]",
+            link.url,
+            title.replace('[', "(").replace(']', ")")
+        ));
+        if !item.tags.is_empty() {
+            // Org tags can't contain hyphens.
+            let tags: Vec<String> = item.tags.iter().map(|t| t.slug.replace('-', "_")).collect();
+            doc.push_str(&format!(" :{}:", tags.join(":")));
+        }
+        doc.push_str(&format!(
+            "\n:PROPERTIES:\n:CREATED: [{}]\n:END:\n",
+            link.created_at.strftime("%F")
+        ));
+        if let Some(description) = &link.description {
+            doc.push_str(&body(description));
+        }
+        if !item.related_links.is_empty() {
+            doc.push_str("** Related\n");
+            for (related_url, relation) in &item.related_links {
+                match relation {
+                    Some(relation) => doc.push_str(&format!("- [[{related_url}]] ({relation})\n")),
+                    None => doc.push_str(&format!("- [[{related_url}]]\n")),
+                }
+            }
+        }
+        if let Some(note) = &item.note {
+            doc.push_str("** Note\n");
+            doc.push_str(&body(&note.content));
+        }
+    }
+    if !notes.is_empty() {
+        doc.push_str("\n* Notes\n");
+        for note in notes {
+            doc.push_str(&format!("** {}\n", note.title));
+            doc.push_str(&body(&note.content));
+        }
+    }
+    doc
+}
+
+fn import_cmd(
+    conn: &mut Connection,
+    args: &ImportArgs,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    if !matches!(args.format, ImportFormat::Csv) && args.csv.is_set() {
+        anyhow::bail!("The CSV column options only apply to --format csv");
+    }
+    let contents = std::fs::read_to_string(&args.file)?;
+    let links = match args.format {
+        ImportFormat::Csv => csv_import_links(&contents, &args.csv, args.no_fetch)?,
+        ImportFormat::Toml => {
+            let import: ImportFile = toml::from_str(&contents)?;
+            import
+                .links
+                .into_iter()
+                .map(|link| AddArgs {
+                    link: Some(link.url),
+                    tag: link.tags,
+                    description: link.description,
+                    title: link.title,
+                    message: link.note,
+                    related_link: link.related_links,
+                    relation: link.relations,
+                    no_fetch: args.no_fetch,
+                    source: Some("import-toml".to_string()),
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>()
+        }
+        ImportFormat::Wallabag => {
+            let entries: Vec<WallabagEntry> = serde_json::from_str(&contents)?;
+            let mut links = vec![];
+            for entry in entries {
+                if entry.is_archived.is_set() && !args.include_archived {
+                    continue;
+                }
+                let created_at = entry
+                    .created_at
+                    .as_deref()
+                    .map(util::parse_date)
+                    .transpose()
+                    .with_context(|| format!("Invalid created_at for <{}>", entry.url))?;
+                links.push(AddArgs {
+                    link: Some(entry.url),
+                    tag: entry.tags.into_iter().map(|t| t.label).collect(),
+                    title: entry.title.filter(|t| !t.is_empty()),
+                    no_fetch: args.no_fetch,
+                    created_at,
+                    html: entry.content.filter(|c| !c.trim().is_empty()),
+                    favorite: entry.is_starred.is_set(),
+                    source: Some("import-wallabag".to_string()),
+                    ..Default::default()
+                });
+            }
+            links
+        }
+    };
+    add_unsaved(conn, links, config, out)
+}
+
+fn csv_import_links(contents: &str, args: &CsvImportArgs, no_fetch: bool) -> Result<Vec<AddArgs>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(!args.no_header)
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+    let headers = if args.no_header {
+        None
+    } else {
+        Some(reader.headers()?.clone())
+    };
+    let column = |flag: &str, spec: Option<&str>| -> Result<Option<usize>> {
+        let Some(spec) = spec else {
+            return Ok(None);
+        };
+        let index = match &headers {
+            Some(headers) => headers
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(spec.trim()))
+                .ok_or_else(|| anyhow!("No `{spec}` column for --{flag}"))?,
+            None => match spec.parse::<usize>() {
+                Ok(n) if n > 0 => n - 1,
+                _ => anyhow::bail!("--{flag} must be a column number (from 1) with --no-header"),
+            },
+        };
+        Ok(Some(index))
+    };
+    let url_spec = match (&args.url_col, args.no_header) {
+        (Some(spec), _) => spec.as_str(),
+        (None, false) => "url",
+        (None, true) => anyhow::bail!("--no-header needs --url-col"),
+    };
+    let url_col = column("url-col", Some(url_spec))?.unwrap_or_default();
+    let title_col = column("title-col", args.title_col.as_deref())?;
+    let description_col = column("description-col", args.description_col.as_deref())?;
+    let tags_col = column("tags-col", args.tags_col.as_deref())?;
+    let created_at_col = column("created-at-col", args.created_at_col.as_deref())?;
+
+    let mut links = vec![];
+    for record in reader.records() {
+        let record = record?;
+        let field = |col: Option<usize>| {
+            col.and_then(|i| record.get(i))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+        let Some(url) = field(Some(url_col)) else {
+            continue;
+        };
+        let created_at = field(created_at_col)
+            .map(util::parse_date)
+            .transpose()
+            .with_context(|| format!("Invalid created_at for <{url}>"))?;
+        let tags = field(tags_col)
+            .map(|tags| {
+                tags.split(args.tags_sep.as_deref().unwrap_or(","))
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        links.push(AddArgs {
+            link: Some(url.to_string()),
+            tag: tags,
+            title: field(title_col).map(String::from),
+            description: field(description_col).map(String::from),
+            no_fetch,
+            created_at,
+            source: Some("import-csv".to_string()),
+            ..Default::default()
+        });
+    }
+    Ok(links)
+}
+
+fn migrate_from_browser_history_cmd(
+    conn: &mut Connection,
+    args: &BrowserHistoryArgs,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let history = if args.profile.is_dir() {
+        args.profile.join(args.browser.history_file())
+    } else {
+        args.profile.clone()
+    };
+    if !history.is_file() {
+        return Err(anyhow!("No history found at {}", history.to_string_lossy()));
+    }
+    // The browser holds a lock on its history while it runs, so read a copy.
+    // Recent visits may still be in the write-ahead log rather than the main
+    // file, so the log and its index are copied alongside it.
+    let copy = std::env::temp_dir().join(format!("meowpad-history-{}.sqlite", Uuid::now_v7()));
+    let with_suffix = |path: &Path, suffix: &str| {
+        let mut path = path.as_os_str().to_owned();
+        path.push(suffix);
+        PathBuf::from(path)
+    };
+    std::fs::copy(&history, &copy)?;
+    let mut copies = vec![copy.clone()];
+    for suffix in ["-wal", "-shm"] {
+        let sibling = with_suffix(&history, suffix);
+        if sibling.is_file() {
+            std::fs::copy(&sibling, with_suffix(&copy, suffix))?;
+            copies.push(with_suffix(&copy, suffix));
+        }
+    }
+    let links = read_browser_history(&copy, args);
+    for path in copies {
+        // SQLite may have folded the log into the copy and removed it already.
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    add_unsaved(conn, links?, config, out)
+}
+
+fn read_browser_history(path: &std::path::Path, args: &BrowserHistoryArgs) -> Result<Vec<AddArgs>> {
+    let history = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let since = match args.since_days {
+        Some(days) => args
+            .browser
+            .history_time(Timestamp::now().checked_sub(Span::new().hours(24 * i64::from(days)))?),
+        None => 0,
+    };
+    let mut stmt = history.prepare(args.browser.history_query())?;
+    let mut rows = stmt.query([since])?;
+    let mut links = vec![];
+    while let Some(row) = rows.next()? {
+        let url: String = row.get(0)?;
+        // Skip browser-internal pages, local files, and the like.
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            continue;
+        }
+        let title: Option<String> = row.get(1)?;
+        let visited: Option<i64> = row.get(2)?;
+        links.push(AddArgs {
+            link: Some(url),
+            title: title.filter(|t| !t.is_empty()),
+            // History can run to hundreds of pages; only the URLs are stored.
+            no_fetch: true,
+            created_at: visited.and_then(|v| args.browser.visit_time(v)),
+            source: Some("import-browser".to_string()),
+            ..Default::default()
+        });
+    }
+    Ok(links)
+}
+
+// Adds the links that aren't saved yet, one transaction each.
+fn add_unsaved(
+    conn: &mut Connection,
+    links: Vec<AddArgs>,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let mut to_add = vec![];
+    {
+        let tx = conn.transaction()?;
+        for link in links {
+            let target = link.target();
+            let existing =
+                db::get_link(&tx, db::TermOrId::Term(&target), db::IsPrimary::PrimaryOnly)?;
+            if existing.is_some() {
+                writeln!(out, "Skipping <{target}>; already saved")?;
+                continue;
+            }
+            to_add.push(link);
+        }
+    }
+    add_each(conn, to_add, false, config, out)
+}
+
+fn ingest_cmd(
+    conn: &mut Connection,
+    args: &IngestArgs,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use std::io::BufRead;
+    let mut to_add = vec![];
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() {
+            to_add.push(AddArgs {
+                link: Some(line.to_string()),
+                tag: args.tag.clone(),
+                no_fetch: args.no_fetch,
+                source: Some("ingest".to_string()),
+                ..Default::default()
+            });
+        }
+    }
+    add_each(conn, to_add, args.fail_fast, config, out)
+}
+
+// Adds each link in its own transaction, so that one bad link doesn't undo
+// the rest; failures are reported together at the end unless `fail_fast`.
+fn add_each(
+    conn: &mut Connection,
+    links: Vec<AddArgs>,
+    fail_fast: bool,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let total = links.len();
+    let mut failures: Vec<(String, anyhow::Error)> = vec![];
+    for (i, add_args) in links.into_iter().enumerate() {
+        let tx = conn.transaction()?;
+        match add_link(&tx, &add_args, config) {
+            Ok(url) => {
+                tx.commit()?;
+                writeln!(out, "[{}/{total}] Added <{url}>", i + 1)?;
+            }
+            Err(e) => {
+                let target = add_args.target();
+                writeln!(out, "[{}/{total}] Failed <{target}>: {e}", i + 1)?;
+                if fail_fast {
+                    return Err(e.context(format!("Unable to add <{target}>")));
+                }
+                failures.push((target, e));
+            }
+        }
+    }
+    if failures.is_empty() {
+        writeln!(out, "Added {total} links")?;
+        return Ok(());
+    }
+    eprintln!("Unable to add {} of {total} links:", failures.len());
+    for (url, e) in &failures {
+        eprintln!("  <{url}>: {e:#}");
+    }
+    Err(anyhow!("{} of {total} links failed", failures.len()))
+}
+
+fn list_cmd(
+    tx: &Transaction,
+    args: &ListArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let output = list_output(tx, args, json_pretty, ui_config)?;
+    writeln!(out, "{output}")?;
+    Ok(())
+}
+
+fn list_output(
+    tx: &Transaction,
+    args: &ListArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+) -> Result<String> {
+    if args.count {
+        let filter = db::LinkFilter {
+            include_secondary: args.include_secondary,
+            ..args.filter.link_filter()
+        };
+        let count = db::count_links(tx, args.filter.tag_slugs()?, None, &filter)?;
+        return count_output(count, &args.format, json_pretty);
+    }
+    let filter = db::LinkFilter {
+        favorites_first: args.favorites_first,
+        pinned_first: args.pinned_first,
+        include_secondary: args.include_secondary,
+        ..args.filter.link_filter()
+    };
+    let items = db::get_links(tx, args.filter.tag_slugs()?, None, &filter, false)?;
+    if let Some(group_by) = args.group_by {
+        return grouped_list_output(tx, items, group_by, args, json_pretty, ui_config);
+    }
+    let output = match args.format {
+        LinkListFormat::Table if args.wide => {
+            list_as_wide_table(tx, items, &wide_columns(), ui_config)?
+        }
+        LinkListFormat::Table => list_as_table(tx, items, &args.columns, ui_config)?,
+        LinkListFormat::Json => as_json(&items, json_pretty)?,
+        LinkListFormat::Yaml => as_yaml(&items)?,
+        LinkListFormat::Tsv => links_as_tsv(tx, &items)?,
+        LinkListFormat::Atom => {
+            let title = if args.filter.tag.is_empty() {
+                "Links".to_string()
+            } else {
+                format!("Links tagged {}", args.filter.tag.join(", "))
+            };
+            links_as_atom(tx, &items, &title)?
+        }
+    };
+    Ok(output)
+}
+
+#[derive(Debug, Serialize)]
+struct LinkGroup {
+    group: Option<String>,
+    links: Vec<Link>,
+}
+
+// Groups are in alphabetical order, except months, which are newest first.
+// Links without a group (no host, or no tags) come last. Within a group,
+// links keep the order they were listed in.
+fn group_links(tx: &Transaction, items: Vec<Link>, group_by: GroupBy) -> Result<Vec<LinkGroup>> {
+    let mut groups: BTreeMap<Option<String>, Vec<Link>> = BTreeMap::new();
+    for item in items {
+        let key = match group_by {
+            GroupBy::Domain => item.url.host_str().map(str::to_string),
+            GroupBy::Tag => db::tags_for_item(tx, &item.id)?
+                .into_iter()
+                .min_by(|a, b| a.slug.cmp(&b.slug))
+                .map(|t| t.name),
+            GroupBy::Month => Some(item.created_at.strftime("%Y-%m").to_string()),
+        };
+        groups.entry(key).or_default().push(item);
+    }
+    let ungrouped = groups.remove(&None);
+    let mut groups: Vec<LinkGroup> = groups
+        .into_iter()
+        .map(|(group, links)| LinkGroup { group, links })
+        .collect();
+    if group_by == GroupBy::Month {
+        groups.reverse();
+    }
+    if let Some(links) = ungrouped {
+        groups.push(LinkGroup { group: None, links });
+    }
+    Ok(groups)
+}
+
+fn grouped_list_output(
+    tx: &Transaction,
+    items: Vec<Link>,
+    group_by: GroupBy,
+    args: &ListArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+) -> Result<String> {
+    let groups = group_links(tx, items, group_by)?;
+    Ok(match args.format {
+        LinkListFormat::Table => {
+            let wide_columns = if args.wide { wide_columns() } else { vec![] };
+            let mut sections = vec![];
+            for group in groups {
+                let heading = group.group.unwrap_or_else(|| {
+                    match group_by {
+                        GroupBy::Domain => "(no domain)",
+                        GroupBy::Tag => "(untagged)",
+                        GroupBy::Month => "(no date)",
+                    }
+                    .to_string()
+                });
+                let count = group.links.len();
+                let table = if args.wide {
+                    list_as_wide_table(tx, group.links, &wide_columns, ui_config)?
+                } else {
+                    list_as_table(tx, group.links, &args.columns, ui_config)?
+                };
+                sections.push(format!("{heading} ({count})\n{table}"));
+            }
+            sections.join("\n\n")
+        }
+        LinkListFormat::Json => as_json(&groups, json_pretty)?,
+        LinkListFormat::Yaml => as_yaml(&groups)?,
+        // These formats have no headings, so the links are just ordered by group.
+        LinkListFormat::Tsv | LinkListFormat::Atom => {
+            let items: Vec<Link> = groups.into_iter().flat_map(|g| g.links).collect();
+            if matches!(args.format, LinkListFormat::Tsv) {
+                links_as_tsv(tx, &items)?
+            } else {
+                links_as_atom(tx, &items, "Links")?
+            }
+        }
+    })
+}
+
+fn links_as_tsv(tx: &Transaction, items: &[Link]) -> Result<String> {
+    let mut rows = vec![];
+    for item in items {
+        let tags = db::tags_for_item(tx, &item.id)?;
+        rows.push(vec![
+            item.id.to_string(),
+            item.url.to_string(),
+            item.title.clone().unwrap_or_default(),
+            item.created_at.to_string(),
+            tag_slugs(&tags),
+        ]);
+    }
+    Ok(as_tsv(&rows))
+}
+
+/// Render links as an Atom 1.0 feed. The feed id is derived from the
+/// database path and the title, so it stays the same between runs.
+fn links_as_atom(tx: &Transaction, items: &[Link], title: &str) -> Result<String> {
+    use quick_xml::events::{BytesDecl, BytesText, Event};
+    use quick_xml::Writer;
+
+    let feed_key = format!("{}#{title}", tx.path().unwrap_or_default());
+    let feed_id = Uuid::new_v5(&Uuid::NAMESPACE_URL, feed_key.as_bytes());
+    let updated = items
+        .iter()
+        .map(|link| link.modified_at)
+        .max()
+        .unwrap_or_else(Timestamp::now);
+    let mut entries = vec![];
+    for link in items {
+        entries.push((link, db::tags_for_item(tx, &link.id)?));
+    }
+
+    let mut writer = Writer::new_with_indent(vec![], b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
+    writer
+        .create_element("feed")
+        .with_attribute(("xmlns", "http://www.w3.org/2005/Atom"))
+        .write_inner_content(|writer| {
+            writer
+                .create_element("id")
+                .write_text_content(BytesText::new(&format!("urn:uuid:{feed_id}")))?;
+            writer
+                .create_element("title")
+                .write_text_content(BytesText::new(title))?;
+            writer
+                .create_element("updated")
+                .write_text_content(BytesText::new(&updated.to_string()))?;
+            writer
+                .create_element("author")
+                .write_inner_content(|writer| {
+                    writer
+                        .create_element("name")
+                        .write_text_content(BytesText::new(APP_NAME))?;
+                    Ok(())
+                })?;
+            writer
+                .create_element("generator")
+                .with_attribute(("version", env!("CARGO_PKG_VERSION")))
+                .write_text_content(BytesText::new(APP_NAME))?;
+            for (link, tags) in &entries {
+                writer
+                    .create_element("entry")
+                    .write_inner_content(|writer| {
+                        writer
+                            .create_element("id")
+                            .write_text_content(BytesText::new(&format!("urn:uuid:{}", link.id)))?;
+                        writer
+                            .create_element("title")
+                            .write_text_content(BytesText::new(
+                                link.title.as_deref().unwrap_or(link.url.as_str()),
+                            ))?;
+                        writer
+                            .create_element("link")
+                            .with_attribute(("href", link.url.as_str()))
+                            .write_empty()?;
+                        if let Some(description) = &link.description {
+                            writer
+                                .create_element("summary")
+                                .write_text_content(BytesText::new(description))?;
+                        }
+                        writer
+                            .create_element("published")
+                            .write_text_content(BytesText::new(&link.created_at.to_string()))?;
+                        writer
+                            .create_element("updated")
+                            .write_text_content(BytesText::new(&link.modified_at.to_string()))?;
+                        for tag in tags {
+                            writer
+                                .create_element("category")
+                                .with_attribute(("term", tag.slug.as_str()))
+                                .with_attribute(("label", tag.name.as_str()))
+                                .write_empty()?;
+                        }
+                        Ok(())
+                    })?;
+            }
+            Ok(())
+        })?;
+    Ok(String::from_utf8(writer.into_inner())?)
+}
+
+fn link_as_csv(
+    link: &Link,
+    tags: &[Tag],
+    note: &Option<Note>,
+    related_links: &[(String, Option<String>)],
+) -> Result<String> {
+    let related_links = related_links
+        .iter()
+        .map(|(url, relation)| match relation {
+            Some(relation) => format!("{url}:{relation}"),
+            None => url.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(";");
+    // The note goes last, so that the other columns keep their positions
+    // whether or not there is one.
+    as_csv(
+        &[
+            "id",
+            "url",
+            "title",
+            "description",
+            "content",
+            "created_at",
+            "modified_at",
+            "tags",
+            "related_links",
+            "note",
+        ],
+        &[
+            link.id.to_string(),
+            link.url.to_string(),
+            link.title.clone().unwrap_or_default(),
+            link.description.clone().unwrap_or_default(),
+            link.content.clone().unwrap_or_default(),
+            link.created_at.to_string(),
+            link.modified_at.to_string(),
+            tags.iter()
+                .map(|t| t.name.as_str())
+                .collect::<Vec<_>>()
+                .join(";"),
+            related_links,
+            note.as_ref()
+                .map(|n| n.content.trim().to_string())
+                .unwrap_or_default(),
+        ],
+    )
+}
+
+fn as_csv<const N: usize>(header: &[&str; N], row: &[String; N]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(header)?;
+    writer.write_record(row)?;
+    // Output is written with writeln!, so drop the record's own newline.
+    Ok(String::from_utf8(writer.into_inner()?)?
+        .trim_end()
+        .to_string())
+}
+
+fn link_as_tsv(
+    link: &Link,
+    tags: &[Tag],
+    note: &Option<Note>,
+    related_links: &[(String, Option<String>)],
+) -> String {
+    let rows = [
+        ("id", link.id.to_string()),
+        ("url", link.url.to_string()),
+        ("title", link.title.clone().unwrap_or_default()),
+        ("description", link.description.clone().unwrap_or_default()),
+        ("created_at", link.created_at.to_string()),
+        (
+            "read_at",
+            link.read_at.map(|t| t.to_string()).unwrap_or_default(),
+        ),
+        (
+            "local_archive_path",
+            link.local_archive_path.clone().unwrap_or_default(),
+        ),
+        ("tags", tag_slugs(tags)),
+        (
+            "related_links",
+            related_links
+                .iter()
+                .map(|rl| rl.0.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+        (
+            "note",
+            note.as_ref()
+                .map(|n| n.content.trim().to_string())
+                .unwrap_or_default(),
+        ),
+    ];
+    as_tsv(
+        &rows
+            .into_iter()
+            .map(|(key, value)| vec![key.to_string(), value])
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn tag_slugs(tags: &[Tag]) -> String {
+    tags.iter()
+        .map(|t| t.slug.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn as_tsv(rows: &[Vec<String>]) -> String {
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .map(|field| util::tsv_field(field))
+                .collect::<Vec<_>>()
+                .join("\t")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn link_as_table(
+    link: Link,
+    tags: Vec<Tag>,
+    note: Option<Note>,
+    related_links: Vec<(String, Option<String>)>,
+    ui_config: &UiConfig,
+) -> Result<String> {
+    let mut table = ui::new_table(ui_config, TableStyle::Utf8Full);
+    table.add_row(vec![
+        "Title",
+        link.title.as_ref().unwrap_or(&"".to_string()),
+    ]);
+    table.add_row(vec!["URL", link.url.as_ref()]);
+    table.add_row(vec![
+        "Description",
+        link.description.as_ref().unwrap_or(&"".to_string()),
+    ]);
+    table.add_row(vec![
+        "Added".to_string(),
+        ui::format_timestamp(&link.created_at, &ui_config.date_format),
+    ]);
+    table.add_row(vec![
+        "Status".to_string(),
+        match link.read_at {
+            Some(read_at) => format!(
+                "Read {}",
+                ui::format_timestamp(&read_at, &ui_config.date_format)
+            ),
+            None => "Unread".to_string(),
+        },
+    ]);
+    if let Some(source) = &link.source {
+        table.add_row(vec!["Source", source.as_str()]);
+    }
+    if let Some(path) = &link.local_archive_path {
+        table.add_row(vec!["Saved Copy", path.as_str()]);
+    }
+    if !tags.is_empty() {
+        table.add_row(vec!["Tags".to_string(), ui::tag_names(&tags, ui_config)]);
+    }
+    if !related_links.is_empty() {
+        table.add_row(vec![
+            "See Also".to_string(),
+            related_links
+                .iter()
+                .map(|rl| {
+                    if let Some(relation) = &rl.1 {
+                        format!("{} ({relation})", rl.0)
+                    } else {
+                        rl.0.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ]);
+    }
+    if let Some(note) = note {
+        let content = note.content.as_str().trim();
+        table.add_row(vec!["Note", content]);
+        let stats = util::text_stats(content);
+        table.add_row(vec![
+            "Stats".to_string(),
+            format!(
+                "{} words, {} characters, {} lines",
+                stats.words, stats.chars, stats.lines
+            ),
+        ]);
+    }
+    Ok(table.to_string())
+}
+
+// Like link_as_table, but each related link gets its own block with its
+// title, description, and tags.
+fn expanded_link_as_table(
+    link: &Link,
+    tags: &[Tag],
+    note: &Option<Note>,
+    related: &[(Link, Option<String>, Vec<Tag>)],
+    ui_config: &UiConfig,
+) -> Result<String> {
+    let mut table = link_as_table(link.clone(), tags.to_vec(), note.clone(), vec![], ui_config)?;
+    if related.is_empty() {
+        return Ok(table);
+    }
+    let mut see_also = ui::new_table(ui_config, TableStyle::Utf8Full);
+    see_also.set_header(vec!["See Also", "Relation", "Description", "Tags"]);
+    for (related, relation, tags) in related {
+        let title = related.title.as_deref().filter(|t| !t.is_empty());
+        see_also.add_row(vec![
+            match title {
+                Some(title) => format!("{title}\n{}", related.url),
+                None => related.url.to_string(),
+            },
+            relation.clone().unwrap_or_default(),
+            related.description.clone().unwrap_or_default(),
+            ui::tag_names(tags, ui_config),
+        ]);
+    }
+    table.push('\n');
+    table.push_str(&see_also.to_string());
+    Ok(table)
+}
+
+fn link_detail<'a>(
+    link: &'a Link,
+    tags: &'a [Tag],
+    note: &'a Option<Note>,
+    related_links: &'a [(String, Option<String>)],
+) -> LinkDetail<'a> {
+    LinkDetail {
+        link,
+        tags: tags.iter().map(|t| t.name.as_str()).collect(),
+        note: note.as_ref().map(NoteDetail::new),
+        related_links: related_link_details(related_links),
+    }
+}
+
+fn as_json<T: Serialize>(value: &T, pretty: bool) -> Result<String> {
+    Ok(if pretty {
+        serde_json::to_string_pretty(value)?
+    } else {
+        serde_json::to_string(value)?
+    })
+}
+
+fn as_yaml<T: Serialize>(value: &T) -> Result<String> {
+    // Output is written with writeln!, so drop the trailing newline serde_yaml emits.
+    Ok(serde_yaml::to_string(value)?.trim_end().to_string())
+}
+
+fn count_output(count: usize, format: &LinkListFormat, json_pretty: bool) -> Result<String> {
+    let output = match format {
+        LinkListFormat::Table => count.to_string(),
+        LinkListFormat::Json => as_json(&serde_json::json!({ "count": count }), json_pretty)?,
+        LinkListFormat::Yaml => as_yaml(&serde_json::json!({ "count": count }))?,
+        LinkListFormat::Tsv => count.to_string(),
+        LinkListFormat::Atom => count.to_string(),
+    };
+    Ok(output)
+}
+
+fn list_as_table(
+    tx: &Transaction,
+    items: Vec<Link>,
+    columns: &[ListColumn],
+    ui_config: &UiConfig,
+) -> Result<String> {
+    let mut table = ui::new_table(ui_config, TableStyle::Utf8Borders);
+    table.set_header(columns.iter().map(|c| c.header()).collect::<Vec<_>>());
+    for item in &items {
+        let mut row = vec![];
+        for column in columns {
+            row.push(list_cell(tx, item, *column, ui_config)?);
+        }
+        table.add_row(row);
+    }
+    Ok(table.to_string())
+}
+
+fn list_cell(
+    tx: &Transaction,
+    item: &Link,
+    column: ListColumn,
+    ui_config: &UiConfig,
+) -> Result<String> {
+    Ok(match column {
+        ListColumn::Id => item.id.to_string(),
+        ListColumn::Url if item.is_favorite => format!("★ {}", item.url),
+        ListColumn::Url if !item.is_primary => format!("{} (related)", item.url),
+        ListColumn::Url => item.url.to_string(),
+        ListColumn::Title => item.title.clone().unwrap_or_default(),
+        ListColumn::Created => ui::format_timestamp(&item.created_at, &ui_config.date_format),
+        ListColumn::Modified => ui::format_timestamp(&item.modified_at, &ui_config.date_format),
+        ListColumn::Description => item.description.clone().unwrap_or_default(),
+        ListColumn::Tags => ui::tag_names(&db::tags_for_item(tx, &item.id)?, ui_config),
+    })
+}
+
+// Narrower terminals than this can't fit a useful description column.
+const WIDE_TABLE_MIN_WIDTH: u16 = 120;
+
+// The columns of the wide list table, each with the most characters it
+// shows.
+// Dates are never cut, since date_format can make them any length.
+fn wide_columns() -> Vec<(ListColumn, Option<usize>)> {
+    let mut columns = vec![
+        (ListColumn::Url, Some(40)),
+        (ListColumn::Title, Some(30)),
+        (ListColumn::Description, Some(50)),
+        (ListColumn::Created, None),
+    ];
+    // Output that isn't going to a terminal has no width to fit.
+    if let Ok((width, _)) = terminal::size() {
+        if std::io::stdout().is_terminal() && width < WIDE_TABLE_MIN_WIDTH {
+            eprintln!(
+                "Terminal is narrower than {WIDE_TABLE_MIN_WIDTH} columns; leaving out descriptions"
+            );
+            columns.retain(|(c, _)| *c != ListColumn::Description);
+        }
+    }
+    columns
+}
+
+// The list table with descriptions, each text column cut to a fixed length.
+fn list_as_wide_table(
+    tx: &Transaction,
+    items: Vec<Link>,
+    columns: &[(ListColumn, Option<usize>)],
+    ui_config: &UiConfig,
+) -> Result<String> {
+    let mut table = ui::new_table(ui_config, TableStyle::Utf8Borders);
+    table.set_header(columns.iter().map(|(c, _)| c.header()).collect::<Vec<_>>());
+    for item in &items {
+        let mut row = vec![];
+        for (column, max_chars) in columns {
+            let cell = list_cell(tx, item, *column, ui_config)?;
+            row.push(match max_chars {
+                Some(max_chars) => util::truncate_chars(&cell, *max_chars),
+                None => cell,
+            });
+        }
+        table.add_row(row);
+    }
+    Ok(table.to_string())
+}
+
+fn tags_import_cmd(tx: &Transaction, args: &TagsImportArgs, out: &mut dyn Write) -> Result<()> {
+    let contents = std::fs::read_to_string(&args.file)?;
+    let now = now()?;
+    let mut created = 0;
+    let mut existing = 0;
+    for line in contents.lines() {
+        let name = line.trim();
+        if name.is_empty() || name.starts_with('#') {
+            continue;
+        }
+        let slug = util::slugify(name)?;
+        if db::get_tag_id_by_slug(tx, &slug)?.is_some() {
+            existing += 1;
+        } else {
+            db::require_tag(tx, name, &slug, &now)?;
+            created += 1;
+        }
+    }
+    writeln!(
+        out,
+        "Created {created} tag{}; {existing} already existed",
+        if created == 1 { "" } else { "s" }
+    )?;
+    Ok(())
+}
+
+fn tags_list_cmd(
+    tx: &Transaction,
+    args: &TagsListArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let tags = db::get_tags(tx)?;
+    let output = match args.format {
+        ListOutputFormat::Table => tags_as_table(&tags, ui_config),
+        ListOutputFormat::Json => as_json(&tags, json_pretty)?,
+        ListOutputFormat::Yaml => as_yaml(&tags)?,
+        ListOutputFormat::Tsv => as_tsv(
+            &tags
+                .iter()
+                .map(|tag| {
+                    vec![
+                        tag.slug.clone(),
+                        tag.name.clone(),
+                        tag.description.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect::<Vec<_>>(),
+        ),
+    };
+    writeln!(out, "{output}")?;
+    Ok(())
+}
+
+fn tags_as_table(tags: &[Tag], ui_config: &UiConfig) -> String {
+    let mut table = ui::new_table(ui_config, TableStyle::Utf8Borders);
+    table.set_header(vec!["Name", "Slug", "Description"]);
+    for tag in tags {
+        table.add_row(vec![
+            ui::tag_name(tag, ui_config),
+            tag.slug.clone(),
+            tag.description.clone().unwrap_or_default(),
+        ]);
+    }
+    table.to_string()
+}
+
+fn tags_orphans_cmd(
+    tx: &Transaction,
+    args: &TagsOrphansArgs,
+    ui_config: &UiConfig,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let orphans = db::get_orphan_tags(tx)?;
+    if orphans.is_empty() {
+        writeln!(out, "No orphaned tags")?;
+        return Ok(());
+    }
+    if !args.delete {
+        writeln!(out, "{}", tags_as_table(&orphans, ui_config))?;
+        return Ok(());
+    }
+    let names = orphans
+        .iter()
+        .map(|t| format!("`{}`", t.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if args.dry_run {
+        writeln!(
+            out,
+            "Would delete {} orphaned tag(s): {names}",
+            orphans.len()
+        )?;
+    } else {
+        for tag in &orphans {
+            db::delete_tag(tx, &tag.id)?;
+        }
+        writeln!(out, "Deleted {} orphaned tag(s): {names}", orphans.len())?;
+    }
+    Ok(())
+}
+
+fn tags_set_description_cmd(
+    tx: &Transaction,
+    args: &TagsSetDescriptionArgs,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let Some(tag) = db::get_tag_by_slug(tx, &util::slugify(&args.tag)?)? else {
+        writeln!(out, "Tag `{}` not found", args.tag)?;
+        return Ok(());
+    };
+    let description = Some(args.description.trim()).filter(|d| !d.is_empty());
+    db::set_tag_description(tx, &tag.id, description)?;
+    match description {
+        Some(_) => writeln!(out, "Described tag `{}`", tag.name)?,
+        None => writeln!(out, "Cleared description of tag `{}`", tag.name)?,
+    }
+    Ok(())
+}
+
+fn tags_set_parent_cmd(
+    tx: &Transaction,
+    args: &TagsSetParentArgs,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let Some(tag) = db::get_tag_by_slug(tx, &util::slugify(&args.tag)?)? else {
+        writeln!(out, "Tag `{}` not found", args.tag)?;
+        return Ok(());
+    };
+    let Some(parent_name) = &args.parent else {
+        db::set_tag_parent(tx, &tag.id, None)?;
+        writeln!(out, "Made tag `{}` top-level", tag.name)?;
+        return Ok(());
+    };
+    let Some(parent) = db::get_tag_by_slug(tx, &util::slugify(parent_name)?)? else {
+        writeln!(out, "Tag `{parent_name}` not found")?;
+        return Ok(());
+    };
+    if db::tag_descendant_ids(tx, &tag.id)?.contains(&parent.id) {
+        anyhow::bail!(
+            "Can't make `{}` a child of itself or one of its descendants",
+            tag.name
+        );
+    }
+    db::set_tag_parent(tx, &tag.id, Some(&parent.id))?;
+    writeln!(out, "Made tag `{}` a child of `{}`", tag.name, parent.name)?;
+    Ok(())
+}
+
+fn tags_tree_cmd(tx: &Transaction, out: &mut dyn Write) -> Result<()> {
+    fn write_subtree(
+        tags: &[Tag],
+        parent_id: Option<&TableId>,
+        depth: usize,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        for tag in tags.iter().filter(|t| t.parent_id.as_ref() == parent_id) {
+            writeln!(out, "{}{}", "  ".repeat(depth), tag.name)?;
+            write_subtree(tags, Some(&tag.id), depth + 1, out)?;
+        }
+        Ok(())
+    }
+    let tags = db::get_tags(tx)?;
+    write_subtree(&tags, None, 0, out)
+}
+
+fn tags_show_cmd(
+    tx: &Transaction,
+    args: &TagsShowArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let Some(tag) = db::get_tag_by_slug(tx, &util::slugify(&args.tag)?)? else {
+        writeln!(out, "Tag `{}` not found", args.tag)?;
+        return Ok(());
+    };
+    let output = match args.format {
+        ListOutputFormat::Table => {
+            let mut table = ui::new_table(ui_config, TableStyle::Utf8Full);
+            table.add_row(vec!["Name", tag.name.as_str()]);
+            table.add_row(vec![
+                "Description",
+                tag.description.as_deref().unwrap_or_default(),
+            ]);
+            table.add_row(vec!["Slug", tag.slug.as_str()]);
+            if let Some(parent_id) = &tag.parent_id {
+                let parent = db::get_tags(tx)?.into_iter().find(|t| &t.id == parent_id);
+                table.add_row(vec![
+                    "Parent".to_string(),
+                    parent.map(|p| p.name).unwrap_or_default(),
+                ]);
+            }
+            table.add_row(vec![
+                "Added".to_string(),
+                ui::format_timestamp(&tag.created_at, &ui_config.date_format),
+            ]);
+            table.to_string()
+        }
+        // Structured output carries the tagged items too, for integrations.
+        ListOutputFormat::Json | ListOutputFormat::Yaml => {
+            let tag_with_items = db::get_tag_with_items(tx, &tag.slug)?
+                .ok_or_else(|| MeowpadError::NotFound(format!("Tag `{}`", args.tag)))?;
+            if matches!(args.format, ListOutputFormat::Json) {
+                as_json(&tag_with_items, json_pretty)?
+            } else {
+                as_yaml(&tag_with_items)?
+            }
+        }
+        ListOutputFormat::Tsv => {
+            let parent = match &tag.parent_id {
+                Some(parent_id) => db::get_tags(tx)?
+                    .into_iter()
+                    .find(|t| &t.id == parent_id)
+                    .map(|p| p.slug),
+                None => None,
+            };
+            as_tsv(&[
+                vec!["name".to_string(), tag.name.clone()],
+                vec!["slug".to_string(), tag.slug.clone()],
+                vec![
+                    "description".to_string(),
+                    tag.description.clone().unwrap_or_default(),
+                ],
+                vec!["parent".to_string(), parent.unwrap_or_default()],
+                vec!["created_at".to_string(), tag.created_at.to_string()],
+            ])
+        }
+    };
+    writeln!(out, "{output}")?;
+    Ok(())
+}
+
+fn note_cmd(tx: &Transaction, args: &NoteArgs, config: &Config, out: &mut dyn Write) -> Result<()> {
+    let now = now()?;
+    let title = match &args.title {
+        Some(given_title) => given_title,
+        None => &now,
+    };
+    let existing_note = db::get_note_by_title(tx, title)?;
+    let before = existing_note
+        .as_ref()
+        .map(|n| note_snapshot(tx, n))
+        .transpose()?;
+    let content = match (existing_note, &args.template) {
+        (Some(_), Some(_)) => {
+            anyhow::bail!("Note <{title}> already exists; templates only apply to new notes")
+        }
+        (Some(existing_note), None) => existing_note.content,
+        (None, Some(template_name)) => {
+            let template = config
+                .note_templates
+                .iter()
+                .find(|t| &t.name == template_name)
+                .ok_or_else(|| anyhow!("No note template named `{template_name}`"))?;
+            let date = Zoned::now().strftime("%F").to_string();
+            let url = util::validate_url(title, false)
+                .map(String::from)
+                .unwrap_or_default();
+            let vars = HashMap::from([
+                ("date", date.as_str()),
+                ("title", title.as_str()),
+                ("url", url.as_str()),
+            ]);
+            util::render_template(&template.content, &vars)
+        }
+        (None, None) => "".to_string(),
+    };
+    let note = if let Some(message) = &args.message {
+        if content.is_empty() {
+            message.clone()
+        } else {
+            let mut new_note = content;
+            new_note.push('\n');
+            new_note.push_str(message);
+            new_note
+        }
+    } else {
+        edit::edit(content)?
+    };
+    if note.is_empty() {
+        writeln!(out, "No note to add")?;
+    } else {
+        let note_id = db::upsert_note(tx, &note, title, None, &now, None)?;
+        for tag_name in &args.tag {
+            let tag_id = get_tag_id(tx, tag_name)?;
+            db::tag_note(tx, note_id, tag_id)?;
+        }
+        let payload = serde_json::json!({ "title": title, "before": before });
+        db::log_operation(tx, "note", "note", &note_id, &payload, &now)?;
+        writeln!(out, "Added note <{}>", &title)?;
+    }
+    Ok(())
 }
 
-#[derive(Debug, Subcommand)]
-enum UpdateCommands {
-    Refresh {
-        #[clap(flatten)]
-        refresh_args: UpdateRefreshArgs,
-    },
-    #[clap(alias = "add-related")]
-    AddRelatedLink {
-        #[clap(flatten)]
-        add_related_link_args: UpdateAddRelatedLinkArgs,
-    },
-    #[clap(alias = "add-tags")]
-    AddTag {
-        #[clap(flatten)]
-        add_tag_args: UpdateAddTagArgs,
-    },
-    #[clap(alias = "remove-related")]
-    RemoveRelatedLink {
-        #[clap(flatten)]
-        remove_related_link_args: UpdateRemoveRelatedLinkArgs,
-    },
-    #[clap(alias = "remove-tags")]
-    RemoveTag {
-        #[clap(flatten)]
-        remove_tag_args: UpdateRemoveTagArgs,
-    },
+fn link_add_file_cmd(tx: &Transaction, args: &LinkAddFileArgs, out: &mut dyn Write) -> Result<()> {
+    let path = std::fs::canonicalize(&args.path)?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    let content = match extension.as_deref() {
+        Some("pdf") => pdf_extract::extract_text(&path)
+            .map_err(|e| anyhow!("Unable to extract text from PDF ({e})"))?,
+        _ => std::fs::read_to_string(&path)?,
+    };
+    let url = match &args.url {
+        Some(url) => util::validate_url(url, true)?,
+        None => Url::from_file_path(&path)
+            .map_err(|_| anyhow!("Unable to make a URL for {}", path.display()))?,
+    };
+    let title = args
+        .title
+        .clone()
+        .or_else(|| path.file_name().map(|n| n.to_string_lossy().to_string()));
+    let now = now()?;
+    let link_insert_args = db::LinkInsert {
+        url: url.as_str(),
+        title: title.as_deref(),
+        description: None,
+        content: Some(content.trim()),
+        is_primary: true,
+        timestamp: &now,
+        created_at: None,
+        source: Some("manual"),
+    };
+    db::insert_link(tx, &link_insert_args, false)?;
+    writeln!(out, "Added bookmark for <{url}>")?;
+    Ok(())
+}
+
+fn link_archive_cmd(tx: &Transaction, args: &LinkArchiveArgs, out: &mut dyn Write) -> Result<()> {
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.link),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    let Some(link) = link else {
+        writeln!(out, "Link <{}> not found", args.link)?;
+        return Ok(());
+    };
+    if link.archived_at.is_some() {
+        writeln!(out, "<{}> is already archived", link.url)?;
+        return Ok(());
+    }
+    db::archive_link(tx, &link.id, &now()?)?;
+    writeln!(out, "Archived <{}>", link.url)?;
+    Ok(())
+}
+
+fn link_bulk_tag_cmd(
+    tx: &Transaction,
+    args: &LinkBulkTagArgs,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let contents = std::fs::read_to_string(&args.file)?;
+    let (mut tagged, mut created, mut skipped) = (0, 0, 0);
+    for line in contents.lines() {
+        let url = line.trim();
+        if url.is_empty() || url.starts_with('#') {
+            continue;
+        }
+        let link = db::get_link(tx, db::TermOrId::Term(url), db::IsPrimary::PrimaryOnly)?;
+        if let Some(link) = link {
+            for tag_name in &args.tag {
+                let tag_id = get_tag_id(tx, tag_name)?;
+                db::tag_link(tx, link.id, tag_id)?;
+            }
+            tagged += 1;
+        } else if args.create_missing {
+            let add_args = AddArgs {
+                link: Some(url.to_string()),
+                tag: args.tag.clone(),
+                no_fetch: true,
+                source: Some("bulk-tag".to_string()),
+                ..Default::default()
+            };
+            let url = add_link(tx, &add_args, config)?;
+            log_link_added(tx, &url)?;
+            writeln!(out, "Saved <{url}>")?;
+            tagged += 1;
+            created += 1;
+        } else {
+            eprintln!("Skipping <{url}>; not saved");
+            skipped += 1;
+        }
+    }
+    writeln!(
+        out,
+        "Tagged {tagged} links ({created} newly saved); skipped {skipped}"
+    )?;
+    Ok(())
+}
+
+fn link_archive_to_cmd(
+    tx: &Transaction,
+    args: &LinkArchiveToArgs,
+    fetch_config: &FetchConfig,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.link),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    let Some(link) = link else {
+        writeln!(out, "Link <{}> not found", args.link)?;
+        return Ok(());
+    };
+    if !args.overwrite {
+        if let Some(existing) = &link.local_archive_path {
+            anyhow::bail!(
+                "<{}> is already saved to {existing}; use --overwrite to replace it",
+                link.url
+            );
+        }
+        if args.path.exists() {
+            anyhow::bail!(
+                "{} already exists; use --overwrite to replace it",
+                args.path.display()
+            );
+        }
+    }
+    util::log_event("INFO", "archiving url", &[("url", link.url.as_str())]);
+    let mut response = http_agent(fetch_config).get(link.url.as_str()).call()?;
+    // Copied as bytes, unlike fetched pages, so that the file keeps the
+    // page's own encoding.
+    let mut file = std::fs::File::create(&args.path)
+        .with_context(|| format!("Unable to create {}", args.path.display()))?;
+    let bytes = std::io::copy(&mut response.body_mut().as_reader(), &mut file)?;
+    let path = std::path::absolute(&args.path)?;
+    db::set_link_local_archive_path(tx, &link.id, Some(&path.to_string_lossy()))?;
+    writeln!(
+        out,
+        "Saved <{}> to {} ({})",
+        link.url,
+        path.display(),
+        util::format_bytes(usize::try_from(bytes)?)
+    )?;
+    Ok(())
+}
+
+fn link_export_opml_cmd(
+    tx: &Transaction,
+    args: &LinkExportOpmlArgs,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use quick_xml::events::{BytesDecl, BytesText, Event};
+    use quick_xml::Writer;
+    // OPML wants RFC 822 dates.
+    const RFC_822: &str = "%a, %d %b %Y %H:%M:%S GMT";
+    fn write_outline<W: Write>(writer: &mut Writer<W>, link: &Link) -> std::io::Result<()> {
+        writer
+            .create_element("outline")
+            .with_attribute(("type", "link"))
+            .with_attribute(("text", link.title.as_deref().unwrap_or(link.url.as_str())))
+            .with_attribute(("url", link.url.as_str()))
+            .with_attribute((
+                "created",
+                link.created_at.strftime(RFC_822).to_string().as_str(),
+            ))
+            .write_empty()?;
+        Ok(())
+    }
+
+    let filter = args.filter.link_filter();
+    // Folders are keyed by slug, so that they come out in the same order as
+    // `tags list`.
+    let mut folders: BTreeMap<String, (String, Vec<Link>)> = BTreeMap::new();
+    let mut untagged = vec![];
+    for link in db::get_links(tx, args.filter.tag_slugs()?, None, &filter, false)? {
+        match db::tags_for_item(tx, &link.id)?.into_iter().next() {
+            Some(tag) => folders
+                .entry(tag.slug)
+                .or_insert_with(|| (tag.name, vec![]))
+                .1
+                .push(link),
+            None => untagged.push(link),
+        }
+    }
+    let mut writer = Writer::new_with_indent(vec![], b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer
+        .create_element("opml")
+        .with_attribute(("version", "2.0"))
+        .write_inner_content(|writer| {
+            writer
+                .create_element("head")
+                .write_inner_content(|writer| {
+                    writer
+                        .create_element("title")
+                        .write_text_content(BytesText::new(APP_NAME))?;
+                    writer
+                        .create_element("dateCreated")
+                        .write_text_content(BytesText::new(
+                            &Timestamp::now().strftime(RFC_822).to_string(),
+                        ))?;
+                    Ok(())
+                })?;
+            writer
+                .create_element("body")
+                .write_inner_content(|writer| {
+                    for (name, links) in folders.values() {
+                        writer
+                            .create_element("outline")
+                            .with_attribute(("text", name.as_str()))
+                            .write_inner_content(|writer| {
+                                for link in links {
+                                    write_outline(writer, link)?;
+                                }
+                                Ok(())
+                            })?;
+                    }
+                    for link in &untagged {
+                        write_outline(writer, link)?;
+                    }
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+    writeln!(out, "{}", String::from_utf8(writer.into_inner())?)?;
+    Ok(())
+}
+
+fn link_copy_cmd(tx: &Transaction, args: &LinkCopyArgs, out: &mut dyn Write) -> Result<()> {
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.link),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    let Some(link) = link else {
+        writeln!(out, "Link <{}> not found", args.link)?;
+        return Ok(());
+    };
+    let text = if args.title {
+        link.title
+            .ok_or_else(|| anyhow!("<{}> has no title", args.link))?
+    } else {
+        link.url.to_string()
+    };
+    // Headless machines won't have a clipboard; fall back to stdout so the
+    // command is still useful in a pipeline.
+    match arboard::Clipboard::new().and_then(|mut c| c.set_text(text.clone())) {
+        Ok(()) => writeln!(out, "Copied <{text}> to the clipboard")?,
+        Err(e) => {
+            eprintln!("Clipboard unavailable ({e}); printing instead");
+            writeln!(out, "{text}")?;
+        }
+    }
+    Ok(())
+}
+
+fn link_show_content_cmd(
+    tx: &Transaction,
+    args: &LinkShowContentArgs,
+    out: &mut dyn Write,
+) -> Result<()> {
+    // Unlike the other link commands, a missing link or missing content is an
+    // error here, so that scripts piping the output can tell by the exit code.
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.link),
+        db::IsPrimary::PrimaryOnly,
+    )?
+    .ok_or_else(|| MeowpadError::NotFound(format!("Link <{}>", args.link)))?;
+    let content = link
+        .content
+        .filter(|c| !c.is_empty())
+        .ok_or_else(|| anyhow!("<{}> has no stored content", link.url))?;
+    if content.ends_with('\n') {
+        write!(out, "{content}")?;
+    } else {
+        writeln!(out, "{content}")?;
+    }
+    Ok(())
+}
+
+fn link_favorite_cmd(tx: &Transaction, args: &LinkFavoriteArgs, out: &mut dyn Write) -> Result<()> {
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.link),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    let Some(link) = link else {
+        writeln!(out, "Link <{}> not found", args.link)?;
+        return Ok(());
+    };
+    db::set_link_favorite(tx, &link.id, !link.is_favorite)?;
+    if link.is_favorite {
+        writeln!(out, "Removed <{}> from favorites", link.url)?;
+    } else {
+        writeln!(out, "Added <{}> to favorites", link.url)?;
+    }
+    Ok(())
+}
+
+fn link_pin_cmd(tx: &Transaction, args: &LinkPinArgs, out: &mut dyn Write) -> Result<()> {
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.link),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    let Some(link) = link else {
+        writeln!(out, "Link <{}> not found", args.link)?;
+        return Ok(());
+    };
+    let position = db::pin_link(tx, &link.id, args.position)?;
+    writeln!(out, "Pinned <{}> at position {position}", link.url)?;
+    Ok(())
+}
+
+fn link_relate_cmd(tx: &Transaction, args: &LinkRelateArgs, out: &mut dyn Write) -> Result<()> {
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.primary),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    let Some(link) = link else {
+        writeln!(out, "Link <{}> not found", args.primary)?;
+        return Ok(());
+    };
+    let related_url = util::validate_url(&args.related, false)?;
+    let already_related = db::related_links(tx, &link.id)?
+        .iter()
+        .any(|(url, _)| url == related_url.as_str());
+    if already_related {
+        writeln!(
+            out,
+            "<{}> is already related to <{}>",
+            related_url, link.url
+        )?;
+        return Ok(());
+    }
+    update_add_related_link_cmd(tx, &link, related_url.as_str(), args.relation)?;
+    writeln!(out, "Related <{}> to <{}>", link.url, related_url)?;
+    Ok(())
+}
+
+fn link_unrelate_cmd(tx: &Transaction, args: &LinkUnrelateArgs, out: &mut dyn Write) -> Result<()> {
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.primary),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    let Some(link) = link else {
+        writeln!(out, "Link <{}> not found", args.primary)?;
+        return Ok(());
+    };
+    let related_link = db::get_link(tx, db::TermOrId::Term(&args.related), db::IsPrimary::Either)?;
+    let is_related = match &related_link {
+        Some(related_link) => db::related_links(tx, &link.id)?
+            .iter()
+            .any(|(url, _)| *url == related_link.url.as_str()),
+        None => false,
+    };
+    let Some(related_link) = related_link.filter(|_| is_related) else {
+        writeln!(out, "<{}> is not related to <{}>", args.related, link.url)?;
+        return Ok(());
+    };
+    db::delete_related_links(tx, Some(&link.id), Some(&related_link.id))?;
+    remove_orphaned_related_link(tx, &related_link)?;
+    writeln!(out, "Unrelated <{}> from <{}>", related_link.url, link.url)?;
+    Ok(())
+}
+
+fn link_unpin_cmd(tx: &Transaction, args: &LinkUnpinArgs, out: &mut dyn Write) -> Result<()> {
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.link),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    let Some(link) = link else {
+        writeln!(out, "Link <{}> not found", args.link)?;
+        return Ok(());
+    };
+    if link.pinned_order.is_none() {
+        writeln!(out, "<{}> isn't pinned", link.url)?;
+        return Ok(());
+    }
+    db::unpin_link(tx, &link.id)?;
+    writeln!(out, "Unpinned <{}>", link.url)?;
+    Ok(())
+}
+
+fn link_read_cmd(
+    tx: &Transaction,
+    args: &LinkReadArgs,
+    read: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.link),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    let Some(link) = link else {
+        writeln!(out, "Link <{}> not found", args.link)?;
+        return Ok(());
+    };
+    if read {
+        db::set_link_read_at(tx, &link.id, Some(&now()?))?;
+        db::set_link_reading_list(tx, &link.id, false)?;
+        writeln!(out, "Marked <{}> read", link.url)?;
+    } else {
+        db::set_link_read_at(tx, &link.id, None)?;
+        writeln!(out, "Marked <{}> unread", link.url)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct LinkRelations<'a> {
+    outgoing: Vec<RelatedLinkDetail<'a>>,
+    incoming: Vec<RelatedLinkDetail<'a>>,
+}
+
+fn link_relations_cmd(
+    tx: &Transaction,
+    args: &LinkRelationsArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+    out: &mut dyn Write,
+) -> Result<()> {
+    // Related links are usually secondary, so either kind can be asked about.
+    let link = db::get_link(tx, db::TermOrId::Term(&args.link), db::IsPrimary::Either)?;
+    let Some(link) = link else {
+        writeln!(out, "Link <{}> not found", args.link)?;
+        return Ok(());
+    };
+    let (outgoing, incoming) = match args.relation {
+        Some(relation) => {
+            let with_relation = |urls: Vec<String>| {
+                urls.into_iter()
+                    .map(|url| (url, Some(relation.as_str().to_string())))
+                    .collect::<Vec<_>>()
+            };
+            (
+                with_relation(db::get_related_links_by_relationship(
+                    tx, &link.id, relation,
+                )?),
+                with_relation(db::get_inverse_related_links_by_relationship(
+                    tx, &link.id, relation,
+                )?),
+            )
+        }
+        None => (
+            db::related_links(tx, &link.id)?,
+            db::inverse_related_links(tx, &link.id)?,
+        ),
+    };
+    let relations = LinkRelations {
+        outgoing: related_link_details(&outgoing),
+        incoming: related_link_details(&incoming),
+    };
+    let output = match args.format {
+        ListOutputFormat::Table => link_relations_as_table(&relations, ui_config),
+        ListOutputFormat::Json => as_json(&relations, json_pretty)?,
+        ListOutputFormat::Yaml => as_yaml(&relations)?,
+        ListOutputFormat::Tsv => {
+            let rows = relations
+                .outgoing
+                .iter()
+                .map(|rl| ("outgoing", rl))
+                .chain(relations.incoming.iter().map(|rl| ("incoming", rl)))
+                .map(|(direction, rl)| {
+                    vec![
+                        direction.to_string(),
+                        rl.url.to_string(),
+                        rl.relation.unwrap_or_default().to_string(),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            as_tsv(&rows)
+        }
+    };
+    writeln!(out, "{output}")?;
+    Ok(())
+}
+
+fn related_link_details(links: &[(String, Option<String>)]) -> Vec<RelatedLinkDetail<'_>> {
+    links
+        .iter()
+        .map(|rl| RelatedLinkDetail {
+            url: &rl.0,
+            relation: rl.1.as_deref(),
+            related_links: None,
+        })
+        .collect()
+}
+
+// Builds the tree of links related to `path`'s last link from the edges
+// found by db::related_links_recursive. Links already on the path are
+// skipped, so that links relating to each other don't repeat forever.
+fn related_link_tree<'a>(
+    edges: &'a [db::RelatedLinkEdge],
+    path: &mut Vec<TableId>,
+) -> Vec<RelatedLinkDetail<'a>> {
+    let Some(&primary_id) = path.last() else {
+        return vec![];
+    };
+    let mut details = vec![];
+    for edge in edges {
+        if edge.primary_id != primary_id || edge.depth != path.len() || path.contains(&edge.id) {
+            continue;
+        }
+        path.push(edge.id);
+        let related_links = related_link_tree(edges, path);
+        path.pop();
+        details.push(RelatedLinkDetail {
+            url: &edge.url,
+            relation: edge.relation.as_deref(),
+            related_links: Some(related_links),
+        });
+    }
+    details
+}
+
+// Flattens a tree of related links into (URL, relation) pairs, indenting
+// each URL by its depth if `indent` is set.
+fn flatten_related_link_tree(
+    details: &[RelatedLinkDetail],
+    depth: usize,
+    indent: bool,
+    flat: &mut Vec<(String, Option<String>)>,
+) {
+    for detail in details {
+        let prefix = if indent {
+            "  ".repeat(depth)
+        } else {
+            "".to_string()
+        };
+        flat.push((
+            format!("{prefix}{}", detail.url),
+            detail.relation.map(|r| r.to_string()),
+        ));
+        if let Some(related_links) = &detail.related_links {
+            flatten_related_link_tree(related_links, depth + 1, indent, flat);
+        }
+    }
+}
+
+fn stats_cmd(tx: &Transaction, ui_config: &UiConfig, out: &mut dyn Write) -> Result<()> {
+    let stats = db::reading_stats(tx)?;
+    let mut table = ui::new_table(ui_config, TableStyle::Utf8Full);
+    table.add_row(vec!["Links".to_string(), stats.links.to_string()]);
+    table.add_row(vec![
+        "Reading list".to_string(),
+        stats.reading_list.to_string(),
+    ]);
+    table.add_row(vec!["Read".to_string(), stats.read.to_string()]);
+    table.add_row(vec!["Unread".to_string(), stats.unread.to_string()]);
+    table.add_row(vec!["Refreshed".to_string(), stats.refreshed.to_string()]);
+    table.add_row(vec![
+        "Average time to read".to_string(),
+        stats
+            .average_secs_to_read
+            .map_or("".to_string(), |s| util::format_duration(s as i64)),
+    ]);
+    writeln!(out, "{table}")?;
+    Ok(())
+}
+
+fn link_content_stats_cmd(
+    tx: &Transaction,
+    ui_config: &UiConfig,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let stats = db::content_stats(tx)?;
+    let mut table = ui::new_table(ui_config, TableStyle::Utf8Full);
+    table.add_row(vec![
+        "Links with content".to_string(),
+        stats.with_content.to_string(),
+    ]);
+    table.add_row(vec![
+        "Links without content".to_string(),
+        stats.without_content.to_string(),
+    ]);
+    table.add_row(vec![
+        "Total characters".to_string(),
+        stats.total_chars.to_string(),
+    ]);
+    let average = stats
+        .total_chars
+        .checked_div(stats.with_content)
+        .map_or("".to_string(), |a| a.to_string());
+    table.add_row(vec!["Average length".to_string(), average]);
+    table.add_row(vec![
+        "Shortest".to_string(),
+        stats.min_chars.map_or("".to_string(), |c| c.to_string()),
+    ]);
+    table.add_row(vec![
+        "Longest".to_string(),
+        stats.max_chars.map_or("".to_string(), |c| c.to_string()),
+    ]);
+    writeln!(out, "{table}")?;
+    if stats.longest.is_empty() {
+        return Ok(());
+    }
+    let mut longest = ui::new_table(ui_config, TableStyle::Utf8Borders);
+    longest.set_header(vec!["Longest articles", "Characters"]);
+    for (url, chars) in stats.longest {
+        longest.add_row(vec![url, chars.to_string()]);
+    }
+    writeln!(out, "{longest}")?;
+    Ok(())
+}
+
+fn link_relations_as_table(relations: &LinkRelations, ui_config: &UiConfig) -> String {
+    let mut table = ui::new_table(ui_config, TableStyle::Utf8Borders);
+    table.set_header(vec!["Direction", "Link", "Relation"]);
+    let rows = relations
+        .outgoing
+        .iter()
+        .map(|rl| ("Outgoing", rl))
+        .chain(relations.incoming.iter().map(|rl| ("Incoming", rl)));
+    for (direction, rl) in rows {
+        table.add_row(vec![direction, rl.url, rl.relation.unwrap_or_default()]);
+    }
+    table.to_string()
+}
+
+fn link_remove_cmd(tx: &Transaction, args: &LinkRemoveArgs, out: &mut dyn Write) -> Result<()> {
+    if remove_link(tx, &args.link)? {
+        writeln!(out, "Removed link for <{}>", args.link)?;
+    } else {
+        writeln!(out, "Link <{}> not found", args.link)?;
+    }
+    Ok(())
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let config = load_config(&cli)?;
-    if let Some(parent) = config.database.parent() {
-        std::fs::create_dir_all(parent).with_context(|| {
-            format!(
-                "Unable to create database at {}",
-                config.database.to_string_lossy()
-            )
-        })?;
+fn link_restore_cmd(tx: &Transaction, args: &LinkRestoreArgs, out: &mut dyn Write) -> Result<()> {
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.link),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    match link {
+        Some(link) if link.archived_at.is_some() => {
+            db::restore_link(tx, &link.id)?;
+            writeln!(out, "Restored <{}>", link.url)?;
+        }
+        _ => writeln!(out, "No archived link <{}>", args.link)?,
     }
-    let conn = Connection::open(&config.database)
-        .with_context(|| format!("Unable to open database at {:?}", &config.database))?;
-    db_migrations::migrate(conn)
-        .with_context(|| format!("Unable to upgrade database at {:?}", &config.database))?;
+    Ok(())
+}
 
-    let mut conn = Connection::open(&config.database)?;
-    let tx = conn.transaction()?;
+fn link_tag_cmd(tx: &Transaction, args: &LinkTagArgs, out: &mut dyn Write) -> Result<()> {
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.link),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    let Some(link) = link else {
+        writeln!(out, "Link <{}> not found", args.link)?;
+        return Ok(());
+    };
+    for tag_name in &args.tags {
+        let tag_id = get_tag_id(tx, tag_name)?;
+        db::tag_link(tx, link.id, tag_id)?;
+    }
+    writeln!(out, "Tagged <{}> with {}", link.url, args.tags.join(", "))?;
+    Ok(())
+}
 
-    match &cli.command {
-        Commands::Add { add_args } => {
-            add_cmd(&tx, add_args).with_context(|| format!("Unable to add <{}>", add_args.link))?;
-            tx.commit()?;
-        }
-        Commands::List { list_args } => {
-            list_cmd(&tx, list_args).with_context(|| "Unable to list items")?;
-        }
-        Commands::Note { note_args } => {
-            note_cmd(&tx, note_args).with_context(|| "Unable to add note")?;
-            tx.commit()?;
-        }
-        Commands::Remove { remove_args } => {
-            remove_cmd(&tx, remove_args).with_context(|| "Unable to remove item")?;
-            tx.commit()?;
-        }
-        Commands::Search { search_args } => {
-            search_cmd(&tx, search_args).with_context(|| "Unable to search")?;
-        }
-        Commands::Show { show_args } => {
-            show_cmd(&tx, show_args)
-                .with_context(|| format!("Unable to show <{}>", show_args.term))?;
+fn link_untag_cmd(tx: &Transaction, args: &LinkTagArgs, out: &mut dyn Write) -> Result<()> {
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.link),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    let Some(link) = link else {
+        writeln!(out, "Link <{}> not found", args.link)?;
+        return Ok(());
+    };
+    for tag_name in &args.tags {
+        // Unlike tagging, an unknown tag is never created here.
+        match db::get_tag_id_by_slug(tx, &util::slugify(tag_name)?)? {
+            Some(tag_id) => db::untag_link(tx, link.id, tag_id)?,
+            None => writeln!(out, "Unknown tag `{tag_name}`")?,
         }
-        Commands::Update { update_args } => {
-            let link = db::get_link(
-                &tx,
-                db::TermOrId::Term(&update_args.link),
-                db::IsPrimary::PrimaryOnly,
-            )?;
-            if let Some(link) = link {
-                let command = match &update_args.command {
-                    UpdateCommands::AddRelatedLink {
-                        add_related_link_args,
-                    } => update_add_related_link_cmd(
-                        &tx,
-                        &link,
-                        &add_related_link_args.related_link,
-                        &add_related_link_args.relation,
-                    ),
-                    UpdateCommands::AddTag { add_tag_args } => {
-                        update_add_tag_cmd(&tx, &link, &add_tag_args.tags)
-                    }
-                    UpdateCommands::Refresh { refresh_args: _ } => {
-                        let mut writeable = link.clone();
-                        update_refresh_cmd(&tx, &mut writeable)
+    }
+    writeln!(out, "Untagged <{}>", link.url)?;
+    Ok(())
+}
+
+fn link_update_url_cmd(
+    tx: &Transaction,
+    args: &LinkUpdateUrlArgs,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let link = db::get_link(
+        tx,
+        db::TermOrId::Term(&args.old_url),
+        db::IsPrimary::PrimaryOnly,
+    )?;
+    let Some(mut link) = link else {
+        writeln!(out, "Link <{}> not found", args.old_url)?;
+        return Ok(());
+    };
+    let old_url = link.url.clone();
+    let new_url = util::validate_url(&args.new_url, false)?;
+    let mut note = db::get_note_by_link_id(tx, &link.id)?;
+    let existing = db::get_link(
+        tx,
+        db::TermOrId::Term(new_url.as_str()),
+        db::IsPrimary::Either,
+    )?;
+    match existing {
+        Some(existing) if existing.id != link.id => {
+            if !args.force {
+                anyhow::bail!(
+                    "<{}> already exists; use --force to merge <{}> into it",
+                    new_url,
+                    old_url
+                );
+            }
+            // Notes cascade with their link, so they have to move before the
+            // merge deletes it. If both links had notes, fold ours into theirs.
+            if let Some(mut old_note) = note.take() {
+                match db::get_note_by_link_id(tx, &existing.id)? {
+                    Some(mut existing_note) => {
+                        existing_note.content.push('\n');
+                        existing_note.content.push_str(&old_note.content);
+                        db::delete_note(tx, &old_note.id)?;
+                        note = Some(existing_note);
                     }
-                    UpdateCommands::RemoveRelatedLink {
-                        remove_related_link_args,
-                    } => update_remove_related_link_cmd(
-                        &tx,
-                        &link,
-                        &remove_related_link_args.related_link,
-                    ),
-                    UpdateCommands::RemoveTag { remove_tag_args } => {
-                        update_remove_tag_cmd(&tx, &link, &remove_tag_args.tags)
+                    None => {
+                        old_note.link_id = Some(existing.id);
+                        db::update_note(tx, &old_note)?;
+                        note = Some(old_note);
                     }
-                };
-                command.with_context(|| format!("Unable to update <{}>", &update_args.link))?;
-                tx.commit()?;
-                println!("<{}> updated", update_args.link);
-            } else {
-                println!("Unknown link <{}>", update_args.link);
+                }
             }
+            db::merge_links(tx, &link.id, &existing.id)?;
+            link = existing;
+            link.is_primary = true;
+        }
+        _ => link.url = new_url,
+    }
+    if args.fetch {
+        let page_info = readability(tx, link.url.as_str(), &config.fetch, true)?;
+        link.title = if page_info.title.is_empty() {
+            None
+        } else {
+            Some(page_info.title)
+        };
+        link.description = page_info.excerpt;
+        db::delete_content(tx, &link.id)?;
+        db::insert_content(tx, &link.id, page_info.text_content.trim())?;
+    }
+    db::update_link(tx, &link)?;
+    // Notes attached to links are titled by URL, so keep the title in step.
+    if let Some(mut note) = note {
+        if note.title == old_url.as_str() || note.title == args.old_url {
+            note.title = link.url.to_string();
         }
+        note.link_id = Some(link.id);
+        db::update_note(tx, &note)?;
     }
+    writeln!(out, "Updated <{}> to <{}>", args.old_url, link.url)?;
+    Ok(())
+}
 
+fn note_delete_cmd(tx: &Transaction, args: &NoteDeleteArgs, out: &mut dyn Write) -> Result<()> {
+    if remove_note(tx, &args.title)? {
+        writeln!(out, "Removed note <{}>", args.title)?;
+    } else {
+        writeln!(out, "Note <{}> not found", args.title)?;
+    }
     Ok(())
 }
 
-fn home_dir() -> Option<PathBuf> {
-    // NB: The state of std::env::home_dir() and its replacements is a mess.
-    // See <https://doc.rust-lang.org/std/env/fn.home_dir.html> and
-    // <https://github.com/rust-lang/libs-team/issues/372>. Notably, `home`
-    // is not recommended for use outside of Cargo. Hopefully `env_home` will
-    // end up in standard library and we can go ahead and use that.
-    env_home::env_home_dir()
+fn note_edit_cmd(tx: &Transaction, args: &NoteEditArgs, out: &mut dyn Write) -> Result<()> {
+    let Some(note) = db::get_note_by_title(tx, &args.title)? else {
+        writeln!(out, "Note <{}> not found", args.title)?;
+        return Ok(());
+    };
+    let content = edit::edit(&note.content)?;
+    if content == note.content {
+        writeln!(out, "No changes")?;
+        return Ok(());
+    }
+    let before = note_snapshot(tx, &note)?;
+    let now = now()?;
+    db::upsert_note(tx, &content, &note.title, None, &now, None)?;
+    let payload = serde_json::json!({ "title": note.title, "before": before });
+    db::log_operation(tx, "edit", "note", &note.id, &payload, &now)?;
+    writeln!(out, "Updated note <{}>", note.title)?;
+    Ok(())
 }
 
-fn expand_tilde(path: &mut PathBuf) {
-    let home = home_dir();
-    if let Some(home) = home {
-        let mut rewritten = PathBuf::new();
-        rewritten.push(home);
-        for arg in path.iter().skip(1) {
-            rewritten.push(arg);
+fn note_tag_cmd(tx: &Transaction, args: &NoteTagArgs, out: &mut dyn Write) -> Result<()> {
+    let Some(note) = db::get_note_by_title(tx, &args.title)? else {
+        writeln!(out, "Note <{}> not found", args.title)?;
+        return Ok(());
+    };
+    for tag_name in &args.tags {
+        let tag_id = get_tag_id(tx, tag_name)?;
+        db::tag_note(tx, note.id, tag_id)?;
+    }
+    writeln!(out, "Tagged <{}> with {}", note.title, args.tags.join(", "))?;
+    Ok(())
+}
+
+fn note_untag_cmd(tx: &Transaction, args: &NoteTagArgs, out: &mut dyn Write) -> Result<()> {
+    let Some(note) = db::get_note_by_title(tx, &args.title)? else {
+        writeln!(out, "Note <{}> not found", args.title)?;
+        return Ok(());
+    };
+    for tag_name in &args.tags {
+        match db::get_tag_id_by_slug(tx, &util::slugify(tag_name)?)? {
+            Some(tag_id) => db::untag_note(tx, note.id, tag_id)?,
+            None => writeln!(out, "Unknown tag `{tag_name}`")?,
         }
-        *path = rewritten;
     }
+    writeln!(out, "Untagged <{}>", note.title)?;
+    Ok(())
 }
 
-fn default_db_location() -> PathBuf {
-    let app_dirs = platform_dirs::AppDirs::new(Some(APP_NAME), true);
-    match app_dirs {
-        Some(app_dirs) => app_dirs.data_dir.join("meowpad.db"),
-        None => match home_dir() {
-            Some(mut home_dir) => {
-                home_dir.push(".meowpad.db");
-                home_dir
+fn note_export_cmd(tx: &Transaction, args: &NoteExportArgs, out: &mut dyn Write) -> Result<()> {
+    let tags = args
+        .tag
+        .iter()
+        .map(|t| util::slugify(t))
+        .collect::<Result<Vec<_>>>()?;
+    let mut notes = db::get_notes(tx, tags, None, None)?;
+    // Oldest first, so that if two titles share a file name, the same note
+    // keeps the unsuffixed one from run to run.
+    notes.reverse();
+    std::fs::create_dir_all(&args.output_dir)?;
+    let mut used_names = HashSet::new();
+    let (mut written, mut skipped) = (0, 0);
+    for note in &notes {
+        let stem = util::slugify(&note.title)
+            .map(|s| s.replace(':', "-"))
+            .unwrap_or_else(|_| "note".to_string());
+        let mut name = format!("{stem}.md");
+        let mut n = 2;
+        while !used_names.insert(name.clone()) {
+            name = format!("{stem}-{n}.md");
+            n += 1;
+        }
+        let link = match note.link_id {
+            Some(link_id) => db::get_link(tx, db::TermOrId::Id(link_id), db::IsPrimary::Either)?,
+            None => None,
+        };
+        let tags = db::tags_for_item(tx, &note.id)?;
+        let contents = note_as_markdown(note, &tags, link.as_ref())?;
+        let path = args.output_dir.join(&name);
+        match std::fs::read_to_string(&path) {
+            Ok(existing) if existing == contents => continue,
+            Ok(_) if !args.force => {
+                writeln!(
+                    out,
+                    "Skipped {} (it already exists; use --force to overwrite)",
+                    path.display()
+                )?;
+                skipped += 1;
+                continue;
             }
-            None => ".meowpad.db".into(),
-        },
+            _ => {}
+        }
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Unable to write {}", path.display()))?;
+        written += 1;
     }
+    writeln!(
+        out,
+        "Exported {written} of {} notes to {}{}",
+        notes.len(),
+        args.output_dir.display(),
+        if skipped > 0 {
+            format!(" ({skipped} skipped)")
+        } else {
+            "".to_string()
+        }
+    )?;
+    Ok(())
 }
 
-fn load_config(cli: &Cli) -> Result<Config> {
-    // Defaults will be overwritten by the TOML config file, which in turn will
-    // be overwritten by CLI arguments, if available.
-    let mut config = Config::new();
-    let mut error_on_load_failure = false;
-    let config_path = if let Some(cli_config) = &cli.config {
-        error_on_load_failure = true;
-        expand_tilde(&mut cli_config.clone());
-        cli_config
-    } else {
-        // It may make sense at some point to switch from `platform_dirs` to
-        // `etcetera` or `xdg` to reduce the number of dependencies that get
-        // pulled in. We're using `platform_dirs` for now because it handles
-        // Windows (less important) and lets us specify that Macs should
-        // follow XDG locations (important).
-        let app_dirs = platform_dirs::AppDirs::new(Some(APP_NAME), true);
-        match app_dirs {
-            Some(app_dirs) => &app_dirs.config_dir.join("config.toml"),
-            // This will error out, which is fine!
-            None => &PathBuf::new(),
-        }
+// A Markdown document with YAML front matter, as written by `note export`.
+fn note_as_markdown(note: &Note, tags: &[Tag], link: Option<&Link>) -> Result<String> {
+    let front_matter = NoteFrontMatter {
+        title: &note.title,
+        tags: tags.iter().map(|t| t.name.as_str()).collect(),
+        created_at: note.created_at,
+        modified_at: note.modified_at,
+        link: link.map(|l| l.url.as_str()),
     };
-    if let Ok(config_str) = std::fs::read_to_string(config_path) {
-        config = toml::from_str(&config_str).with_context(|| {
-            format!(
-                "Unable to parse config file at {}",
-                config_path.to_string_lossy()
-            )
-        })?;
+    let mut contents = format!("---\n{}\n---\n\n{}", as_yaml(&front_matter)?, note.content);
+    if let Some(link) = link {
+        if !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push_str(&format!("\n## Source\n\n<{}>\n", link.url));
+    }
+    Ok(contents)
+}
+
+fn note_import_cmd(tx: &Transaction, args: &NoteImportArgs, out: &mut dyn Write) -> Result<()> {
+    fn markdown_files(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+            {
+                continue;
+            }
+            if path.is_dir() {
+                if recursive {
+                    markdown_files(&path, recursive, files)?;
+                }
+            } else if path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("md"))
+            {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = vec![];
+    markdown_files(&args.dir, args.recursive, &mut files)?;
+    files.sort();
+    let now = now()?;
+    let mut imported = 0;
+    for path in &files {
+        match import_note_file(tx, path, &now) {
+            Ok(title) => {
+                writeln!(out, "Imported note <{title}> from {}", path.display())?;
+                imported += 1;
+            }
+            Err(e) => writeln!(out, "Unable to import {}: {e:#}", path.display())?,
+        }
+    }
+    writeln!(
+        out,
+        "Imported {imported} of {} notes from {}",
+        files.len(),
+        args.dir.display()
+    )?;
+    Ok(())
+}
+
+// Adds (or replaces) the note in one Markdown file, returning its title.
+fn import_note_file(tx: &Transaction, path: &Path, now: &str) -> Result<String> {
+    let contents = std::fs::read_to_string(path)?;
+    // The front matter parser takes any `---` line as the start of front
+    // matter, so it's only used when the file opens with one.
+    let (front_matter, body) = if contents.lines().next().map(str::trim) == Some("---") {
+        let document =
+            yaml_front_matter::YamlFrontMatter::parse::<ImportedNoteFrontMatter>(&contents)
+                .map_err(|e| anyhow!("Invalid front matter ({e})"))?;
+        (document.metadata, document.content)
     } else {
-        // If we are just using a default config path and there is no config present,
-        // we'll treat it as a noop and stick with the default config.
-        if error_on_load_failure {
-            return Err(anyhow!(
-                "Unable to open config file at {}",
-                config_path.to_string_lossy()
-            ));
+        (ImportedNoteFrontMatter::default(), contents)
+    };
+    let title = match front_matter.title {
+        Some(title) => title,
+        None => path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .ok_or_else(|| anyhow!("No title"))?,
+    };
+    let created_at = match &front_matter.created_at {
+        Some(created_at) => util::parse_date(created_at)?,
+        None => Timestamp::try_from(std::fs::metadata(path)?.modified()?)?,
+    }
+    .round(Unit::Second)?
+    .to_string();
+    let link = match &front_matter.url {
+        Some(url) => Some(
+            db::get_link(tx, db::TermOrId::Term(url), db::IsPrimary::Either)?
+                .ok_or_else(|| MeowpadError::NotFound(format!("Link <{url}>")))?,
+        ),
+        None => None,
+    };
+    let mut content = body.trim().to_string();
+    // Drop the source section that `note export` adds after linked notes.
+    if let Some(link) = &link {
+        let source = format!("## Source\n\n<{}>", link.url);
+        if let Some(stripped) = content.strip_suffix(&source) {
+            content = stripped.trim_end().to_string();
         }
     }
-    // If we ever want to support setting options via ENV variables,
-    // they would go here. Then, any values that can be overwritten
-    // from the CLI should go last.
-    if let Some(cli_db) = &cli.db {
-        config.database = cli_db.to_path_buf();
+    if content.is_empty() {
+        anyhow::bail!("The note is empty");
+    }
+    // A file that fails is skipped but the import carries on, so check the
+    // tags before anything is written.
+    let tags = front_matter
+        .tags
+        .iter()
+        .map(|name| Ok((name, util::slugify(name)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let before = db::get_note_by_title(tx, &title)?
+        .map(|n| note_snapshot(tx, &n))
+        .transpose()?;
+    let note_id = db::upsert_note(
+        tx,
+        &content,
+        &title,
+        link.as_ref().map(|l| &l.id),
+        now,
+        Some(&created_at),
+    )?;
+    for (name, slug) in &tags {
+        let tag_id = db::require_tag(tx, name, slug, now)?;
+        db::tag_note(tx, note_id, tag_id)?;
+    }
+    let payload = serde_json::json!({ "title": title, "before": before });
+    db::log_operation(tx, "note", "note", &note_id, &payload, now)?;
+    Ok(title)
+}
+
+fn note_show_cmd(
+    tx: &Transaction,
+    args: &NoteShowArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let Some(note) = db::get_note_by_title(tx, &args.title)? else {
+        writeln!(out, "Note <{}> not found", args.title)?;
+        return Ok(());
+    };
+    let link = match note.link_id {
+        Some(link_id) => db::get_link(tx, db::TermOrId::Id(link_id), db::IsPrimary::Either)?,
+        None => None,
+    };
+    let tags = db::tags_for_item(tx, &note.id)?;
+    let detail = NoteShowDetail {
+        note: NoteDetail::new(&note),
+        tags: tags.iter().map(|t| t.name.as_str()).collect(),
+        link: link.as_ref(),
+    };
+    let output = match args.format {
+        NoteShowFormat::Table => note_as_table(&note, &tags, link.as_ref(), ui_config),
+        NoteShowFormat::Json => as_json(&detail, json_pretty)?,
+        NoteShowFormat::Yaml => as_yaml(&detail)?,
+        // The document already ends with a newline.
+        NoteShowFormat::Markdown => note_as_markdown(&note, &tags, link.as_ref())?
+            .trim_end()
+            .to_string(),
+    };
+    writeln!(out, "{output}")?;
+    Ok(())
+}
+
+fn note_as_table(note: &Note, tags: &[Tag], link: Option<&Link>, ui_config: &UiConfig) -> String {
+    let mut table = ui::new_table(ui_config, TableStyle::Utf8Full);
+    table.add_row(vec!["Title", note.title.as_str()]);
+    if let Some(link) = link {
+        table.add_row(vec!["Link", link.url.as_str()]);
+    }
+    if !tags.is_empty() {
+        table.add_row(vec!["Tags".to_string(), ui::tag_names(tags, ui_config)]);
+    }
+    table.add_row(vec![
+        "Added".to_string(),
+        ui::format_timestamp(&note.created_at, &ui_config.date_format),
+    ]);
+    table.add_row(vec![
+        "Modified".to_string(),
+        ui::format_timestamp(&note.modified_at, &ui_config.date_format),
+    ]);
+    let content = note.content.trim();
+    table.add_row(vec!["Note", content]);
+    let stats = util::text_stats(content);
+    table.add_row(vec![
+        "Stats".to_string(),
+        format!(
+            "{} words, {} characters, {} lines",
+            stats.words, stats.chars, stats.lines
+        ),
+    ]);
+    table.to_string()
+}
+
+fn note_list_cmd(
+    tx: &Transaction,
+    args: &NoteListArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let tags = args
+        .tag
+        .iter()
+        .map(|t| util::slugify(t))
+        .collect::<Result<Vec<_>>>()?;
+    if args.linked && matches!(args.format, ListOutputFormat::Table) {
+        let items = db::get_notes_with_links(tx, tags)?;
+        writeln!(out, "{}", linked_notes_as_table(&items, ui_config))?;
+        return Ok(());
+    }
+    let link_association = match (args.linked, args.standalone) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    };
+    let notes = db::get_notes(tx, tags, None, link_association)?;
+    let output = match args.format {
+        ListOutputFormat::Table => notes_with_urls_as_table(tx, notes, ui_config)?,
+        ListOutputFormat::Json => as_json(
+            &notes.iter().map(NoteDetail::new).collect::<Vec<_>>(),
+            json_pretty,
+        )?,
+        ListOutputFormat::Yaml => as_yaml(&notes.iter().map(NoteDetail::new).collect::<Vec<_>>())?,
+        ListOutputFormat::Tsv => notes_as_tsv(tx, &notes)?,
+    };
+    writeln!(out, "{output}")?;
+    Ok(())
+}
+
+// Notes share the list columns, with the attached link's URL (if any) in the
+// url column.
+fn notes_as_tsv(tx: &Transaction, notes: &[Note]) -> Result<String> {
+    let mut rows = vec![];
+    for note in notes {
+        let link = match note.link_id {
+            Some(link_id) => db::get_link(tx, db::TermOrId::Id(link_id), db::IsPrimary::Either)?,
+            None => None,
+        };
+        let tags = db::tags_for_item(tx, &note.id)?;
+        rows.push(vec![
+            note.id.to_string(),
+            link.map(|l| l.url.to_string()).unwrap_or_default(),
+            note.title.clone(),
+            note.created_at.to_string(),
+            tag_slugs(&tags),
+        ]);
+    }
+    Ok(as_tsv(&rows))
+}
+
+fn notes_with_urls_as_table(
+    tx: &Transaction,
+    notes: Vec<Note>,
+    ui_config: &UiConfig,
+) -> Result<String> {
+    let mut items = vec![];
+    for note in notes {
+        let link = match note.link_id {
+            Some(link_id) => db::get_link(tx, db::TermOrId::Id(link_id), db::IsPrimary::Either)?,
+            None => None,
+        };
+        items.push((note, link.map(|l| l.url)));
+    }
+    note_list_as_table(items, ui_config)
+}
+
+fn note_list_as_table(items: Vec<(Note, Option<Url>)>, ui_config: &UiConfig) -> Result<String> {
+    let mut table = ui::new_table(ui_config, TableStyle::Utf8Borders);
+    table.set_header(vec!["Title", "Link", "Created"]);
+    for (note, url) in &items {
+        table.add_row(vec![
+            note.title.clone(),
+            url.as_ref().map(|u| u.to_string()).unwrap_or_default(),
+            ui::format_timestamp(&note.created_at, &ui_config.date_format),
+        ]);
     }
-    // Finally, let's do tilde expansion on file paths if needed.
-    if config.database.starts_with("~/") {
-        expand_tilde(&mut config.database);
+    Ok(table.to_string())
+}
+
+fn linked_notes_as_table(items: &[(Note, Link)], ui_config: &UiConfig) -> String {
+    let mut table = ui::new_table(ui_config, TableStyle::Utf8Borders);
+    table.set_header(vec!["Title", "Link", "Link Title", "Created"]);
+    for (note, link) in items {
+        table.add_row(vec![
+            note.title.clone(),
+            link.url.to_string(),
+            link.title.clone().unwrap_or_default(),
+            ui::format_timestamp(&note.created_at, &ui_config.date_format),
+        ]);
     }
-    Ok(config)
+    table.to_string()
+}
+
+fn related_links_cmd(
+    tx: &Transaction,
+    args: &RelatedLinksArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let primary_id = match &args.primary {
+        Some(url) => {
+            let link = db::get_link(tx, db::TermOrId::Term(url), db::IsPrimary::PrimaryOnly)?;
+            let Some(link) = link else {
+                writeln!(out, "Link <{url}> not found")?;
+                return Ok(());
+            };
+            Some(link.id)
+        }
+        None => None,
+    };
+    let related_links = db::get_all_related_links(tx, primary_id.as_ref())?;
+    let output = match args.format {
+        ListOutputFormat::Table => related_links_as_table(&related_links, ui_config),
+        ListOutputFormat::Json => as_json(&related_links, json_pretty)?,
+        ListOutputFormat::Yaml => as_yaml(&related_links)?,
+        ListOutputFormat::Tsv => as_tsv(
+            &related_links
+                .iter()
+                .map(|rl| {
+                    vec![
+                        rl.url.to_string(),
+                        rl.relation.clone().unwrap_or_default(),
+                        rl.primary_url.to_string(),
+                    ]
+                })
+                .collect::<Vec<_>>(),
+        ),
+    };
+    writeln!(out, "{output}")?;
+    Ok(())
 }
 
-// UTIL
-fn now() -> Result<String> {
-    let zoned = Zoned::now().round(Unit::Second)?;
-    Ok(zoned.timestamp().to_string())
+fn related_links_as_table(related_links: &[RelatedLink], ui_config: &UiConfig) -> String {
+    let mut table = ui::new_table(ui_config, TableStyle::Utf8Borders);
+    table.set_header(vec!["Related Link", "Relation", "Primary Link"]);
+    for rl in related_links {
+        table.add_row(vec![
+            rl.url.as_str(),
+            rl.relation.as_deref().unwrap_or_default(),
+            rl.primary_url.as_str(),
+        ]);
+    }
+    table.to_string()
 }
 
-// LINK
-fn readability(url: &str) -> Result<Article> {
-    let agent: Agent = Agent::config_builder()
-        .user_agent(APP_USER_AGENT)
-        .timeout_global(Some(std::time::Duration::from_secs(5)))
-        .build()
-        .into();
-    let html: String = agent.get(url).call()?.body_mut().read_to_string()?;
-    // TODO: We should test to see if we believe that the readability score is
-    // high enough to make this worthwhile, or if we should instead just
-    // extract the title (and maybe excerpt?).
-    let mut readability = Readability::new(html, Some(url), None)?;
-    Ok(readability.parse()?)
+fn remove_link(tx: &Transaction, url: &str) -> Result<bool> {
+    if let Some(link) = db::get_link(tx, db::TermOrId::Term(url), db::IsPrimary::PrimaryOnly)? {
+        remove_link_item(tx, link)?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
 }
 
-// UTIL
-fn get_tag_id(tx: &Transaction, tag_name: &str) -> Result<TableId> {
-    let now = now()?;
-    let slug = util::slugify(tag_name)?;
-    let id = db::require_tag(tx, tag_name, &slug, &now)?;
-    Ok(id)
+fn remove_link_item(tx: &Transaction, link: Link) -> Result<()> {
+    let payload = serde_json::to_value(link_snapshot(tx, &link)?)?;
+    db::log_operation(tx, "remove", "link", &link.id, &payload, &now()?)?;
+    delete_link_item(tx, link)
 }
 
-fn add_cmd(tx: &Transaction, args: &AddArgs) -> Result<()> {
-    let url =
-        Url::parse(&args.link).with_context(|| format!("{} is an invalid URL", &args.link))?;
-    let scheme = url.scheme();
-    if scheme != "https" && scheme != "http" {
-        return Err(anyhow!("Non-web URL scheme {}", scheme));
+// Deletes the link, or if other links relate to it, demotes it to a related
+// link.
+fn delete_link_item(tx: &Transaction, mut link: Link) -> Result<()> {
+    let inverse_relations = db::get_inverse_related_links(tx, &link.id)?;
+    if inverse_relations.is_empty() {
+        db::delete_link(tx, &link.id)?;
+    } else {
+        link.is_primary = false;
+        db::update_link(tx, &link)?;
+        db::delete_item_tags(tx, &link.id)?;
+        db::delete_related_links(tx, Some(&link.id), None)?;
+        db::delete_content(tx, &link.id)?;
     }
-    let now = now()?;
-    // TODO: We should be able to disable fetch via the command-line, everywhere
-    // via config, or on a per-domain or per-tag basis.
-    let page_info = readability(args.link.as_ref())?;
-    let title = if args.title.is_some() {
-        args.title.as_deref()
-    } else if page_info.title.is_empty() {
-        None
+    Ok(())
+}
+
+fn remove_note(tx: &Transaction, title: &str) -> Result<bool> {
+    if let Some(note) = db::get_note_by_title(tx, title)? {
+        let payload = serde_json::to_value(note_snapshot(tx, &note)?)?;
+        db::log_operation(tx, "remove", "note", &note.id, &payload, &now()?)?;
+        db::delete_note(tx, &note.id)?;
+        Ok(true)
     } else {
-        Some(page_info.title.as_ref())
+        Ok(false)
+    }
+}
+
+fn link_snapshot(tx: &Transaction, link: &Link) -> Result<LinkSnapshot> {
+    // Links from lists and searches come without their content.
+    let content = match &link.content {
+        Some(content) => Some(content.clone()),
+        None => db::get_link(tx, db::TermOrId::Id(link.id), db::IsPrimary::Either)?
+            .and_then(|l| l.content),
     };
-    let description = if args.description.is_some() {
-        args.description.as_deref()
-    } else {
-        page_info.excerpt.as_deref()
+    let note = db::get_note_by_link_id(tx, &link.id)?
+        .map(|n| note_snapshot(tx, &n))
+        .transpose()?;
+    Ok(LinkSnapshot {
+        url: link.url.to_string(),
+        title: link.title.clone(),
+        description: link.description.clone(),
+        content,
+        created_at: link.created_at,
+        read_at: link.read_at,
+        is_favorite: link.is_favorite,
+        is_reading_list: link.is_reading_list,
+        local_archive_path: link.local_archive_path.clone(),
+        meta: link.meta.clone(),
+        source: link.source.clone(),
+        tags: db::tags_for_item(tx, &link.id)?
+            .into_iter()
+            .map(|t| t.name)
+            .collect(),
+        note,
+        related_links: db::related_links(tx, &link.id)?,
+    })
+}
+
+fn note_snapshot(tx: &Transaction, note: &Note) -> Result<NoteSnapshot> {
+    let link_url = match note.link_id {
+        Some(link_id) => db::get_link(tx, db::TermOrId::Id(link_id), db::IsPrimary::Either)?
+            .map(|l| l.url.to_string()),
+        None => None,
     };
-    let text_content = page_info.text_content.trim();
+    Ok(NoteSnapshot {
+        title: note.title.clone(),
+        content: note.content.clone(),
+        created_at: note.created_at,
+        tags: db::tags_for_item(tx, &note.id)?
+            .into_iter()
+            .map(|t| t.name)
+            .collect(),
+        link_url,
+    })
+}
 
-    let link_insert_args = db::LinkInsert {
-        url: args.link.as_ref(),
-        title,
-        description,
-        content: Some(text_content),
+// The link gets back its old ID, so that earlier operations still find it.
+fn restore_link_snapshot(tx: &Transaction, id: TableId, snapshot: &LinkSnapshot) -> Result<()> {
+    let now = now()?;
+    let created_at = snapshot.created_at.to_string();
+    let insert_vals = db::LinkInsert {
+        url: &snapshot.url,
+        title: snapshot.title.as_deref(),
+        description: snapshot.description.as_deref(),
+        content: None,
         is_primary: true,
         timestamp: &now,
+        created_at: Some(&created_at),
+        source: snapshot.source.as_deref(),
     };
+    // Removing a link that other links relate to only demotes it to a related
+    // link, so it may still be here. (Archiving never demotes; it only sets
+    // archived_at.)
+    let link_id = db::insert_link_as(tx, id, &insert_vals, true)?;
+    let mut link = db::get_link(tx, db::TermOrId::Id(link_id), db::IsPrimary::Either)?
+        .ok_or_else(|| anyhow!("<{}> vanished while being restored", snapshot.url))?;
+    link.title = snapshot.title.clone();
+    link.description = snapshot.description.clone();
+    link.is_primary = true;
+    db::update_link(tx, &link)?;
+    if let Some(content) = &snapshot.content {
+        db::delete_content(tx, &link_id)?;
+        db::insert_content(tx, &link_id, content)?;
+    }
+    let read_at = snapshot.read_at.map(|t| t.to_string());
+    db::set_link_read_at(tx, &link_id, read_at.as_deref())?;
+    db::set_link_favorite(tx, &link_id, snapshot.is_favorite)?;
+    db::set_link_reading_list(tx, &link_id, snapshot.is_reading_list)?;
+    db::set_link_local_archive_path(tx, &link_id, snapshot.local_archive_path.as_deref())?;
+    if let Some(meta) = &snapshot.meta {
+        db::set_link_meta(tx, &link_id, meta)?;
+    }
+    for tag_name in &snapshot.tags {
+        let tag_id = get_tag_id(tx, tag_name)?;
+        db::tag_link(tx, link_id, tag_id)?;
+    }
+    for (related_url, relation) in &snapshot.related_links {
+        let relation = relation
+            .as_deref()
+            .and_then(|r| RelationshipType::from_str(r, true).ok());
+        update_add_related_link_cmd(tx, &link, related_url, relation)?;
+    }
+    if let Some(note) = &snapshot.note {
+        restore_note_snapshot(tx, note)?;
+    }
+    Ok(())
+}
 
-    let link_result = db::insert_link(tx, &link_insert_args, false);
-
-    let link_id = if let Ok(new_link) = link_result {
-        new_link
-    } else {
-        // Let's see if we have an existing *secondary* link that we are changing
-        // to a primary (so it can have its own tags, notes, etc.)
-        let mut secondary_link = db::get_link(
-            tx,
-            db::TermOrId::Term(args.link.as_ref()),
-            db::IsPrimary::SecondaryOnly,
-        )?;
-        if let Some(ref mut secondary_link) = secondary_link {
-            secondary_link.title = link_insert_args.title.map(|s| s.to_string());
-            secondary_link.description = link_insert_args.description.map(|s| s.to_string());
-            secondary_link.is_primary = true;
-            db::update_link(tx, secondary_link)?;
-            // A secondary link should never have attached content.
-            db::insert_content(tx, &secondary_link.id, text_content)?;
-        } else {
-            anyhow::bail!("Unable to insert <{}>; is it a duplicate?", args.link);
-        };
-        secondary_link.unwrap().id
+fn restore_note_snapshot(tx: &Transaction, snapshot: &NoteSnapshot) -> Result<()> {
+    let link_id = match &snapshot.link_url {
+        Some(url) => {
+            db::get_link(tx, db::TermOrId::Term(url), db::IsPrimary::Either)?.map(|l| l.id)
+        }
+        None => None,
     };
-
-    for tag_name in &args.tag {
+    let created_at = snapshot.created_at.to_string();
+    let note_id = db::upsert_note(
+        tx,
+        &snapshot.content,
+        &snapshot.title,
+        link_id.as_ref(),
+        &now()?,
+        Some(&created_at),
+    )?;
+    db::delete_item_tags(tx, &note_id)?;
+    for tag_name in &snapshot.tags {
         let tag_id = get_tag_id(tx, tag_name)?;
-        db::tag_link(tx, link_id, tag_id)?;
+        db::tag_note(tx, note_id, tag_id)?;
     }
+    Ok(())
+}
 
-    // NB: We don't currently need to do any kind of checking on note existence
-    // or updating a note, because we don't currently allow link editing/--force,
-    // but when that changes, this should chage as well.
-    let note = if let Some(message) = &args.message {
-        Some(message.clone())
-    } else if args.note {
-        Some(edit::edit("")?)
-    } else {
-        None
+fn undo_cmd(tx: &Transaction, out: &mut dyn Write) -> Result<()> {
+    let Some(operation) = db::get_operations(tx, 1)?.into_iter().next() else {
+        writeln!(out, "Nothing to undo")?;
+        return Ok(());
+    };
+    let label = operation.label();
+    let payload = operation.payload.clone().unwrap_or_default();
+    let message = match (operation.operation.as_str(), operation.item_type.as_str()) {
+        ("add", "link") => {
+            match db::get_link(
+                tx,
+                db::TermOrId::Id(operation.item_id),
+                db::IsPrimary::Either,
+            )? {
+                Some(link) => {
+                    let related = db::related_links(tx, &link.id)?;
+                    delete_link_item(tx, link)?;
+                    // Drop the related links that came along with the add.
+                    for (related_url, _) in related {
+                        let related_link = db::get_link(
+                            tx,
+                            db::TermOrId::Term(&related_url),
+                            db::IsPrimary::Either,
+                        )?;
+                        if let Some(related_link) = related_link {
+                            remove_orphaned_related_link(tx, &related_link)?;
+                        }
+                    }
+                    format!("Undid add of <{label}>")
+                }
+                None => format!("<{label}> is already gone"),
+            }
+        }
+        ("note", "note") | ("edit", "note") => {
+            let before: Option<NoteSnapshot> = serde_json::from_value(payload["before"].clone())?;
+            match before {
+                Some(before) => {
+                    restore_note_snapshot(tx, &before)?;
+                    format!("Restored the previous version of note <{label}>")
+                }
+                None => {
+                    db::delete_note(tx, &operation.item_id)?;
+                    format!("Removed note <{label}>")
+                }
+            }
+        }
+        ("remove", "link") => {
+            restore_link_snapshot(tx, operation.item_id, &serde_json::from_value(payload)?)?;
+            format!("Restored <{label}>")
+        }
+        ("remove", "note") => {
+            restore_note_snapshot(tx, &serde_json::from_value(payload)?)?;
+            format!("Restored note <{label}>")
+        }
+        (op, item_type) => return Err(anyhow!("Don't know how to undo {op} of a {item_type}")),
     };
+    db::delete_operation(tx, &operation.id)?;
+    writeln!(out, "{message}")?;
+    Ok(())
+}
 
-    if let Some(note_text) = note {
-        let note_id = db::upsert_note(tx, &note_text, &args.link, Some(&link_id), &now)?;
-        for tag_name in &args.tag {
-            let tag_id = get_tag_id(tx, tag_name)?;
-            db::tag_note(tx, note_id, tag_id)?;
+fn history_cmd(
+    tx: &Transaction,
+    args: &HistoryArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let operations = db::get_operations(tx, args.limit)?;
+    let output = match args.format {
+        ListOutputFormat::Table => {
+            let mut table = ui::new_table(ui_config, TableStyle::Utf8Borders);
+            table.set_header(vec!["When", "Operation", "Type", "Item"]);
+            for operation in &operations {
+                table.add_row(vec![
+                    operation.created_at.strftime("%F %T").to_string(),
+                    operation.operation.clone(),
+                    operation.item_type.clone(),
+                    operation.label(),
+                ]);
+            }
+            table.to_string()
         }
-    }
+        ListOutputFormat::Json => as_json(&operations, json_pretty)?,
+        ListOutputFormat::Yaml => as_yaml(&operations)?,
+        ListOutputFormat::Tsv => as_tsv(
+            &operations
+                .iter()
+                .map(|o| {
+                    vec![
+                        o.id.to_string(),
+                        o.created_at.to_string(),
+                        o.operation.clone(),
+                        o.item_type.clone(),
+                        o.label(),
+                    ]
+                })
+                .collect::<Vec<_>>(),
+        ),
+    };
+    writeln!(out, "{output}")?;
+    Ok(())
+}
 
-    if let Some(related_link) = &args.related_link {
-        // TODO: We should I think grab title using Readability, even if we don't
-        // need or want description or contents.
-        let insert_vals = db::LinkInsert {
-            url: related_link,
-            title: None,
-            description: None,
-            content: None,
-            is_primary: false,
-            timestamp: &now,
-        };
-        let related_link_id = db::insert_link(tx, &insert_vals, true)?;
-        db::relate_links(tx, link_id, related_link_id, args.relation.as_deref())?;
+fn remove_cmd(tx: &Transaction, args: &RemoveArgs, out: &mut dyn Write) -> Result<()> {
+    let item = match &args.item {
+        Some(item) => item,
+        None => return remove_interactive(tx, args, out),
+    };
+    let mut which: Vec<&str> = vec![];
+    if remove_link(tx, item)? {
+        which.push("link");
+    }
+    if remove_note(tx, item)? {
+        which.push("note");
+    }
+    if which.is_empty() {
+        match find_link_by_title(tx, item, args.first)? {
+            Some(link) => {
+                let url = link.url.clone();
+                remove_link_item(tx, link)?;
+                writeln!(out, "Removed link for <{url}>")?;
+            }
+            None => writeln!(out, "<{item}> not found")?,
+        }
+    } else {
+        let message = which.join(" and ");
+        writeln!(out, "Removed {message} for <{item}>")?;
     }
-
-    println!("Added bookmark for <{}>", args.link);
     Ok(())
 }
 
-fn list_cmd(tx: &Transaction, args: &ListArgs) -> Result<()> {
-    let tags = if args.tag.is_empty() {
-        vec![]
+// The links being chosen between are shown on stderr, with dialoguer's
+// prompts, so that only the outcome goes to `out`.
+fn remove_interactive(tx: &Transaction, args: &RemoveArgs, out: &mut dyn Write) -> Result<()> {
+    let candidates = db::get_links(
+        tx,
+        args.filter.tag_slugs()?,
+        None,
+        &args.filter.link_filter(),
+        false,
+    )?;
+    let total = candidates.len();
+    let choices = ["Delete", "Keep", "Quit"];
+    let mut to_delete: Vec<Link> = vec![];
+    for (i, link) in candidates.into_iter().enumerate() {
+        eprintln!(
+            "[{}/{}] {}\n      {}",
+            i + 1,
+            total,
+            link.title.as_deref().unwrap_or("(untitled)"),
+            link.url
+        );
+        let choice = dialoguer::Select::new()
+            .items(choices)
+            .default(1)
+            .interact()?;
+        match choices[choice] {
+            "Delete" => to_delete.push(link),
+            "Quit" => break,
+            _ => {}
+        }
+    }
+    if to_delete.is_empty() {
+        writeln!(out, "Nothing to remove")?;
+        return Ok(());
+    }
+    let confirmed = dialoguer::Confirm::new()
+        .with_prompt(format!("Remove {} link(s)?", to_delete.len()))
+        .default(false)
+        .interact()?;
+    if confirmed {
+        let count = to_delete.len();
+        for link in to_delete {
+            remove_link_item(tx, link)?;
+        }
+        writeln!(out, "Removed {count} link(s)")?;
     } else {
-        args.tag
-            .iter()
-            .map(|t| util::slugify(t))
-            .collect::<Result<Vec<_>>>()?
-    };
-    let items = db::get_links(tx, tags, None)?;
-    let output = match args.format {
-        ListOutputFormat::Table => list_as_table(items)?,
-    };
-    println!("{output}");
+        writeln!(out, "Nothing removed")?;
+    }
     Ok(())
 }
 
-fn link_as_table(
-    link: Link,
-    tags: Vec<Tag>,
-    note: Option<Note>,
-    related_links: Vec<(String, Option<String>)>,
+fn search_cmd(
+    tx: &Transaction,
+    args: &SearchArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let output = search_output(tx, args, json_pretty, ui_config)?;
+    writeln!(out, "{output}")?;
+    Ok(())
+}
+
+// Search results that are only links, as for --links-only or --regex.
+fn links_search_output(
+    tx: &Transaction,
+    hits: Vec<SearchHit>,
+    title: &str,
+    footer: Option<String>,
+    args: &SearchArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
 ) -> Result<String> {
-    let mut table = Table::new();
-    table
-        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
-        .load_preset(comfy_table::presets::UTF8_FULL)
-        .apply_modifier(comfy_table::modifiers::UTF8_ROUND_CORNERS);
-    table.add_row(vec![
-        "Title",
-        link.title.as_ref().unwrap_or(&"".to_string()),
-    ]);
-    table.add_row(vec!["URL", link.url.as_ref()]);
-    table.add_row(vec![
-        "Description",
-        link.description.as_ref().unwrap_or(&"".to_string()),
-    ]);
-    table.add_row(vec![
-        "Added".to_string(),
-        link.created_at.strftime("%F").to_string(),
-    ]);
-    if !tags.is_empty() {
-        table.add_row(vec![
-            "Tags".to_string(),
-            tags.iter()
-                .map(|t| t.name.as_str())
-                .collect::<Vec<_>>()
-                .join(", "),
-        ]);
+    Ok(match args.format {
+        LinkListFormat::Table => {
+            let mut output = search_hits_as_table(tx, &hits, args.verbose, ui_config)?;
+            if let Some(footer) = footer {
+                output.push_str(&format!("\n{footer}"));
+            }
+            output
+        }
+        LinkListFormat::Json => as_json(&hits, json_pretty)?,
+        LinkListFormat::Yaml => as_yaml(&hits)?,
+        LinkListFormat::Tsv => links_as_tsv(tx, &hit_links(&hits))?,
+        LinkListFormat::Atom => links_as_atom(tx, &hit_links(&hits), title)?,
+    })
+}
+
+// FTS can't do regular expressions, so every link is checked in memory.
+fn regex_search_links(
+    tx: &Transaction,
+    pattern: &str,
+    field: Option<RegexField>,
+    filter: &db::LinkFilter,
+) -> Result<Vec<Link>> {
+    let re = regex::Regex::new(pattern)
+        .with_context(|| format!("`{pattern}` is not a valid regular expression"))?;
+    let mut matches = vec![];
+    let with_content = field == Some(RegexField::Content);
+    for link in db::get_links(tx, vec![], None, filter, with_content)? {
+        let is_match = match field {
+            Some(RegexField::Url) => re.is_match(link.url.as_str()),
+            Some(RegexField::Title) => link.title.as_deref().is_some_and(|t| re.is_match(t)),
+            Some(RegexField::Description) => {
+                link.description.as_deref().is_some_and(|d| re.is_match(d))
+            }
+            Some(RegexField::Content) => link.content.as_deref().is_some_and(|c| re.is_match(c)),
+            None => {
+                re.is_match(link.url.as_str())
+                    || link.title.as_deref().is_some_and(|t| re.is_match(t))
+                    || link.description.as_deref().is_some_and(|d| re.is_match(d))
+            }
+        };
+        if is_match {
+            matches.push(link);
+        }
     }
-    if !related_links.is_empty() {
-        table.add_row(vec![
-            "See Also".to_string(),
-            related_links
-                .iter()
-                .map(|rl| {
-                    if let Some(relation) = &rl.1 {
-                        format!("{} ({relation})", rl.0)
-                    } else {
-                        rl.0.to_string()
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join("\n"),
-        ]);
+    Ok(matches)
+}
+
+// The list table, with a Score column added for `verbose`.
+fn search_hits_as_table(
+    tx: &Transaction,
+    hits: &[SearchHit],
+    verbose: bool,
+    ui_config: &UiConfig,
+) -> Result<String> {
+    let mut table = ui::new_table(ui_config, TableStyle::Utf8Borders);
+    let mut header: Vec<_> = DEFAULT_LIST_COLUMNS.iter().map(|c| c.header()).collect();
+    if verbose {
+        header.push("Score");
     }
-    if let Some(note) = note {
-        let content = note.content.as_str().trim();
-        table.add_row(vec!["Note", content]);
+    table.set_header(header);
+    for hit in hits {
+        let mut row = vec![];
+        for column in DEFAULT_LIST_COLUMNS {
+            row.push(list_cell(tx, &hit.link, column, ui_config)?);
+        }
+        if verbose {
+            row.push(hit.relevance.map(|r| format!("{r:.4}")).unwrap_or_default());
+        }
+        table.add_row(row);
     }
     Ok(table.to_string())
 }
 
-fn list_as_table(items: Vec<Link>) -> Result<String> {
-    let mut table = Table::new();
-    table
-        .set_header(vec!["URL", "Title", "Created"])
-        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
-        .load_preset(comfy_table::presets::UTF8_BORDERS_ONLY)
-        .apply_modifier(comfy_table::modifiers::UTF8_ROUND_CORNERS);
-    for item in &items {
-        table.add_row(vec![
-            &item.url.to_string(),
-            item.title.as_ref().unwrap_or(&"".to_string()),
-            &item.created_at.strftime("%F").to_string(),
-        ]);
+fn hit_links(hits: &[SearchHit]) -> Vec<Link> {
+    hits.iter().map(|hit| hit.link.clone()).collect()
+}
+
+// The "Showing 21-40 of 53 links" line under a table, when the page doesn't
+// hold every match.
+fn page_footer(offset: usize, shown: usize, total: usize, noun: &str) -> Option<String> {
+    if shown == total {
+        None
+    } else if shown == 0 {
+        Some(format!("Showing 0 of {total} {noun}"))
+    } else {
+        Some(format!(
+            "Showing {}-{} of {total} {noun}",
+            offset + 1,
+            offset + shown
+        ))
     }
-    Ok(table.to_string())
 }
 
-fn note_cmd(tx: &Transaction, args: &NoteArgs) -> Result<()> {
-    let now = now()?;
-    let title = match &args.title {
-        Some(given_title) => given_title,
-        None => &now,
+// A link found by `search`, with its full-text rank (BM25, negative, nearer
+// zero is better) when it was found by a full-text match.
+#[derive(Debug, Serialize)]
+struct SearchHit {
+    #[serde(flatten)]
+    link: Link,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    relevance: Option<f64>,
+}
+
+impl SearchHit {
+    fn unranked(links: Vec<Link>) -> Vec<SearchHit> {
+        links
+            .into_iter()
+            .map(|link| SearchHit {
+                link,
+                relevance: None,
+            })
+            .collect()
+    }
+}
+
+// What `search` prints as JSON or YAML when it matches both links and notes.
+// Searches of only one or the other print a plain list.
+#[derive(Serialize)]
+struct SearchResults<'a> {
+    links: &'a [SearchHit],
+    notes: &'a [NoteDetail<'a>],
+}
+
+fn search_output(
+    tx: &Transaction,
+    args: &SearchArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+) -> Result<String> {
+    let filter = args.dates.link_filter();
+    let offset = match args.page {
+        Some(0) => anyhow::bail!("Pages are numbered from 1"),
+        Some(page) => (page - 1).saturating_mul(args.limit),
+        None => args.offset.unwrap_or(0),
     };
-    let content = match db::get_note_by_title(tx, title)? {
-        Some(existing_note) => existing_note.content,
-        None => "".to_string(),
+    let paginate = |links: Vec<Link>| -> Vec<Link> {
+        links.into_iter().skip(offset).take(args.limit).collect()
     };
-    let note = if let Some(message) = &args.message {
-        if content.is_empty() {
-            message.clone()
-        } else {
-            let mut new_note = content;
-            new_note.push('\n');
-            new_note.push_str(message);
-            new_note
+    if let Some(pattern) = &args.regex {
+        let link_items = regex_search_links(tx, pattern, args.regex_field, &filter)?;
+        if args.count {
+            return count_output(link_items.len(), &args.format, json_pretty);
         }
+        let total = link_items.len();
+        let hits = SearchHit::unranked(paginate(link_items));
+        let footer = page_footer(offset, hits.len(), total, "links");
+        let title = format!("Links matching /{pattern}/");
+        return links_search_output(tx, hits, &title, footer, args, json_pretty, ui_config);
+    }
+    let search_term = args.term.as_deref().unwrap_or_default();
+    // Notes are only searched by content, so --title-only and --all, which
+    // are about links' other fields, leave them out.
+    let notes = if args.links_only || args.title_only || args.all {
+        vec![]
     } else {
-        edit::edit(content)?
+        let query = util::parse_fts_query(search_term)?;
+        db::search_notes(tx, &query, filter.after, filter.before)?
     };
-    if note.is_empty() {
-        println!("No note to add");
+    if args.count {
+        let count = if args.notes_only {
+            0
+        } else if args.title_only {
+            db::search_links_by_title(tx, search_term, &filter)?.len()
+        } else if args.all {
+            db::search_links_all_fields(tx, search_term, &filter)?.len()
+        } else {
+            let query = util::parse_fts_query(search_term)?;
+            db::count_links(tx, vec![], Some(&query), &filter)?
+        };
+        return count_output(count + notes.len(), &args.format, json_pretty);
+    }
+    let (hits, total) = if args.notes_only {
+        (vec![], 0)
+    } else if args.title_only {
+        let links = db::search_links_by_title(tx, search_term, &filter)?;
+        let total = links.len();
+        (SearchHit::unranked(paginate(links)), total)
+    } else if args.all {
+        let links = db::search_links_all_fields(tx, search_term, &filter)?;
+        let total = links.len();
+        (SearchHit::unranked(paginate(links)), total)
     } else {
-        let note_id = db::upsert_note(tx, &note, title, None, &now)?;
-        for tag_name in &args.tag {
-            let tag_id = get_tag_id(tx, tag_name)?;
-            db::tag_note(tx, note_id, tag_id)?;
+        // Only full-text matches have a rank, and only they are paged by the
+        // query itself.
+        let page_filter = db::LinkFilter {
+            by_relevance: true,
+            limit: Some(args.limit),
+            offset,
+            ..args.dates.link_filter()
+        };
+        let query = util::parse_fts_query(search_term)?;
+        let total = db::count_links(tx, vec![], Some(&query), &filter)?;
+        (db::search_links(tx, &query, &page_filter)?, total)
+    };
+    let footer = page_footer(offset, hits.len(), total, "links");
+    if args.links_only {
+        let title = format!("Links matching {search_term}");
+        return links_search_output(tx, hits, &title, footer, args, json_pretty, ui_config);
+    }
+    // Notes are paged alongside links: the same page of each.
+    let notes_total = notes.len();
+    let notes: Vec<Note> = notes.into_iter().skip(offset).take(args.limit).collect();
+    let notes_footer = page_footer(offset, notes.len(), notes_total, "notes");
+    let notes_table = |notes: Vec<Note>| -> Result<String> {
+        let mut output = notes_with_urls_as_table(tx, notes, ui_config)?;
+        if let Some(footer) = &notes_footer {
+            output.push_str(&format!("\n{footer}"));
+        }
+        Ok(output)
+    };
+    let note_details = notes.iter().map(NoteDetail::new).collect::<Vec<_>>();
+    if args.notes_only {
+        return Ok(match args.format {
+            LinkListFormat::Table => notes_table(notes)?,
+            LinkListFormat::Json => as_json(&note_details, json_pretty)?,
+            LinkListFormat::Yaml => as_yaml(&note_details)?,
+            LinkListFormat::Tsv => notes_as_tsv(tx, &notes)?,
+            LinkListFormat::Atom => anyhow::bail!("Atom feeds only carry links, not notes"),
+        });
+    }
+    let output = match args.format {
+        LinkListFormat::Table => {
+            let mut output = search_hits_as_table(tx, &hits, args.verbose, ui_config)?;
+            if let Some(footer) = footer {
+                output.push_str(&format!("\n{footer}"));
+            }
+            if notes_total > 0 {
+                output.push_str("\n\n");
+                output.push_str(&notes_table(notes)?);
+            }
+            output
         }
-        println!("Added note <{}>", &title);
-    }
-    Ok(())
+        // Links and notes are kept apart, as their fields differ.
+        LinkListFormat::Json => as_json(
+            &SearchResults {
+                links: &hits,
+                notes: &note_details,
+            },
+            json_pretty,
+        )?,
+        LinkListFormat::Yaml => as_yaml(&SearchResults {
+            links: &hits,
+            notes: &note_details,
+        })?,
+        // Links and notes share the same columns, so one block of rows serves.
+        LinkListFormat::Tsv => {
+            let mut output = links_as_tsv(tx, &hit_links(&hits))?;
+            if !hits.is_empty() && !notes.is_empty() {
+                output.push('\n');
+            }
+            output.push_str(&notes_as_tsv(tx, &notes)?);
+            output
+        }
+        // Feeds only carry links, so any notes are left out.
+        LinkListFormat::Atom => links_as_atom(
+            tx,
+            &hit_links(&hits),
+            &format!("Links matching {search_term}"),
+        )?,
+    };
+    Ok(output)
 }
 
-fn remove_cmd(tx: &Transaction, args: &RemoveArgs) -> Result<()> {
-    let item = &args.item;
-    let mut which: Vec<&str> = vec![];
-    if let Some(mut link) = db::get_link(tx, db::TermOrId::Term(item), db::IsPrimary::PrimaryOnly)?
-    {
-        let inverse_relations = db::get_inverse_related_links(tx, &link.id)?;
-        if inverse_relations.is_empty() {
-            db::delete_link(tx, &link.id)?;
+fn serve_cmd(db_path: &std::path::Path, args: &ServeArgs, out: &mut dyn Write) -> Result<()> {
+    let mut conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Unable to open database at {:?}", db_path))?;
+    let addr = format!("{}:{}", args.host, args.port);
+    let server = tiny_http::Server::http(&addr)
+        .map_err(|e| anyhow!("Unable to listen on {}: {}", addr, e))?;
+    writeln!(out, "Serving on http://{addr}")?;
+    // The server runs until it's stopped, so don't leave this in a buffer.
+    out.flush()?;
+    let content_type = tiny_http::Header::from_bytes("Content-Type", "application/json")
+        .map_err(|_| anyhow!("Invalid Content-Type header"))?;
+    for request in server.incoming_requests() {
+        let (status, body) = if *request.method() == tiny_http::Method::Get {
+            // Each request reads from a fresh snapshot of the database. Any
+            // failure, even to start reading, fails only this request.
+            let result = conn
+                .transaction()
+                .map_err(anyhow::Error::from)
+                .and_then(|tx| serve_route(&tx, request.url()));
+            match result {
+                Ok(Some(body)) => (200, body),
+                Ok(None) => (404, serde_json::json!({"error": "not found"}).to_string()),
+                Err(e) => {
+                    let status = if e.is::<BadRequest>() { 400 } else { 500 };
+                    (
+                        status,
+                        serde_json::json!({"error": e.to_string()}).to_string(),
+                    )
+                }
+            }
         } else {
-            link.is_primary = false;
-            db::update_link(tx, &link)?;
-            db::delete_item_tags(tx, &link.id)?;
-            db::delete_related_links(tx, Some(&link.id), None)?;
-            db::delete_content(tx, &link.id)?;
+            let body = serde_json::json!({"error": "method not allowed"});
+            (405, body.to_string())
+        };
+        let response = tiny_http::Response::from_string(body)
+            .with_status_code(status)
+            .with_header(content_type.clone());
+        if let Err(e) = request.respond(response) {
+            eprintln!("Unable to send response: {e}");
         }
-        which.push("link");
-    }
-    if let Some(note) = db::get_note_by_title(tx, item)? {
-        db::delete_note(tx, &note.id)?;
-        which.push("note");
-    }
-    if which.is_empty() {
-        println!("<{item}> not found");
-    } else {
-        let message = which.join(" and ");
-        println!("Removed {message} for <{item}>");
     }
     Ok(())
 }
 
-fn search_cmd(tx: &Transaction, args: &SearchArgs) -> Result<()> {
-    let search_term = &args.term;
-    let link_items = db::search_links(tx, search_term.as_str())?;
-    let output = match args.format {
-        ListOutputFormat::Table => list_as_table(link_items)?,
+#[derive(Debug)]
+struct BadRequest(String);
+
+impl std::fmt::Display for BadRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for BadRequest {}
+
+fn serve_route(tx: &Transaction, request_url: &str) -> Result<Option<String>> {
+    // tiny_http hands us only the path and query, so give it a base to parse against.
+    let url = Url::parse("http://localhost")?.join(request_url)?;
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|s| s.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+    let param = |key: &str| -> Vec<String> {
+        url.query_pairs()
+            .filter(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+            .collect()
     };
-    println!("{output}");
-    Ok(())
+    let usize_param = |key: &str| -> Result<Option<usize>> {
+        match param(key).first() {
+            Some(value) => Ok(Some(value.parse().map_err(|_| {
+                BadRequest(format!("`{key}` must be a non-negative integer"))
+            })?)),
+            None => Ok(None),
+        }
+    };
+    let parse_id = |id: &str| -> Result<TableId> {
+        Ok(Uuid::parse_str(id).map_err(|_| BadRequest(format!("`{id}` is not a valid ID")))?)
+    };
+    let tags = param("tag")
+        .iter()
+        .map(|t| util::slugify(t).map_err(|e| BadRequest(e.to_string())))
+        .collect::<Result<Vec<_>, _>>()?;
+    let body = match segments.as_slice() {
+        ["links"] => {
+            let search = param("search").into_iter().next();
+            let links = db::get_links(
+                tx,
+                tags,
+                search.as_deref(),
+                &db::LinkFilter::default(),
+                false,
+            )?;
+            let offset = usize_param("offset")?.unwrap_or(0);
+            let limit = usize_param("limit")?.unwrap_or(links.len());
+            let page: Vec<&Link> = links.iter().skip(offset).take(limit).collect();
+            serde_json::to_string(&page)?
+        }
+        ["links", id] => {
+            let link = db::get_link(
+                tx,
+                db::TermOrId::Id(parse_id(id)?),
+                db::IsPrimary::PrimaryOnly,
+            )?;
+            match link {
+                Some(link) => {
+                    let tags = db::tags_for_item(tx, &link.id)?;
+                    let note = db::get_note_by_link_id(tx, &link.id)?;
+                    let related_links = db::related_links(tx, &link.id)?;
+                    serde_json::to_string(&link_detail(&link, &tags, &note, &related_links))?
+                }
+                None => return Ok(None),
+            }
+        }
+        ["notes"] => {
+            let notes = db::get_notes(tx, tags, None, None)?;
+            let details: Vec<NoteDetail> = notes.iter().map(NoteDetail::new).collect();
+            serde_json::to_string(&details)?
+        }
+        ["notes", id] => match db::get_note_by_id(tx, &parse_id(id)?)? {
+            Some(note) => serde_json::to_string(&NoteDetail::new(&note))?,
+            None => return Ok(None),
+        },
+        ["tags"] => serde_json::to_string(&db::get_tags(tx)?)?,
+        _ => return Ok(None),
+    };
+    Ok(Some(body))
 }
 
-fn show_cmd(tx: &Transaction, args: &ShowArgs) -> Result<()> {
-    let link = db::get_link(
+fn show_cmd(
+    tx: &Transaction,
+    args: &ShowArgs,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let link = match db::get_link(
         tx,
         db::TermOrId::Term(args.term.as_str()),
         db::IsPrimary::PrimaryOnly,
-    )?;
+    )? {
+        Some(link) => Some(link),
+        None => find_link_by_title(tx, &args.term, args.first)?,
+    };
     let output = if let Some(link) = link {
         let tags = db::tags_for_item(tx, &link.id)?;
+        if args.brief {
+            writeln!(
+                out,
+                "{}",
+                link_as_brief(&link, &tags, &args.format, json_pretty, ui_config)?
+            )?;
+            return Ok(());
+        }
         let note = db::get_note_by_link_id(tx, &link.id)?;
+        if args.all_related {
+            let edges = db::related_links_recursive(tx, &link.id, args.depth)?;
+            let tree = related_link_tree(&edges, &mut vec![link.id]);
+            let mut flat = vec![];
+            let indent = matches!(args.format, ShowFormat::Table);
+            flatten_related_link_tree(&tree, 0, indent, &mut flat);
+            let detail = |link| LinkDetail {
+                link,
+                tags: tags.iter().map(|t| t.name.as_str()).collect(),
+                note: note.as_ref().map(NoteDetail::new),
+                related_links: tree,
+            };
+            let output = match args.format {
+                ShowFormat::Table => link_as_table(link, tags, note, flat, ui_config)?,
+                ShowFormat::Json => as_json(&detail(&link), json_pretty)?,
+                ShowFormat::Yaml => as_yaml(&detail(&link))?,
+                ShowFormat::Tsv => link_as_tsv(&link, &tags, &note, &flat),
+                ShowFormat::Csv => link_as_csv(&link, &tags, &note, &flat)?,
+            };
+            writeln!(out, "{output}")?;
+            return Ok(());
+        }
+        if args.expand_related {
+            let related = db::get_related_links_full(tx, &link.id)?
+                .into_iter()
+                .map(|(related, relation)| {
+                    let tags = db::tags_for_item(tx, &related.id)?;
+                    Ok((related, relation, tags))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let detail = || ExpandedLinkDetail {
+                link: &link,
+                tags: tags.iter().map(|t| t.name.as_str()).collect(),
+                note: note.as_ref().map(NoteDetail::new),
+                related_links: related
+                    .iter()
+                    .map(|(link, relation, tags)| ExpandedRelatedLink {
+                        link,
+                        relation: relation.as_deref(),
+                        tags: tags.iter().map(|t| t.name.as_str()).collect(),
+                    })
+                    .collect(),
+            };
+            let urls = || {
+                related
+                    .iter()
+                    .map(|(link, relation, _)| (link.url.to_string(), relation.clone()))
+                    .collect::<Vec<_>>()
+            };
+            let output = match args.format {
+                ShowFormat::Table => {
+                    expanded_link_as_table(&link, &tags, &note, &related, ui_config)?
+                }
+                ShowFormat::Json => as_json(&detail(), json_pretty)?,
+                ShowFormat::Yaml => as_yaml(&detail())?,
+                ShowFormat::Tsv => link_as_tsv(&link, &tags, &note, &urls()),
+                ShowFormat::Csv => link_as_csv(&link, &tags, &note, &urls())?,
+            };
+            writeln!(out, "{output}")?;
+            return Ok(());
+        }
         let related_links = db::related_links(tx, &link.id)?;
         match args.format {
-            ListOutputFormat::Table => link_as_table(link, tags, note, related_links)?,
+            ShowFormat::Table => link_as_table(link, tags, note, related_links, ui_config)?,
+            ShowFormat::Json => as_json(
+                &link_detail(&link, &tags, &note, &related_links),
+                json_pretty,
+            )?,
+            ShowFormat::Yaml => as_yaml(&link_detail(&link, &tags, &note, &related_links))?,
+            ShowFormat::Tsv => link_as_tsv(&link, &tags, &note, &related_links),
+            ShowFormat::Csv => link_as_csv(&link, &tags, &note, &related_links)?,
         }
     } else {
         format!("<{}> not found", args.term).to_string()
     };
-    println!("{output}");
+    writeln!(out, "{output}")?;
     Ok(())
 }
 
+fn link_as_brief(
+    link: &Link,
+    tags: &[Tag],
+    format: &ShowFormat,
+    json_pretty: bool,
+    ui_config: &UiConfig,
+) -> Result<String> {
+    let brief = LinkBrief {
+        id: link.id,
+        url: &link.url,
+        title: link.title.as_deref(),
+        tags: tags.iter().map(|t| t.name.as_str()).collect(),
+    };
+    Ok(match format {
+        ShowFormat::Table => {
+            let mut parts = vec![];
+            if let Some(title) = brief.title.filter(|t| !t.is_empty()) {
+                parts.push(title.to_string());
+            }
+            parts.push(link.url.to_string());
+            parts.push(ui::format_timestamp(
+                &link.created_at,
+                &ui_config.date_format,
+            ));
+            if !brief.tags.is_empty() {
+                parts.push(format!("Tags: {}", brief.tags.join(", ")));
+            }
+            parts.join(" \u{2014} ")
+        }
+        ShowFormat::Json => as_json(&brief, json_pretty)?,
+        ShowFormat::Yaml => as_yaml(&brief)?,
+        ShowFormat::Tsv => as_tsv(&[vec![
+            link.id.to_string(),
+            link.url.to_string(),
+            link.title.clone().unwrap_or_default(),
+            tag_slugs(tags),
+        ]]),
+        ShowFormat::Csv => as_csv(
+            &["id", "url", "title", "tags"],
+            &[
+                link.id.to_string(),
+                link.url.to_string(),
+                link.title.clone().unwrap_or_default(),
+                tag_slugs(tags),
+            ],
+        )?,
+    })
+}
+
+// The fallback for a term that isn't a saved URL: a link whose title contains
+// it. Several matches are an error unless `first` picks the oldest. The
+// notice goes to stderr so as not to disturb JSON or YAML output.
+fn find_link_by_title(tx: &Transaction, term: &str, first: bool) -> Result<Option<Link>> {
+    let mut matches = db::find_links_by_title_fragment(tx, term)?;
+    if matches.len() > 1 && !first {
+        let candidates = matches
+            .iter()
+            .map(|l| format!("  {} <{}>", l.title.as_deref().unwrap_or_default(), l.url))
+            .collect::<Vec<_>>()
+            .join("\n");
+        return Err(anyhow!(
+            "'{term}' matches the titles of {} links; give a URL or use --first:\n{candidates}",
+            matches.len()
+        ));
+    }
+    if matches.is_empty() {
+        return Ok(None);
+    }
+    let link = matches.remove(0);
+    eprintln!(
+        "No exact match for '{term}'; using: {}",
+        link.title.as_deref().unwrap_or_default()
+    );
+    Ok(Some(link))
+}
+
+fn watch_cmd(conn: &mut Connection, args: &WatchArgs, ui_config: &UiConfig) -> Result<()> {
+    terminal::enable_raw_mode()?;
+    let result = watch_loop(conn, args, ui_config);
+    // Always try to hand the terminal back in a usable state, even on error.
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn watch_loop(conn: &mut Connection, args: &WatchArgs, ui_config: &UiConfig) -> Result<()> {
+    let interval = Duration::from_secs(args.interval.max(1));
+    let tick = Duration::from_millis(100);
+    let mut stdout = std::io::stdout();
+    let mut previous: Option<String> = None;
+    loop {
+        // Each refresh gets its own (read-only, never committed) transaction
+        // so that we pick up links added by other processes.
+        let output = {
+            let tx = conn.transaction()?;
+            // JSON stays compact so that new links show up as single changed lines.
+            match &args.term {
+                Some(term) => {
+                    let search_args = SearchArgs {
+                        term: Some(term.clone()),
+                        format: args.list_args.format.clone(),
+                        title_only: false,
+                        all: false,
+                        count: args.list_args.count,
+                        notes_only: false,
+                        links_only: true,
+                        regex: None,
+                        regex_field: None,
+                        limit: usize::MAX,
+                        offset: None,
+                        page: None,
+                        verbose: false,
+                        dates: args.list_args.filter.dates.clone(),
+                    };
+                    search_output(&tx, &search_args, false, ui_config)?
+                }
+                None => list_output(&tx, &args.list_args, false, ui_config)?,
+            }
+        };
+        let previous_lines: HashSet<&str> = match &previous {
+            Some(previous) => previous.lines().collect(),
+            None => HashSet::new(),
+        };
+        queue!(stdout, Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+        // Raw mode doesn't translate newlines, so we emit our own carriage returns.
+        for line in output.lines() {
+            if previous.is_some() && !previous_lines.contains(line) {
+                queue!(stdout, PrintStyledContent(line.reverse()), Print("\r\n"))?;
+            } else {
+                queue!(stdout, Print(line), Print("\r\n"))?;
+            }
+        }
+        queue!(
+            stdout,
+            Print(format!(
+                "Refreshing every {}s; press q to quit\r\n",
+                interval.as_secs()
+            ))
+        )?;
+        stdout.flush()?;
+        previous = Some(output);
+
+        let deadline = Instant::now() + interval;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            while event::poll(Duration::ZERO)? {
+                if let Event::Key(key) = event::read()? {
+                    let is_ctrl_c = key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL);
+                    if key.code == KeyCode::Char('q') || is_ctrl_c {
+                        return Ok(());
+                    }
+                }
+            }
+            std::thread::sleep(remaining.min(tick));
+        }
+    }
+}
+
 fn update_add_related_link_cmd(
     tx: &Transaction,
     link: &Link,
-    related_link: &String,
-    link_relation: &Option<String>,
+    related_link: &str,
+    link_relation: Option<RelationshipType>,
 ) -> Result<()> {
+    let related_url = util::validate_url(related_link, false)?;
     let now = now()?;
     let insert_vals = db::LinkInsert {
-        url: related_link,
+        url: related_url.as_str(),
         title: None,
         description: None,
         content: None,
         is_primary: false,
         timestamp: &now,
+        created_at: None,
+        source: None,
     };
     let related_link_id = db::insert_link(tx, &insert_vals, true)?;
     // TODO: Add a better error message for a duplicate related link.
-    db::relate_links(tx, link.id, related_link_id, link_relation.as_deref())?;
+    db::relate_links(tx, link.id, related_link_id, link_relation)?;
     Ok(())
 }
 
@@ -800,8 +7112,14 @@ fn update_add_tag_cmd(tx: &Transaction, link: &Link, tags: &Vec<String>) -> Resu
     Ok(())
 }
 
-fn update_refresh_cmd(tx: &Transaction, link: &mut Link) -> Result<()> {
-    let page_info = readability(link.url.as_ref())?;
+fn update_refresh_cmd(
+    tx: &Transaction,
+    link: &mut Link,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<()> {
+    // An explicit refresh should always go back to the network.
+    let page_info = readability(tx, link.url.as_ref(), &config.fetch, true)?;
     // TODO: We should eventually support user override for title and
     // description here.
     let title: Option<String> = if page_info.title.is_empty() {
@@ -814,9 +7132,17 @@ fn update_refresh_cmd(tx: &Transaction, link: &mut Link) -> Result<()> {
 
     link.title = title;
     link.description = description;
-    link.content = Some(text_content.to_string());
 
-    db::update_link(tx, &link)?;
+    db::update_link(tx, link)?;
+    db::mark_refreshed(tx, &link.id, &now()?)?;
+    // Re-indexing is the expensive part, so skip it if nothing has changed.
+    if link.content_sha256.as_deref() == Some(util::sha256(text_content).as_str()) {
+        writeln!(out, "Content unchanged")?;
+    } else {
+        db::delete_content(tx, &link.id)?;
+        db::insert_content(tx, &link.id, text_content)?;
+    }
+    link.content = Some(text_content.to_string());
 
     Ok(())
 }
@@ -825,6 +7151,7 @@ fn update_remove_related_link_cmd(
     tx: &Transaction,
     link: &Link,
     related_link_url: &String,
+    out: &mut dyn Write,
 ) -> Result<()> {
     let related_link = db::get_link(
         tx,
@@ -835,7 +7162,11 @@ fn update_remove_related_link_cmd(
         db::delete_related_links(tx, Some(&link.id), Some(&related_link.id))?;
         remove_orphaned_related_link(tx, &related_link)?;
     } else {
-        println!("<{}> is not related to <{}>", related_link_url, link.url);
+        writeln!(
+            out,
+            "<{}> is not related to <{}>",
+            related_link_url, link.url
+        )?;
     }
     Ok(())
 }
@@ -852,16 +7183,83 @@ fn remove_orphaned_related_link(tx: &Transaction, related_link: &Link) -> Result
 
 fn update_remove_tag_cmd(tx: &Transaction, link: &Link, tags: &Vec<String>) -> Result<()> {
     for tag_name in tags {
-        let tag_id = get_tag_id(tx, tag_name)?;
-        db::delete_item_tag(tx, &link.id, &tag_id)?;
+        // Unlike tagging, an unknown tag is never created here.
+        if let Some(tag_id) = db::get_tag_id_by_slug(tx, &util::slugify(tag_name)?)? {
+            db::untag_link(tx, link.id, tag_id)?;
+        }
     }
     Ok(())
 }
 
+mod ui {
+    use super::{util, TableStyle, Tag, UiConfig};
+    use comfy_table::{modifiers, presets, ContentArrangement, Table};
+    use crossterm::style::Stylize;
+    use jiff::Timestamp;
+
+    /// The comfy_table preset for a style, and the modifier to apply on top
+    /// of it, if any.
+    pub fn load_table_preset(style: &TableStyle) -> (&'static str, Option<&'static str>) {
+        match style {
+            TableStyle::Utf8Full => (presets::UTF8_FULL, Some(modifiers::UTF8_ROUND_CORNERS)),
+            TableStyle::Utf8Borders => (
+                presets::UTF8_BORDERS_ONLY,
+                Some(modifiers::UTF8_ROUND_CORNERS),
+            ),
+            TableStyle::Ascii => (presets::ASCII_FULL, None),
+            TableStyle::Minimal => (presets::NOTHING, None),
+        }
+    }
+
+    /// An empty table in the configured style, or `default` if none is set.
+    pub fn new_table(config: &UiConfig, default: TableStyle) -> Table {
+        let (preset, modifier) = load_table_preset(&config.table_style.unwrap_or(default));
+        let mut table = Table::new();
+        table
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .load_preset(preset);
+        if let Some(modifier) = modifier {
+            table.apply_modifier(modifier);
+        }
+        table
+    }
+
+    /// The tag's name, colored by namespace if tag colors are on.
+    pub fn tag_name(tag: &Tag, config: &UiConfig) -> String {
+        if !config.tag_colors {
+            return tag.name.clone();
+        }
+        let namespace = tag.name.split(':').next().unwrap_or_default();
+        tag.name
+            .as_str()
+            .with(util::tag_color(namespace))
+            .to_string()
+    }
+
+    /// The timestamp in the configured format, or as an ISO date if the
+    /// format is invalid.
+    pub fn format_timestamp(ts: &Timestamp, fmt: &str) -> String {
+        jiff::fmt::strtime::format(fmt, *ts).unwrap_or_else(|_| ts.strftime("%F").to_string())
+    }
+
+    /// Tag names for a table cell, separated by commas.
+    pub fn tag_names(tags: &[Tag], config: &UiConfig) -> String {
+        tags.iter()
+            .map(|t| tag_name(t, config))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
 mod db {
-    use anyhow::{anyhow, Result};
-    use rusqlite::{named_params, params_from_iter, ToSql, Transaction};
-    use uuid::Uuid;
+    use jiff::{Timestamp, Unit};
+    use meowpad::{MeowpadError, Result};
+    use rusqlite::functions::FunctionFlags;
+    use rusqlite::types::{Type, Value};
+    use rusqlite::{named_params, params, params_from_iter, Connection, ToSql, Transaction};
+    use std::collections::HashSet;
+    use std::sync::{Mutex, OnceLock};
+    use uuid::{ContextV7, Uuid};
 
     type TableId = super::TableId;
 
@@ -886,69 +7284,611 @@ mod db {
         }
     }
 
+    // The v7 timestamp is milliseconds, so the shared context puts the rest
+    // of the clock's precision into the counter bits and keeps IDs made in
+    // the same millisecond in order.
     fn get_uuid() -> Uuid {
+        static CONTEXT: OnceLock<Mutex<ContextV7>> = OnceLock::new();
+        let context = CONTEXT
+            .get_or_init(|| Mutex::new(ContextV7::new().with_additional_precision()))
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
         let now = jiff::Timestamp::now();
-        // NB: We should grab the microseconds and use them instead of 0.
-        let ts = uuid::Timestamp::from_unix(uuid::NoContext, now.as_second() as u64, 0);
+        let ts = uuid::Timestamp::from_unix(
+            &*context,
+            now.as_second() as u64,
+            now.subsec_nanosecond() as u32,
+        );
         Uuid::new_v7(ts)
     }
 
-    // LINKS
-    pub fn get_links(
-        tx: &Transaction,
-        tags: Vec<String>,
-        search_term: Option<&str>,
-    ) -> Result<Vec<super::Link>> {
-        let select = "SELECT
-            id, url, title, description, is_primary, created_at, modified_at
-            FROM link
-            ";
-        let where_clause = "WHERE is_primary IS TRUE";
-        let tag_filter = if tags.is_empty() {
-            "".to_string()
-        } else {
-            let qmarks: Vec<&str> = tags.iter().map(|_| "?").collect();
-            let joined = qmarks.join(", ");
-            format!(
-                "AND id in (SELECT link_id FROM item_tag WHERE tag_id in
-            (SELECT id FROM tag WHERE slug IN ({joined})))"
-            )
-        };
-        let search_filter = if search_term.is_some() {
-            "AND id in (SELECT link_id FROM link_content
-            WHERE link_content MATCH ?)"
-                .to_string()
-        } else {
-            "".to_string()
+    // LINKS
+    const LINK_COLUMNS: &str = "id, url, title, description, is_primary, created_at, \
+        modified_at, archived_at, read_at, is_favorite, content_sha256, pinned_order, meta, \
+        source, is_reading_list, local_archive_path";
+
+    // Conditions applied on top of any tag or search filtering. Bounds on when
+    // a link was added are inclusive for `after` and exclusive for `before`.
+    #[derive(Debug, Default)]
+    pub struct LinkFilter {
+        pub after: Option<Timestamp>,
+        pub before: Option<Timestamp>,
+        // Archived links only, rather than the (default) primary links.
+        pub archived: bool,
+        // Only links created after the link with this ID.
+        pub after_id: Option<TableId>,
+        // Only read (true) or unread (false) links.
+        pub read: Option<bool>,
+        pub favorites_only: bool,
+        pub reading_list_only: bool,
+        // Sort favorites ahead of everything else.
+        pub favorites_first: bool,
+        // Sort pinned links, by position, ahead of everything else
+        // (including favorites).
+        pub pinned_first: bool,
+        // Tag filters also match the tags' descendants.
+        pub include_subtags: bool,
+        // Tag filters match links with all of the tags, not just any of them.
+        pub match_all_tags: bool,
+        // Only links on this host; needs `create_url_host_function`.
+        pub domain: Option<String>,
+        // Secondary (related-only) links as well as primary ones.
+        pub include_secondary: bool,
+        // Only links saved this way, e.g. "manual".
+        pub source: Option<String>,
+        // Sort full-text matches best first; only used with a search term.
+        pub by_relevance: bool,
+        // At most this many links, after skipping `offset` of them.
+        pub limit: Option<usize>,
+        pub offset: usize,
+    }
+
+    // With `include_content`, each link's stored text is read in the same
+    // query, rather than one query per link.
+    pub fn get_links(
+        tx: &Transaction,
+        tags: Vec<String>,
+        search_term: Option<&str>,
+        filter: &LinkFilter,
+        include_content: bool,
+    ) -> Result<Vec<super::Link>> {
+        let links = get_ranked_links(tx, tags, search_term, filter, include_content)?;
+        Ok(links.into_iter().map(|(link, _)| link).collect())
+    }
+
+    // Each link comes with its full-text rank, if `filter.by_relevance` asked
+    // for one.
+    fn get_ranked_links(
+        tx: &Transaction,
+        tags: Vec<String>,
+        search_term: Option<&str>,
+        filter: &LinkFilter,
+        include_content: bool,
+    ) -> Result<Vec<(super::Link, Option<f64>)>> {
+        let mut all_params = vec![];
+        let ranked = match search_term {
+            Some(term) if filter.by_relevance => {
+                all_params.push(Value::Text(term.to_string()));
+                true
+            }
+            _ => false,
+        };
+        // Any extra columns follow the link's own: the content, then the rank.
+        let mut columns = LINK_COLUMNS.to_string();
+        let mut joins = String::new();
+        if include_content {
+            // Joined through a subquery so that SQLite materializes it with
+            // an index on link_id; joining the FTS table directly would scan
+            // it once per link.
+            columns.push_str(", lc.content");
+            joins.push_str(
+                "LEFT JOIN (SELECT link_id, content FROM link_content) AS lc
+                ON lc.link_id = link.id ",
+            );
+        }
+        if ranked {
+            // FTS5's rank is only available to a query that runs the MATCH.
+            columns.push_str(", fts.rank");
+            joins.push_str(
+                "JOIN (SELECT link_id AS ranked_id, rank FROM link_content
+                WHERE link_content MATCH ?) AS fts ON fts.ranked_id = link.id",
+            );
+        }
+        let select = format!("SELECT {columns} FROM link {joins}");
+        let (where_clause, where_params) = links_where(tags, search_term, filter)?;
+        all_params.extend(where_params);
+        let mut order_by = vec![];
+        if filter.pinned_first {
+            order_by.push("pinned_order IS NULL, pinned_order ASC");
+        }
+        if filter.favorites_first {
+            order_by.push("is_favorite DESC");
+        }
+        // BM25 ranks are negative, and lower is better.
+        if ranked {
+            order_by.push("fts.rank ASC");
+        }
+        order_by.push("created_at DESC");
+        let order = format!("ORDER BY {}", order_by.join(", "));
+        let mut query = format!("{} {} {}", select, where_clause, order);
+        if let Some(limit) = filter.limit {
+            // SQLite reads a negative limit as no limit at all.
+            query.push_str(" LIMIT ? OFFSET ?");
+            all_params.push(Value::Integer(i64::try_from(limit).unwrap_or(-1)));
+            all_params.push(Value::Integer(
+                i64::try_from(filter.offset).unwrap_or(i64::MAX),
+            ));
+        }
+        let mut stmt = tx.prepare(query.as_ref())?;
+        let query_params = params_from_iter(all_params.iter());
+        let mut rows = stmt.query(query_params)?;
+        let extra_index = LINK_COLUMNS.split(',').count();
+        let mut resp = vec![];
+        while let Some(row) = rows.next()? {
+            let mut link = link_from_row(row)?;
+            let mut index = extra_index;
+            if include_content {
+                link.content = row.get(index)?;
+                index += 1;
+            }
+            let relevance = if ranked { row.get(index)? } else { None };
+            resp.push((link, relevance));
+        }
+        Ok(resp)
+    }
+
+    pub fn count_links(
+        tx: &Transaction,
+        tags: Vec<String>,
+        search_term: Option<&str>,
+        filter: &LinkFilter,
+    ) -> Result<usize> {
+        let (where_clause, all_params) = links_where(tags, search_term, filter)?;
+        let query = format!("SELECT COUNT(*) FROM link {}", where_clause);
+        let count = tx.query_row(&query, params_from_iter(all_params.iter()), |row| {
+            row.get(0)
+        })?;
+        Ok(count)
+    }
+
+    // The WHERE clause, and its parameters, shared by `get_links` and
+    // `count_links`.
+    fn links_where(
+        tags: Vec<String>,
+        search_term: Option<&str>,
+        filter: &LinkFilter,
+    ) -> Result<(String, Vec<Value>)> {
+        let where_clause = "WHERE 1 = 1";
+        let tag_filter = if tags.is_empty() {
+            "".to_string()
+        } else {
+            let qmarks: Vec<&str> = tags.iter().map(|_| "?").collect();
+            let joined = qmarks.join(", ");
+            // UNION rather than UNION ALL, so that a cycle in the hierarchy
+            // can't recurse forever.
+            let tag_ids = if filter.include_subtags {
+                format!(
+                    "WITH RECURSIVE subtags(id) AS (
+                SELECT id FROM tag WHERE slug IN ({joined})
+                UNION SELECT tag.id FROM tag JOIN subtags ON tag.parent_id = subtags.id
+            ) SELECT id FROM subtags"
+                )
+            } else {
+                format!("SELECT id FROM tag WHERE slug IN ({joined})")
+            };
+            if filter.match_all_tags {
+                format!(
+                    "AND id IN (SELECT link_id FROM item_tag
+                    JOIN tag ON tag.id = item_tag.tag_id
+                    WHERE tag.slug IN ({joined})
+                    GROUP BY link_id HAVING COUNT(DISTINCT tag.slug) = ?)"
+                )
+            } else {
+                format!("AND id in (SELECT link_id FROM item_tag WHERE tag_id in ({tag_ids}))")
+            }
+        };
+        // Repeating a tag mustn't raise the number that all have to match.
+        // (Slugs are counted rather than tag IDs because tags named e.g.
+        // "Rust" and "rust" share a slug.)
+        let distinct_tags = tags.iter().collect::<HashSet<_>>().len();
+        let search_filter = if search_term.is_some() {
+            "AND id in (SELECT link_id FROM link_content
+            WHERE link_content MATCH ?)"
+                .to_string()
+        } else {
+            "".to_string()
+        };
+        let (link_filter, filter_params) = link_filter(filter)?;
+        let clause = format!(
+            "{} {} {} {}",
+            where_clause, tag_filter, search_filter, link_filter
+        );
+        let mut all_params: Vec<Value> = tags.into_iter().map(Value::Text).collect();
+        if filter.match_all_tags && distinct_tags > 0 {
+            all_params.push(Value::Integer(distinct_tags as i64));
+        }
+        if let Some(term) = search_term {
+            all_params.push(Value::Text(term.to_string()));
+        }
+        all_params.extend(filter_params);
+        Ok((clause, all_params))
+    }
+
+    // Matches primary links whose title contains the term, without touching
+    // the full-text index.
+    pub fn search_links_by_title(
+        tx: &Transaction,
+        term: &str,
+        filter: &LinkFilter,
+    ) -> Result<Vec<super::Link>> {
+        let (link_filter, filter_params) = link_filter(filter)?;
+        let query = format!(
+            "SELECT {LINK_COLUMNS}
+            FROM link
+            WHERE title LIKE '%' || ? || '%' ESCAPE '\\'
+            {link_filter}
+            ORDER BY created_at DESC"
+        );
+        let mut all_params = vec![Value::Text(escape_like(term))];
+        all_params.extend(filter_params);
+        let mut stmt = tx.prepare(&query)?;
+        let mut rows = stmt.query(params_from_iter(all_params.iter()))?;
+        let mut resp: Vec<super::Link> = vec![];
+        while let Some(row) = rows.next()? {
+            resp.push(link_from_row(row)?);
+        }
+        Ok(resp)
+    }
+
+    // Primary links whose titles contain `fragment`, ignoring case, oldest
+    // first.
+    pub fn find_links_by_title_fragment(
+        tx: &Transaction,
+        fragment: &str,
+    ) -> Result<Vec<super::Link>> {
+        let query = format!(
+            "SELECT {LINK_COLUMNS}
+            FROM link
+            WHERE LOWER(title) LIKE '%' || LOWER(?) || '%' ESCAPE '\\'
+            AND is_primary = true AND archived_at IS NULL
+            ORDER BY created_at ASC"
+        );
+        let mut stmt = tx.prepare(&query)?;
+        let mut rows = stmt.query([escape_like(fragment)])?;
+        let mut resp: Vec<super::Link> = vec![];
+        while let Some(row) = rows.next()? {
+            resp.push(link_from_row(row)?);
+        }
+        Ok(resp)
+    }
+
+    pub fn search_links_all_fields(
+        tx: &Transaction,
+        term: &str,
+        filter: &LinkFilter,
+    ) -> Result<Vec<super::Link>> {
+        let (link_filter, filter_params) = link_filter(filter)?;
+        // The UNION deduplicates ids, so a link matching on several fields is
+        // only returned once. Since the LIKE matches take the term literally,
+        // the full-text match does too, as a single quoted phrase; otherwise
+        // terms like `example.com` would be FTS syntax errors.
+        let query = format!(
+            "SELECT {LINK_COLUMNS}
+            FROM link
+            WHERE id IN (
+                SELECT link_id FROM link_content WHERE link_content MATCH ?
+                UNION SELECT id FROM link WHERE title LIKE '%' || ? || '%' ESCAPE '\\'
+                UNION SELECT id FROM link WHERE description LIKE '%' || ? || '%' ESCAPE '\\'
+                UNION SELECT id FROM link WHERE url LIKE '%' || ? || '%' ESCAPE '\\'
+            )
+            {link_filter}
+            ORDER BY created_at DESC"
+        );
+        let escaped = escape_like(term);
+        let mut all_params = vec![
+            Value::Text(format!("\"{}\"", term.replace('"', "\"\""))),
+            Value::Text(escaped.clone()),
+            Value::Text(escaped.clone()),
+            Value::Text(escaped),
+        ];
+        all_params.extend(filter_params);
+        let mut stmt = tx.prepare(&query)?;
+        let mut rows = stmt.query(params_from_iter(all_params.iter()))?;
+        let mut resp: Vec<super::Link> = vec![];
+        while let Some(row) = rows.next()? {
+            resp.push(link_from_row(row)?);
+        }
+        Ok(resp)
+    }
+
+    // Escapes LIKE wildcards so the term matches literally (with ESCAPE '\').
+    fn escape_like(term: &str) -> String {
+        term.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    }
+
+    fn link_filter(filter: &LinkFilter) -> Result<(String, Vec<Value>)> {
+        let mut clauses = if filter.archived {
+            String::from("AND archived_at IS NOT NULL ")
+        } else if filter.include_secondary {
+            String::from("AND archived_at IS NULL ")
+        } else {
+            String::from("AND is_primary IS TRUE AND archived_at IS NULL ")
+        };
+        let mut params = vec![];
+        // Timestamps are stored as second-precision UTC strings, which sort
+        // chronologically, so bounds can be compared as text.
+        if let Some(after) = filter.after {
+            clauses.push_str("AND created_at >= ? ");
+            params.push(Value::Text(after.round(Unit::Second)?.to_string()));
+        }
+        if let Some(before) = filter.before {
+            clauses.push_str("AND created_at < ? ");
+            params.push(Value::Text(before.round(Unit::Second)?.to_string()));
+        }
+        if filter.favorites_only {
+            clauses.push_str("AND is_favorite IS TRUE ");
+        }
+        if filter.reading_list_only {
+            clauses.push_str("AND is_reading_list IS TRUE ");
+        }
+        match filter.read {
+            Some(true) => clauses.push_str("AND read_at IS NOT NULL "),
+            Some(false) => clauses.push_str("AND read_at IS NULL "),
+            None => {}
+        }
+        // v7 UUIDs begin with their timestamp, so their bytes sort by creation.
+        if let Some(after_id) = filter.after_id {
+            clauses.push_str("AND id > ? ");
+            params.push(Value::Blob(after_id.as_bytes().to_vec()));
+        }
+        if let Some(domain) = &filter.domain {
+            clauses.push_str("AND url_host(url) = ? ");
+            params.push(Value::Text(domain.clone()));
+        }
+        if let Some(source) = &filter.source {
+            clauses.push_str("AND source = ? ");
+            params.push(Value::Text(source.clone()));
+        }
+        Ok((clauses, params))
+    }
+
+    pub struct DatabaseInfo {
+        pub sqlite_version: String,
+        pub size_bytes: usize,
+        pub schema_version: usize,
+        // None for tables the schema doesn't have (yet).
+        pub row_counts: Vec<(&'static str, Option<usize>)>,
+    }
+
+    pub fn database_info(conn: &Connection) -> Result<DatabaseInfo> {
+        let sqlite_version = conn.query_row("SELECT sqlite_version()", [], |row| row.get(0))?;
+        let page_count: usize = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: usize = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        // rusqlite_migration records the number of applied migrations here.
+        let schema_version = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let mut row_counts = vec![];
+        for table in [
+            "link",
+            "note",
+            "tag",
+            "item_tag",
+            "related_link",
+            "link_content",
+            "note_content",
+            "operation_log",
+        ] {
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+                [table],
+                |row| row.get(0),
+            )?;
+            let count = if exists {
+                Some(
+                    conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                        row.get(0)
+                    })?,
+                )
+            } else {
+                None
+            };
+            row_counts.push((table, count));
+        }
+        Ok(DatabaseInfo {
+            sqlite_version,
+            size_bytes: page_count * page_size,
+            schema_version,
+            row_counts,
+        })
+    }
+
+    // Deletes rows that foreign keys should have cascaded away but didn't,
+    // then, if asked, tags that label nothing (parents of other tags are
+    // kept, as in get_orphan_tags). Returns how many rows each step deleted.
+    pub fn repair(tx: &Transaction, unused_tags: bool) -> Result<Vec<(&'static str, usize)>> {
+        // RETURNING doesn't work on the full-text tables, which are virtual,
+        // so those are counted by `execute` instead.
+        let returning_count = |query: &str| -> Result<usize> {
+            let mut stmt = tx.prepare(query)?;
+            let mut rows = stmt.query([])?;
+            let mut count = 0;
+            while rows.next()?.is_some() {
+                count += 1;
+            }
+            Ok(count)
+        };
+        let item_tags = returning_count(
+            "DELETE FROM item_tag
+            WHERE tag_id NOT IN (SELECT id FROM tag)
+            OR (link_id IS NOT NULL AND link_id NOT IN (SELECT id FROM link))
+            OR (note_id IS NOT NULL AND note_id NOT IN (SELECT id FROM note))
+            RETURNING tag_id",
+        )?;
+        let related_links = returning_count(
+            "DELETE FROM related_link
+            WHERE primary_link_id NOT IN (SELECT id FROM link)
+            OR related_link_id NOT IN (SELECT id FROM link)
+            RETURNING primary_link_id",
+        )?;
+        let link_content = tx.execute(
+            "DELETE FROM link_content WHERE link_id NOT IN (SELECT id FROM link)",
+            [],
+        )?;
+        let note_content = tx.execute(
+            "DELETE FROM note_content WHERE note_id NOT IN (SELECT id FROM note)",
+            [],
+        )?;
+        let mut steps = vec![
+            ("Orphaned tag assignments", item_tags),
+            ("Orphaned related links", related_links),
+            ("Orphaned link content", link_content),
+            ("Orphaned note content", note_content),
+        ];
+        if unused_tags {
+            let tags = returning_count(
+                "DELETE FROM tag
+                WHERE id NOT IN (SELECT DISTINCT tag_id FROM item_tag)
+                AND id NOT IN (SELECT parent_id FROM tag WHERE parent_id IS NOT NULL)
+                RETURNING id",
+            )?;
+            steps.push(("Unused tags", tags));
+        }
+        Ok(steps)
+    }
+
+    pub struct ContentStats {
+        pub with_content: usize,
+        pub without_content: usize,
+        // In characters, not bytes.
+        pub total_chars: usize,
+        pub min_chars: Option<usize>,
+        pub max_chars: Option<usize>,
+        // The ten longest, as URL and character count.
+        pub longest: Vec<(String, usize)>,
+    }
+
+    pub struct ReadingStats {
+        pub links: usize,
+        pub reading_list: usize,
+        pub read: usize,
+        pub unread: usize,
+        pub refreshed: usize,
+        // Average time from saving a link to reading it, in seconds.
+        pub average_secs_to_read: Option<f64>,
+    }
+
+    pub fn reading_stats(tx: &Transaction) -> Result<ReadingStats> {
+        Ok(tx.query_row(
+            "SELECT COUNT(*),
+            COUNT(*) FILTER (WHERE is_reading_list IS TRUE),
+            COUNT(read_at),
+            COUNT(*) FILTER (WHERE read_at IS NULL),
+            COUNT(refreshed_at),
+            AVG(unixepoch(read_at) - unixepoch(created_at))
+            FROM link WHERE is_primary IS TRUE AND archived_at IS NULL",
+            [],
+            |row| {
+                Ok(ReadingStats {
+                    links: row.get(0)?,
+                    reading_list: row.get(1)?,
+                    read: row.get(2)?,
+                    unread: row.get(3)?,
+                    refreshed: row.get(4)?,
+                    average_secs_to_read: row.get(5)?,
+                })
+            },
+        )?)
+    }
+
+    // Only primary links are counted; related links never have content.
+    pub fn content_stats(tx: &Transaction) -> Result<ContentStats> {
+        let stored = "FROM link JOIN link_content ON link_content.link_id = link.id
+            WHERE link.is_primary IS TRUE AND link.archived_at IS NULL
+            AND link_content.content <> ''";
+        let (with_content, total_chars, min_chars, max_chars) = tx.query_row(
+            &format!(
+                "SELECT COUNT(DISTINCT link.id), COALESCE(SUM(length(content)), 0),
+                MIN(length(content)), MAX(length(content)) {stored}"
+            ),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        let without_content = tx.query_row(
+            "SELECT COUNT(*) FROM link WHERE is_primary IS TRUE AND archived_at IS NULL
+            AND id NOT IN (SELECT link_id FROM link_content WHERE content <> '')",
+            [],
+            |row| row.get(0),
+        )?;
+        let mut stmt = tx.prepare(&format!(
+            "SELECT link.url, length(content) {stored}
+            ORDER BY length(content) DESC LIMIT 10"
+        ))?;
+        let longest = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(ContentStats {
+            with_content,
+            without_content,
+            total_chars,
+            min_chars,
+            max_chars,
+            longest,
+        })
+    }
+
+    // Registers `url_host(url)`, which returns the host of a URL (or NULL if
+    // it has none), for filtering links by domain.
+    pub fn create_url_host_function(conn: &Connection) -> Result<()> {
+        conn.create_scalar_function(
+            "url_host",
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let url = ctx.get::<String>(0)?;
+                Ok(url::Url::parse(&url)
+                    .ok()
+                    .and_then(|u| u.host_str().map(|h| h.to_string())))
+            },
+        )?;
+        Ok(())
+    }
+
+    // Reads a row selected with LINK_COLUMNS.
+    fn link_from_row(row: &rusqlite::Row) -> Result<super::Link> {
+        let optional_timestamp = |idx: usize| -> Result<Option<Timestamp>> {
+            Ok(row
+                .get::<_, Option<String>>(idx)?
+                .map(|t| t.parse())
+                .transpose()?)
         };
-        let order = "ORDER BY created_at DESC";
-        let query = format!(
-            "{} {} {} {} {}",
-            select, where_clause, tag_filter, search_filter, order
-        );
-        let mut stmt = tx.prepare(query.as_ref())?;
-        let mut all_params = tags;
-        if let Some(term) = search_term {
-            all_params.push(term.to_string());
-        }
-        let query_params = params_from_iter(all_params.iter());
-        let mut rows = stmt.query(query_params)?;
-        let mut resp: Vec<super::Link> = vec![];
-        while let Some(row) = rows.next()? {
-            resp.push(super::Link {
-                id: row.get(0)?,
-                url: row.get(1)?,
-                title: Some(row.get::<_, String>(2)?),
-                description: Some(row.get::<_, String>(3)?),
-                // In the context of a bulk get, we don't need to fetch the
-                // content value at this time.
-                content: None,
-                is_primary: row.get(4)?,
-                created_at: row.get::<_, String>(5)?.parse()?,
-                modified_at: row.get::<_, String>(6)?.parse()?,
-            })
-        }
-        Ok(resp)
+        Ok(super::Link {
+            id: row.get(0)?,
+            url: row.get(1)?,
+            title: row.get::<_, Option<String>>(2)?,
+            description: row.get::<_, Option<String>>(3)?,
+            // Content lives in the full-text index, and is fetched separately
+            // only where it's needed.
+            content: None,
+            is_primary: row.get(4)?,
+            created_at: row.get::<_, String>(5)?.parse()?,
+            modified_at: row.get::<_, String>(6)?.parse()?,
+            archived_at: optional_timestamp(7)?,
+            read_at: optional_timestamp(8)?,
+            is_favorite: row.get(9)?,
+            content_sha256: row.get(10)?,
+            pinned_order: row.get(11)?,
+            meta: row
+                .get::<_, Option<String>>(12)?
+                .map(|m| serde_json::from_str(&m))
+                .transpose()
+                .map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(12, Type::Text, Box::new(e))
+                })?,
+            source: row.get(13)?,
+            is_reading_list: row.get(14)?,
+            local_archive_path: row.get(15)?,
+        })
     }
 
     pub fn get_link(
@@ -956,53 +7896,55 @@ mod db {
         identifier: TermOrId,
         is_primary: IsPrimary,
     ) -> Result<Option<super::Link>> {
-        let insert = "SELECT
-            id, url, title, description, is_primary, created_at, modified_at
-            FROM link
-            ";
+        let insert = format!("SELECT {LINK_COLUMNS} FROM link");
         let where_clause = match is_primary {
             IsPrimary::PrimaryOnly => "WHERE is_primary IS TRUE",
             IsPrimary::SecondaryOnly => "WHERE is_primary IS FALSE",
             _ => "WHERE 1 = 1",
         };
+        // URLs are stored in their normalized form, but older entries may not
+        // be, so we'll match on either the term as given or its normalization.
         let id_filter = match identifier {
-            TermOrId::Term(_) => "AND url = ?",
-            TermOrId::Id(_) => "AND id = ?",
+            TermOrId::Term(_) => "AND url IN (?1, ?2)",
+            TermOrId::Id(_) => "AND id = ?1",
         };
         let query = format!("{} {} {}", insert, where_clause, id_filter);
         let mut stmt = tx.prepare(query.as_ref())?;
-        let mut rows = stmt.query([identifier])?;
+        let mut rows = match identifier {
+            TermOrId::Term(term) => {
+                let normalized = url::Url::parse(term)
+                    .map(String::from)
+                    .unwrap_or(term.to_string());
+                stmt.query([term, normalized.as_str()])?
+            }
+            TermOrId::Id(_) => stmt.query([identifier])?,
+        };
         if let Some(row) = rows.next()? {
-            let mut link = super::Link {
-                id: row.get(0)?,
-                url: row.get(1)?,
-                title: row.get::<_, Option<String>>(2)?,
-                description: row.get::<_, Option<String>>(3)?,
-                content: None,
-                is_primary: row.get(4)?,
-                created_at: row.get::<_, String>(5)?.parse()?,
-                modified_at: row.get::<_, String>(6)?.parse()?,
-            };
-            let mut stmt =
-                tx.prepare("SELECT content FROM link_content WHERE link_id = ?".as_ref())?;
-            let mut content_rows = stmt.query([link.id.to_string()])?;
-            if let Some(row) = content_rows.next()? {
-                link.content = row.get(0)?;
-            };
+            let mut link = link_from_row(row)?;
+            link.content = get_link_content(tx, &link.id)?;
             Ok(Some(link))
         } else {
             Ok(None)
         }
     }
 
+    pub fn get_link_content(tx: &Transaction, link_id: &TableId) -> Result<Option<String>> {
+        let mut stmt = tx.prepare("SELECT content FROM link_content WHERE link_id = ?")?;
+        let mut rows = stmt.query([link_id])?;
+        match rows.next()? {
+            Some(row) => Ok(row.get(0)?),
+            None => Ok(None),
+        }
+    }
+
     pub fn delete_link(tx: &Transaction, link_id: &TableId) -> Result<()> {
         // If the link is a primary link but also serves as a related link,
         // we want to make it is_primary FALSE and also drop related links,
         // associated notes, and tags; in the normal course of things, however,
         // our foreign key cascades will clean them up.
         //
-        // A possible improvement would be to check here and remove orphaned
-        // tags.
+        // Orphaned tags are left behind; `tags orphans --delete` clears
+        // them out.
         let delete_query = "DELETE FROM link WHERE id = ? AND is_primary = true";
         tx.execute(delete_query, [&link_id])?;
         Ok(())
@@ -1029,6 +7971,9 @@ mod db {
         pub content: Option<&'a str>,
         pub is_primary: bool,
         pub timestamp: &'a str,
+        // Defaults to timestamp; set when importing links saved elsewhere.
+        pub created_at: Option<&'a str>,
+        pub source: Option<&'a str>,
     }
 
     pub fn insert_link(
@@ -1036,19 +7981,31 @@ mod db {
         link: &LinkInsert,
         ignore_conflict: bool,
     ) -> Result<TableId> {
-        let id = get_uuid();
+        insert_link_as(tx, get_uuid(), link, ignore_conflict)
+    }
+
+    // Like insert_link, but with a given ID, e.g. to put back a removed link
+    // under the ID that the operation log knows it by.
+    pub fn insert_link_as(
+        tx: &Transaction,
+        id: TableId,
+        link: &LinkInsert,
+        ignore_conflict: bool,
+    ) -> Result<TableId> {
         let values = named_params! {
             ":id": id,
             ":url": link.url,
             ":title": link.title,
             ":description": link.description,
             ":is_primary": link.is_primary,
-            ":created_at": link.timestamp,
+            ":created_at": link.created_at.unwrap_or(link.timestamp),
             ":modified_at": link.timestamp,
+            ":source": link.source,
         };
         let insert = "INSERT INTO link
-            (id, url, title, description, is_primary, created_at, modified_at)
-            VALUES(:id, :url, :title, :description, :is_primary, :created_at, :modified_at)
+            (id, url, title, description, is_primary, created_at, modified_at, source)
+            VALUES(:id, :url, :title, :description, :is_primary, :created_at, :modified_at,
+                :source)
             ";
         // We can't simply "DO NOTHING", because that terminates the query
         // and we don't return an id; instead we'll update something that
@@ -1063,11 +8020,21 @@ mod db {
         let returning = "RETURNING id";
         let query = format!("{} {} {}", insert, conflict, returning);
         let mut stmt = tx.prepare(query.as_ref())?;
-        let mut rows = stmt.query(values)?;
-        let row_result = if let Some(row) = rows.next()? {
+        // The URL is the only unique column; a clash of IDs, say, is
+        // reported as the database error it is.
+        let duplicate = |e: rusqlite::Error| match e {
+            rusqlite::Error::SqliteFailure(ref failure, _)
+                if failure.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE =>
+            {
+                MeowpadError::DuplicateUrl(link.url.to_string())
+            }
+            _ => e.into(),
+        };
+        let mut rows = stmt.query(values).map_err(duplicate)?;
+        let row_result = if let Some(row) = rows.next().map_err(duplicate)? {
             Ok(row.get(0)?)
         } else {
-            Err(anyhow!("Unable to insert link `{}`", link.url))
+            Err(rusqlite::Error::QueryReturnedNoRows.into())
         };
         // Now, insert the content into the full-text index.
         if let Ok(row_id) = row_result {
@@ -1099,6 +8066,166 @@ mod db {
         get_link(tx, TermOrId::Id(link.id), IsPrimary::Either)
     }
 
+    pub fn mark_refreshed(tx: &Transaction, link_id: &TableId, timestamp: &str) -> Result<()> {
+        tx.execute(
+            "UPDATE link SET refreshed_at = ?1 WHERE id = ?2",
+            params![timestamp, link_id],
+        )?;
+        Ok(())
+    }
+
+    // Archiving only sets archived_at: the link stays primary and keeps its
+    // content, tags and relations, and queries leave it out by filtering on
+    // archived_at IS NULL.
+    pub fn archive_link(tx: &Transaction, link_id: &TableId, timestamp: &str) -> Result<()> {
+        tx.execute(
+            "UPDATE link SET archived_at = ?1 WHERE id = ?2",
+            params![timestamp, link_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_link_favorite(tx: &Transaction, link_id: &TableId, is_favorite: bool) -> Result<()> {
+        tx.execute(
+            "UPDATE link SET is_favorite = ?1 WHERE id = ?2",
+            params![is_favorite, link_id],
+        )?;
+        Ok(())
+    }
+
+    // Pins the link at `position`, or after every pinned link if none is
+    // given; returns the position used.
+    pub fn pin_link(tx: &Transaction, link_id: &TableId, position: Option<i64>) -> Result<i64> {
+        let position = match position {
+            Some(position) => position,
+            None => tx.query_row(
+                "SELECT COALESCE(MAX(pinned_order), 0) + 1 FROM link",
+                [],
+                |row| row.get(0),
+            )?,
+        };
+        tx.execute(
+            "UPDATE link SET pinned_order = ?1 WHERE id = ?2",
+            params![position, link_id],
+        )?;
+        Ok(position)
+    }
+
+    pub fn set_link_meta(
+        tx: &Transaction,
+        link_id: &TableId,
+        meta: &serde_json::Value,
+    ) -> Result<()> {
+        tx.execute(
+            "UPDATE link SET meta = ?1 WHERE id = ?2",
+            params![meta.to_string(), link_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn unpin_link(tx: &Transaction, link_id: &TableId) -> Result<()> {
+        tx.execute(
+            "UPDATE link SET pinned_order = NULL WHERE id = ?",
+            [link_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_link_local_archive_path(
+        tx: &Transaction,
+        link_id: &TableId,
+        path: Option<&str>,
+    ) -> Result<()> {
+        tx.execute(
+            "UPDATE link SET local_archive_path = ?1 WHERE id = ?2",
+            params![path, link_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_link_reading_list(
+        tx: &Transaction,
+        link_id: &TableId,
+        is_reading_list: bool,
+    ) -> Result<()> {
+        tx.execute(
+            "UPDATE link SET is_reading_list = ?1 WHERE id = ?2",
+            params![is_reading_list, link_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_link_read_at(
+        tx: &Transaction,
+        link_id: &TableId,
+        timestamp: Option<&str>,
+    ) -> Result<()> {
+        tx.execute(
+            "UPDATE link SET read_at = ?1 WHERE id = ?2",
+            params![timestamp, link_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn restore_link(tx: &Transaction, link_id: &TableId) -> Result<()> {
+        tx.execute("UPDATE link SET archived_at = NULL WHERE id = ?", [link_id])?;
+        Ok(())
+    }
+
+    // Moves tags, relations, and content from one link to another, then deletes
+    // the first link. Notes are left to the caller, and must be moved first or
+    // they will cascade with the deleted link.
+    pub fn merge_links(tx: &Transaction, from_id: &TableId, into_id: &TableId) -> Result<()> {
+        // item_tag's uniqueness constraint doesn't apply when note_id is NULL,
+        // so drop tags the target already has before moving the rest.
+        tx.execute(
+            "DELETE FROM item_tag
+            WHERE link_id = ?1
+            AND tag_id IN (SELECT tag_id FROM item_tag WHERE link_id = ?2)",
+            [from_id, into_id],
+        )?;
+        tx.execute(
+            "UPDATE item_tag SET link_id = ?2 WHERE link_id = ?1",
+            [from_id, into_id],
+        )?;
+        tx.execute(
+            "UPDATE OR IGNORE related_link SET primary_link_id = ?2
+            WHERE primary_link_id = ?1",
+            [from_id, into_id],
+        )?;
+        tx.execute(
+            "UPDATE OR IGNORE related_link SET related_link_id = ?2
+            WHERE related_link_id = ?1",
+            [from_id, into_id],
+        )?;
+        tx.execute(
+            "DELETE FROM related_link
+            WHERE primary_link_id = ?1
+            OR related_link_id = ?1
+            OR primary_link_id = related_link_id",
+            [from_id],
+        )?;
+        // Keep the target's content if it has any; otherwise adopt ours.
+        tx.execute(
+            "UPDATE link SET content_sha256 = (SELECT content_sha256 FROM link WHERE id = ?1)
+            WHERE id = ?2
+            AND NOT EXISTS (SELECT 1 FROM link_content WHERE link_id = ?2)",
+            [from_id, into_id],
+        )?;
+        tx.execute(
+            "DELETE FROM link_content
+            WHERE link_id = ?1
+            AND EXISTS (SELECT 1 FROM link_content WHERE link_id = ?2)",
+            [from_id, into_id],
+        )?;
+        tx.execute(
+            "UPDATE link_content SET link_id = ?2 WHERE link_id = ?1",
+            [from_id, into_id],
+        )?;
+        tx.execute("DELETE FROM link WHERE id = ?", [from_id])?;
+        Ok(())
+    }
+
     pub fn tag_link(tx: &Transaction, link_id: TableId, tag_id: TableId) -> Result<()> {
         let query = "INSERT INTO item_tag (link_id, tag_id)
         VALUES (?1, ?2)
@@ -1115,16 +8242,28 @@ mod db {
         Ok(())
     }
 
+    pub fn untag_link(tx: &Transaction, link_id: TableId, tag_id: TableId) -> Result<()> {
+        let query = "DELETE FROM item_tag WHERE link_id = ?1 AND tag_id = ?2";
+        tx.execute(query, [&link_id, &tag_id])?;
+        Ok(())
+    }
+
+    pub fn untag_note(tx: &Transaction, note_id: TableId, tag_id: TableId) -> Result<()> {
+        let query = "DELETE FROM item_tag WHERE note_id = ?1 AND tag_id = ?2";
+        tx.execute(query, [&note_id, &tag_id])?;
+        Ok(())
+    }
+
     pub fn relate_links(
         tx: &Transaction,
         primary_id: TableId,
         secondary_id: TableId,
-        relationship: Option<&str>,
+        relationship: Option<super::RelationshipType>,
     ) -> Result<()> {
         let related_values = named_params! {
             ":primary_id": primary_id,
             ":secondary_id": secondary_id,
-            ":relationship": relationship,
+            ":relationship": relationship.map(|r| r.as_str()),
         };
         tx.execute(
             "INSERT INTO related_link
@@ -1154,9 +8293,163 @@ mod db {
         Ok(resp)
     }
 
+    // Like related_links, but with the full link for each.
+    pub fn get_related_links_full(
+        tx: &Transaction,
+        primary_id: &TableId,
+    ) -> Result<Vec<(super::Link, Option<String>)>> {
+        // The link's columns come first, so that link_from_row can read them.
+        let query = format!(
+            "SELECT link.*, related_link.relationship
+            FROM (SELECT {LINK_COLUMNS} FROM link) AS link JOIN related_link
+            ON link.id = related_link.related_link_id
+            WHERE related_link.primary_link_id = ?"
+        );
+        let mut stmt = tx.prepare(&query)?;
+        let mut rows = stmt.query([&primary_id])?;
+        let relation_index = LINK_COLUMNS.split(',').count();
+        let mut resp = vec![];
+        while let Some(row) = rows.next()? {
+            resp.push((link_from_row(row)?, row.get(relation_index)?));
+        }
+        Ok(resp)
+    }
+
+    pub struct RelatedLinkEdge {
+        pub primary_id: TableId,
+        pub id: TableId,
+        pub url: String,
+        pub relation: Option<String>,
+        // 1 for the starting link's own related links.
+        pub depth: usize,
+    }
+
+    // Relationships reachable from a link, following related links that are
+    // themselves primary links, up to max_depth steps away.
+    pub fn related_links_recursive(
+        tx: &Transaction,
+        link_id: &TableId,
+        max_depth: usize,
+    ) -> Result<Vec<RelatedLinkEdge>> {
+        let query = "WITH RECURSIVE tree(primary_id, related_id, relationship, depth) AS (
+                SELECT primary_link_id, related_link_id, relationship, 1
+                FROM related_link WHERE primary_link_id = ?1
+                UNION
+                SELECT related_link.primary_link_id, related_link.related_link_id,
+                related_link.relationship, tree.depth + 1
+                FROM related_link JOIN tree ON related_link.primary_link_id = tree.related_id
+                WHERE tree.depth < ?2
+            )
+            SELECT tree.primary_id, tree.related_id, link.url, tree.relationship, tree.depth
+            FROM tree JOIN link ON link.id = tree.related_id
+            ORDER BY tree.depth, link.url";
+        let mut stmt = tx.prepare(query)?;
+        let mut rows = stmt.query(params![link_id, max_depth])?;
+        let mut edges = vec![];
+        while let Some(row) = rows.next()? {
+            edges.push(RelatedLinkEdge {
+                primary_id: row.get(0)?,
+                id: row.get(1)?,
+                url: row.get(2)?,
+                relation: row.get(3)?,
+                depth: row.get(4)?,
+            });
+        }
+        Ok(edges)
+    }
+
+    // Every relationship, or only those of one primary link, ordered by
+    // primary link and then related link.
+    pub fn get_all_related_links(
+        tx: &Transaction,
+        primary_id: Option<&TableId>,
+    ) -> Result<Vec<super::RelatedLink>> {
+        let query = format!(
+            "SELECT related.url, related_link.relationship, primary_link.url
+            FROM related_link
+            JOIN link AS related ON related.id = related_link.related_link_id
+            JOIN link AS primary_link ON primary_link.id = related_link.primary_link_id
+            {}
+            ORDER BY primary_link.url, related.url",
+            if primary_id.is_some() {
+                "WHERE related_link.primary_link_id = ?"
+            } else {
+                ""
+            }
+        );
+        let mut stmt = tx.prepare(&query)?;
+        let mut rows = stmt.query(params_from_iter(primary_id))?;
+        let mut resp: Vec<super::RelatedLink> = vec![];
+        while let Some(row) = rows.next()? {
+            resp.push(super::RelatedLink {
+                url: row.get(0)?,
+                relation: row.get(1)?,
+                primary_url: row.get(2)?,
+            });
+        }
+        Ok(resp)
+    }
+
+    // The links that name this one as a related link, with their relationship.
+    pub fn inverse_related_links(
+        tx: &Transaction,
+        related_id: &TableId,
+    ) -> Result<Vec<(String, Option<String>)>> {
+        let query = "SELECT
+            url, related_link.relationship
+            FROM link JOIN related_link
+            ON link.id = related_link.primary_link_id
+            WHERE related_link.related_link_id = ?
+            ";
+        let mut stmt = tx.prepare(query)?;
+        let mut rows = stmt.query([&related_id])?;
+        let mut resp: Vec<(String, Option<String>)> = vec![];
+        while let Some(row) = rows.next()? {
+            resp.push((row.get(0)?, row.get(1)?));
+        }
+        Ok(resp)
+    }
+
+    pub fn get_related_links_by_relationship(
+        tx: &Transaction,
+        primary_id: &TableId,
+        rel_type: super::RelationshipType,
+    ) -> Result<Vec<String>> {
+        let query = "SELECT url
+            FROM link JOIN related_link
+            ON link.id = related_link.related_link_id
+            WHERE related_link.primary_link_id = ?1
+            AND related_link.relationship = ?2
+            ";
+        let mut stmt = tx.prepare(query)?;
+        let rows = stmt.query_map(params![primary_id, rel_type.as_str()], |row| row.get(0))?;
+        Ok(rows.collect::<Result<Vec<String>, _>>()?)
+    }
+
+    pub fn get_inverse_related_links_by_relationship(
+        tx: &Transaction,
+        related_id: &TableId,
+        rel_type: super::RelationshipType,
+    ) -> Result<Vec<String>> {
+        let query = "SELECT url
+            FROM link JOIN related_link
+            ON link.id = related_link.primary_link_id
+            WHERE related_link.related_link_id = ?1
+            AND related_link.relationship = ?2
+            ";
+        let mut stmt = tx.prepare(query)?;
+        let rows = stmt.query_map(params![related_id, rel_type.as_str()], |row| row.get(0))?;
+        Ok(rows.collect::<Result<Vec<String>, _>>()?)
+    }
+
+    // Archived links, and links with a note, are kept even when nothing
+    // relates to them any more.
     pub fn delete_orphaned_related_link(tx: &Transaction, related_link_id: &TableId) -> Result<()> {
         let query = "DELETE FROM link
             WHERE id = ?
+            AND is_primary IS FALSE
+            AND archived_at IS NULL
+            AND NOT EXISTS (SELECT 1 FROM note WHERE note.link_id = link.id)
             AND (
                 SELECT COUNT(1) FROM related_link
                 WHERE related_link_id = ?
@@ -1171,7 +8464,9 @@ mod db {
         related_link_id: Option<&TableId>,
     ) -> Result<()> {
         if primary_link_id.is_none() && related_link_id.is_none() {
-            Err(anyhow!("Primary or related link ID required"))
+            Err(MeowpadError::InvalidArgument(
+                "Primary or related link ID required".to_string(),
+            ))
         } else {
             let query_base = "DELETE FROM related_link
                 WHERE";
@@ -1192,52 +8487,192 @@ mod db {
         }
     }
 
-    pub fn insert_content(tx: &Transaction, link_id: &TableId, content: &str) -> Result<()> {
-        let ft_query = "INSERT INTO link_content(link_id, content)
-            VALUES (:id, :content)";
-        let mut ft_stmt = tx.prepare(ft_query)?;
-        let ft_values = named_params! {
-            ":id": link_id,
-            ":content": content,
-        };
-        ft_stmt.execute(ft_values)?;
+    pub fn insert_content(tx: &Transaction, link_id: &TableId, content: &str) -> Result<()> {
+        let ft_query = "INSERT INTO link_content(link_id, content)
+            VALUES (:id, :content)";
+        let mut ft_stmt = tx.prepare(ft_query)?;
+        let ft_values = named_params! {
+            ":id": link_id,
+            ":content": content,
+        };
+        ft_stmt.execute(ft_values)?;
+        tx.execute(
+            "UPDATE link SET content_sha256 = ?1 WHERE id = ?2",
+            params![super::util::sha256(content), link_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_content(tx: &Transaction, link_id: &TableId) -> Result<()> {
+        let ft_query = "DELETE FROM link_content
+            WHERE link_id = :id";
+        let mut ft_stmt = tx.prepare(ft_query)?;
+        let ft_values = named_params! {
+            ":id": link_id,
+        };
+        ft_stmt.execute(ft_values)?;
+        tx.execute(
+            "UPDATE link SET content_sha256 = NULL WHERE id = ?",
+            [link_id],
+        )?;
+        Ok(())
+    }
+
+    // TAGS
+    const TAG_COLUMNS: &str = "id, slug, name, created_at, modified_at, description, parent_id";
+
+    fn tag_from_row(row: &rusqlite::Row) -> Result<super::Tag> {
+        Ok(super::Tag {
+            id: row.get(0)?,
+            slug: row.get(1)?,
+            name: row.get(2)?,
+            created_at: row.get::<_, String>(3)?.parse()?,
+            modified_at: row.get::<_, String>(4)?.parse()?,
+            description: row.get(5)?,
+            parent_id: row.get(6)?,
+        })
+    }
+
+    pub fn get_tags(tx: &Transaction) -> Result<Vec<super::Tag>> {
+        let query = format!(
+            "SELECT {TAG_COLUMNS}
+            FROM tag
+            ORDER BY slug"
+        );
+        let mut stmt = tx.prepare(&query)?;
+        let mut rows = stmt.query([])?;
+        let mut tags: Vec<super::Tag> = vec![];
+        while let Some(row) = rows.next()? {
+            tags.push(tag_from_row(row)?);
+        }
+        Ok(tags)
+    }
+
+    // Tags that label nothing. Parents of other tags are kept, since they
+    // still hold the hierarchy together.
+    pub fn get_orphan_tags(tx: &Transaction) -> Result<Vec<super::Tag>> {
+        let query = format!(
+            "SELECT {TAG_COLUMNS}
+            FROM tag
+            WHERE id NOT IN (SELECT DISTINCT tag_id FROM item_tag)
+            AND id NOT IN (SELECT parent_id FROM tag WHERE parent_id IS NOT NULL)
+            ORDER BY slug"
+        );
+        let mut stmt = tx.prepare(&query)?;
+        let mut rows = stmt.query([])?;
+        let mut tags: Vec<super::Tag> = vec![];
+        while let Some(row) = rows.next()? {
+            tags.push(tag_from_row(row)?);
+        }
+        Ok(tags)
+    }
+
+    pub fn delete_tag(tx: &Transaction, tag_id: &TableId) -> Result<()> {
+        tx.execute("DELETE FROM tag WHERE id = ?", [tag_id])?;
         Ok(())
     }
 
-    pub fn delete_content(tx: &Transaction, link_id: &TableId) -> Result<()> {
-        let ft_query = "DELETE FROM link_content
-            WHERE link_id = :id";
-        let mut ft_stmt = tx.prepare(ft_query)?;
-        let ft_values = named_params! {
-            ":id": link_id,
-        };
-        ft_stmt.execute(ft_values)?;
+    pub fn get_tag_by_slug(tx: &Transaction, slug: &str) -> Result<Option<super::Tag>> {
+        let query = format!("SELECT {TAG_COLUMNS} FROM tag WHERE slug = ?");
+        let mut stmt = tx.prepare(&query)?;
+        let mut rows = stmt.query([slug])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(tag_from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_tag_parent(
+        tx: &Transaction,
+        tag_id: &TableId,
+        parent_id: Option<&TableId>,
+    ) -> Result<()> {
+        tx.execute(
+            "UPDATE tag SET parent_id = ?1, modified_at = ?2 WHERE id = ?3",
+            params![parent_id, super::now()?, tag_id],
+        )?;
+        Ok(())
+    }
+
+    // The tag itself, plus its children, their children, and so on.
+    pub fn tag_descendant_ids(tx: &Transaction, tag_id: &TableId) -> Result<Vec<TableId>> {
+        let mut stmt = tx.prepare(
+            "WITH RECURSIVE subtags(id) AS (
+                SELECT ?
+                UNION SELECT tag.id FROM tag JOIN subtags ON tag.parent_id = subtags.id
+            ) SELECT id FROM subtags",
+        )?;
+        let ids = stmt
+            .query_map([tag_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<TableId>>>()?;
+        Ok(ids)
+    }
+
+    pub fn set_tag_description(
+        tx: &Transaction,
+        tag_id: &TableId,
+        description: Option<&str>,
+    ) -> Result<()> {
+        tx.execute(
+            "UPDATE tag SET description = ?1, modified_at = ?2 WHERE id = ?3",
+            params![description, super::now()?, tag_id],
+        )?;
         Ok(())
     }
 
-    // TAGS
     pub fn tags_for_item(tx: &Transaction, item_id: &TableId) -> Result<Vec<super::Tag>> {
-        let query = "SELECT DISTINCT id, slug, name, created_at, modified_at
+        let query = format!(
+            "SELECT DISTINCT {TAG_COLUMNS}
             FROM tag
             WHERE id IN (
                 SELECT tag_id FROM item_tag
                 WHERE note_id = ?1 OR link_id = ?2
-            ) ORDER BY slug";
-        let mut stmt = tx.prepare(query)?;
+            ) ORDER BY slug"
+        );
+        let mut stmt = tx.prepare(&query)?;
         let mut rows = stmt.query([&item_id, &item_id])?;
         let mut tags: Vec<super::Tag> = vec![];
         while let Some(row) = rows.next()? {
-            tags.push(super::Tag {
-                id: row.get(0)?,
-                slug: row.get(1)?,
-                name: row.get(2)?,
-                created_at: row.get::<_, String>(3)?.parse()?,
-                modified_at: row.get::<_, String>(4)?.parse()?,
-            });
+            tags.push(tag_from_row(row)?);
         }
         Ok(tags)
     }
 
+    // A tag with everything tagged with it, as `tags show` serializes it.
+    #[derive(Debug, serde::Serialize)]
+    pub struct TagWithItems {
+        pub tag: super::Tag,
+        pub link_count: usize,
+        pub note_count: usize,
+        pub links: Vec<super::Link>,
+        pub notes: Vec<super::Note>,
+    }
+
+    pub fn get_tag_with_items(tx: &Transaction, slug: &str) -> Result<Option<TagWithItems>> {
+        let Some(tag) = get_tag_by_slug(tx, slug)? else {
+            return Ok(None);
+        };
+        let tags = vec![tag.slug.clone()];
+        let links = get_links(tx, tags.clone(), None, &LinkFilter::default(), false)?;
+        let notes = get_notes(tx, tags, None, None)?;
+        Ok(Some(TagWithItems {
+            tag,
+            link_count: links.len(),
+            note_count: notes.len(),
+            links,
+            notes,
+        }))
+    }
+
+    pub fn get_tag_id_by_slug(tx: &Transaction, slug: &str) -> Result<Option<TableId>> {
+        let mut stmt = tx.prepare("SELECT id FROM tag WHERE slug = ?")?;
+        let mut rows = stmt.query([slug])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
     pub fn require_tag(
         tx: &Transaction,
         name: &str,
@@ -1262,7 +8697,7 @@ mod db {
         if let Some(row) = rows.next()? {
             Ok(row.get(0)?)
         } else {
-            Err(anyhow!("Unable to insert or load tag `{}`", slug))
+            Err(rusqlite::Error::QueryReturnedNoRows.into())
         }
     }
 
@@ -1273,15 +8708,6 @@ mod db {
         Ok(())
     }
 
-    pub fn delete_item_tag(tx: &Transaction, item_id: &TableId, tag_id: &TableId) -> Result<()> {
-        let query = "DELETE FROM item_tag
-            WHERE (note_id = ?1 OR link_id = ?2)
-            AND tag_id = ?3";
-        let mut stmt = tx.prepare(query)?;
-        stmt.execute([item_id, item_id, tag_id])?;
-        Ok(())
-    }
-
     // NOTES
     pub fn upsert_note(
         tx: &Transaction,
@@ -1289,6 +8715,7 @@ mod db {
         title: &str,
         link_id: Option<&TableId>,
         timestamp: &str,
+        created_at: Option<&str>,
     ) -> Result<TableId> {
         let id = get_uuid();
         let values = named_params! {
@@ -1296,7 +8723,7 @@ mod db {
             ":content": note,
             ":title": title,
             ":link_id": link_id,
-            ":created_at": timestamp,
+            ":created_at": created_at.unwrap_or(timestamp),
             ":modified_at": timestamp,
         };
         // We can't simply "DO NOTHING" for the reasons given in insert_link().
@@ -1309,11 +8736,43 @@ mod db {
             RETURNING id";
         let mut stmt = tx.prepare(query)?;
         let mut rows = stmt.query(values)?;
-        if let Some(row) = rows.next()? {
-            Ok(row.get(0)?)
+        let id: TableId = if let Some(row) = rows.next()? {
+            row.get(0)?
         } else {
-            Err(anyhow!("Unable to insert note `{}`", title))
-        }
+            return Err(rusqlite::Error::QueryReturnedNoRows.into());
+        };
+        index_note_content(tx, &id, note)?;
+        Ok(id)
+    }
+
+    // Like link_content, note_content has no unique constraint on note_id,
+    // so we replace rather than upsert.
+    fn index_note_content(tx: &Transaction, note_id: &TableId, content: &str) -> Result<()> {
+        tx.execute("DELETE FROM note_content WHERE note_id = ?", [note_id])?;
+        tx.execute(
+            "INSERT INTO note_content(note_id, content) VALUES (?1, ?2)",
+            params![note_id, content],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_note(tx: &Transaction, note: &super::Note) -> Result<()> {
+        let values = named_params! {
+            ":id": note.id,
+            ":content": note.content,
+            ":title": note.title,
+            ":link_id": note.link_id,
+            ":modified_at": super::now()?,
+        };
+        let query = "UPDATE note SET
+            content = :content,
+            title = :title,
+            link_id = :link_id,
+            modified_at = :modified_at
+            WHERE id = :id";
+        tx.execute(query, values)?;
+        index_note_content(tx, &note.id, &note.content)?;
+        Ok(())
     }
 
     pub fn get_note_by_title(tx: &Transaction, title: &str) -> Result<Option<super::Note>> {
@@ -1324,6 +8783,10 @@ mod db {
         get_note(tx, None, Some(link_id), None)
     }
 
+    pub fn get_note_by_id(tx: &Transaction, id: &TableId) -> Result<Option<super::Note>> {
+        get_note(tx, Some(id), None, None)
+    }
+
     fn get_note(
         tx: &Transaction,
         id: Option<&TableId>,
@@ -1345,7 +8808,9 @@ mod db {
             values.push(Box::new(title.to_string()));
         }
         if filters.is_empty() {
-            Err(anyhow!("No filter provided for get_note"))
+            Err(MeowpadError::InvalidArgument(
+                "No filter provided for get_note".to_string(),
+            ))
         } else {
             let filter = filters.join(" AND ");
             let query = format!(
@@ -1357,43 +8822,685 @@ mod db {
             let mut rows = stmt.query(params_from_iter(values.iter()))?;
             let row0 = rows.next()?;
             if let Some(row) = row0 {
-                let created_at: String = row.get(4)?;
-                let modified_at: String = row.get(5)?;
-                Ok(Some(super::Note {
-                    id: row.get(0)?,
-                    content: row.get(1)?,
-                    title: row.get(2)?,
-                    link_id: row.get(3)?,
-                    created_at: created_at.parse()?,
-                    modified_at: modified_at.parse()?,
-                }))
+                Ok(Some(note_from_row(row)?))
             } else {
                 Ok(None)
             }
         }
     }
 
-    pub fn delete_note(tx: &Transaction, note_id: &TableId) -> Result<()> {
-        // Our foreign key cascades will clean up tags -- a possible improvement
-        // would be to remove orphaned tags after this is applied.
-        let delete_query = "DELETE FROM note WHERE id = ?";
-        tx.execute(delete_query, [&note_id])?;
-        Ok(())
+    // `link_association` limits the notes to those attached to a link (true)
+    // or standalone ones (false).
+    pub fn get_notes(
+        tx: &Transaction,
+        tags: Vec<String>,
+        search_term: Option<&str>,
+        link_association: Option<bool>,
+    ) -> Result<Vec<super::Note>> {
+        let select = "SELECT id, content, title, link_id, created_at, modified_at
+            FROM note
+            WHERE 1 = 1";
+        let tag_filter = note_tag_filter("id", &tags);
+        let search_filter = if search_term.is_some() {
+            "AND (title LIKE '%' || ? || '%' OR content LIKE '%' || ? || '%')".to_string()
+        } else {
+            "".to_string()
+        };
+        let link_filter = match link_association {
+            Some(true) => "AND link_id IS NOT NULL",
+            Some(false) => "AND link_id IS NULL",
+            None => "",
+        };
+        let order = "ORDER BY created_at DESC";
+        let query = format!(
+            "{} {} {} {} {}",
+            select, tag_filter, search_filter, link_filter, order
+        );
+        let mut stmt = tx.prepare(&query)?;
+        let mut all_params = tags;
+        if let Some(term) = search_term {
+            // Once for the title, once for the content.
+            all_params.push(term.to_string());
+            all_params.push(term.to_string());
+        }
+        let mut rows = stmt.query(params_from_iter(all_params.iter()))?;
+        let mut resp: Vec<super::Note> = vec![];
+        while let Some(row) = rows.next()? {
+            resp.push(note_from_row(row)?);
+        }
+        Ok(resp)
+    }
+
+    // Notes attached to a link, with the link read in the same query.
+    pub fn get_notes_with_links(
+        tx: &Transaction,
+        tags: Vec<String>,
+    ) -> Result<Vec<(super::Note, super::Link)>> {
+        // The link's columns come first, so that link_from_row can read them.
+        let query = format!(
+            "SELECT link.*, note.id, note.content, note.title, note.link_id,
+                note.created_at, note.modified_at
+            FROM note JOIN (SELECT {LINK_COLUMNS} FROM link) AS link
+            ON link.id = note.link_id
+            WHERE 1 = 1 {}
+            ORDER BY note.created_at DESC",
+            note_tag_filter("note.id", &tags)
+        );
+        let mut stmt = tx.prepare(&query)?;
+        let mut rows = stmt.query(params_from_iter(tags.iter()))?;
+        let note_start = LINK_COLUMNS.split(',').count();
+        let mut resp = vec![];
+        while let Some(row) = rows.next()? {
+            resp.push((note_from_row_at(row, note_start)?, link_from_row(row)?));
+        }
+        Ok(resp)
+    }
+
+    fn note_tag_filter(id_column: &str, tags: &[String]) -> String {
+        if tags.is_empty() {
+            "".to_string()
+        } else {
+            let qmarks: Vec<&str> = tags.iter().map(|_| "?").collect();
+            let joined = qmarks.join(", ");
+            format!(
+                "AND {id_column} in (SELECT note_id FROM item_tag WHERE tag_id in
+            (SELECT id FROM tag WHERE slug IN ({joined})))"
+            )
+        }
+    }
+
+    fn note_from_row(row: &rusqlite::Row) -> Result<super::Note> {
+        note_from_row_at(row, 0)
+    }
+
+    // Reads note columns starting at `start`, for queries that select other
+    // columns first.
+    fn note_from_row_at(row: &rusqlite::Row, start: usize) -> Result<super::Note> {
+        let created_at: String = row.get(start + 4)?;
+        let modified_at: String = row.get(start + 5)?;
+        Ok(super::Note {
+            id: row.get(start)?,
+            content: row.get(start + 1)?,
+            title: row.get(start + 2)?,
+            link_id: row.get(start + 3)?,
+            created_at: created_at.parse()?,
+            modified_at: modified_at.parse()?,
+        })
+    }
+
+    pub fn delete_note(tx: &Transaction, note_id: &TableId) -> Result<()> {
+        // Our foreign key cascades will clean up item tags; the tags themselves
+        // are left for `tags orphans --delete`.
+        let delete_query = "DELETE FROM note WHERE id = ?";
+        tx.execute(delete_query, [&note_id])?;
+        tx.execute("DELETE FROM note_content WHERE note_id = ?", [&note_id])?;
+        Ok(())
+    }
+
+    // `query` is an FTS5 query, as for search_links. Bounds on when a note
+    // was added work as they do in LinkFilter.
+    pub fn search_notes(
+        tx: &Transaction,
+        query: &str,
+        after: Option<Timestamp>,
+        before: Option<Timestamp>,
+    ) -> Result<Vec<super::Note>> {
+        let mut all_params = vec![Value::Text(query.to_string())];
+        let mut date_filter = String::new();
+        if let Some(after) = after {
+            date_filter.push_str("AND created_at >= ? ");
+            all_params.push(Value::Text(after.round(Unit::Second)?.to_string()));
+        }
+        if let Some(before) = before {
+            date_filter.push_str("AND created_at < ? ");
+            all_params.push(Value::Text(before.round(Unit::Second)?.to_string()));
+        }
+        // Notes deleted along with their link leave rows behind in
+        // note_content; selecting from note skips them.
+        let mut stmt = tx.prepare(&format!(
+            "SELECT id, content, title, link_id, created_at, modified_at
+            FROM note
+            WHERE id IN (SELECT note_id FROM note_content WHERE note_content MATCH ?)
+            {date_filter}
+            ORDER BY created_at DESC"
+        ))?;
+        let mut rows = stmt.query(params_from_iter(all_params.iter()))?;
+        let mut resp: Vec<super::Note> = vec![];
+        while let Some(row) = rows.next()? {
+            resp.push(note_from_row(row)?);
+        }
+        Ok(resp)
+    }
+
+    // FETCH CACHE
+    pub fn get_cached_html(
+        tx: &Transaction,
+        url: &str,
+        not_before: &str,
+    ) -> Result<Option<String>> {
+        let query = "SELECT html FROM fetch_cache
+            WHERE url = ? AND fetched_at >= ?";
+        let mut stmt = tx.prepare(query)?;
+        let mut rows = stmt.query([url, not_before])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(row.get(0)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn cache_html(
+        tx: &Transaction,
+        url: &str,
+        html: &str,
+        timestamp: &str,
+        expire_before: &str,
+    ) -> Result<()> {
+        // Opportunistically drop stale entries so the cache doesn't grow forever.
+        tx.execute(
+            "DELETE FROM fetch_cache WHERE fetched_at < ?",
+            [expire_before],
+        )?;
+        let values = named_params! {
+            ":url": url,
+            ":html": html,
+            ":fetched_at": timestamp,
+        };
+        tx.execute(
+            "INSERT INTO fetch_cache (url, html, fetched_at)
+            VALUES (:url, :html, :fetched_at)
+            ON CONFLICT(url) DO UPDATE
+            SET html = :html, fetched_at = :fetched_at",
+            values,
+        )?;
+        Ok(())
+    }
+
+    // OPERATIONS
+    pub fn log_operation(
+        tx: &Transaction,
+        operation: &str,
+        item_type: &str,
+        item_id: &TableId,
+        payload: &serde_json::Value,
+        timestamp: &str,
+    ) -> Result<()> {
+        tx.execute(
+            "INSERT INTO operation_log (id, operation, item_id, item_type, payload, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                get_uuid(),
+                operation,
+                item_id,
+                item_type,
+                payload.to_string(),
+                timestamp
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Newest first. IDs only have second precision, so the rowid orders
+    // operations logged within the same second.
+    pub fn get_operations(tx: &Transaction, limit: usize) -> Result<Vec<super::Operation>> {
+        let mut stmt = tx.prepare(
+            "SELECT id, operation, item_id, item_type, payload, created_at
+            FROM operation_log
+            ORDER BY rowid DESC
+            LIMIT ?",
+        )?;
+        let mut rows = stmt.query([limit])?;
+        let mut resp = vec![];
+        while let Some(row) = rows.next()? {
+            resp.push(super::Operation {
+                id: row.get(0)?,
+                operation: row.get(1)?,
+                item_id: row.get(2)?,
+                item_type: row.get(3)?,
+                payload: row
+                    .get::<_, Option<String>>(4)?
+                    .map(|p| serde_json::from_str(&p))
+                    .transpose()
+                    .map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(4, Type::Text, Box::new(e))
+                    })?,
+                created_at: row.get::<_, String>(5)?.parse()?,
+            });
+        }
+        Ok(resp)
+    }
+
+    pub fn delete_operation(tx: &Transaction, id: &TableId) -> Result<()> {
+        tx.execute("DELETE FROM operation_log WHERE id = ?", [id])?;
+        Ok(())
+    }
+
+    // SEARCH
+    // `query` is an FTS5 query, as made by util::parse_fts_query.
+    pub fn search_links(
+        tx: &Transaction,
+        query: &str,
+        filter: &LinkFilter,
+    ) -> Result<Vec<super::SearchHit>> {
+        let links = get_ranked_links(tx, vec![], Some(query), filter, false)?;
+        Ok(links
+            .into_iter()
+            .map(|(link, relevance)| super::SearchHit { link, relevance })
+            .collect())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use anyhow::Result;
+
+        const NOW: &str = "2025-01-01T00:00:00Z";
+
+        // A migrated in-memory database; open a transaction on it to test.
+        fn test_conn() -> Result<Connection> {
+            let mut conn = Connection::open_in_memory()?;
+            crate::db_migrations::migrate(&mut conn)?;
+            Ok(conn)
+        }
+
+        fn insert_test_link(tx: &Transaction, url: &str, is_primary: bool) -> Result<TableId> {
+            let link = LinkInsert {
+                url,
+                title: None,
+                description: None,
+                content: None,
+                is_primary,
+                timestamp: NOW,
+                created_at: None,
+                source: None,
+            };
+            Ok(insert_link(tx, &link, false)?)
+        }
+
+        #[test]
+        fn test_get_uuid_is_ordered() {
+            let ids: Vec<Uuid> = (0..1000).map(|_| get_uuid()).collect();
+            for pair in ids.windows(2) {
+                assert!(pair[0] < pair[1], "{} !< {}", pair[0], pair[1]);
+            }
+        }
+
+        #[test]
+        fn test_untag_link_and_note() -> Result<()> {
+            let mut conn = test_conn()?;
+            let tx = conn.transaction()?;
+            let link_id = insert_test_link(&tx, "https://example.com/", true)?;
+            let note_id = upsert_note(&tx, "A note", "note", None, NOW, None)?;
+            let rust = require_tag(&tx, "Rust", "rust", NOW)?;
+            let web = require_tag(&tx, "Web", "web", NOW)?;
+            for tag_id in [rust, web] {
+                tag_link(&tx, link_id, tag_id)?;
+                tag_note(&tx, note_id, tag_id)?;
+            }
+            untag_link(&tx, link_id, rust)?;
+            untag_note(&tx, note_id, web)?;
+            let names = |id| -> Result<Vec<String>> {
+                Ok(tags_for_item(&tx, &id)?
+                    .into_iter()
+                    .map(|t| t.name)
+                    .collect())
+            };
+            assert_eq!(names(link_id)?, ["Web"]);
+            assert_eq!(names(note_id)?, ["Rust"]);
+            Ok(())
+        }
+
+        #[test]
+        fn test_unrelate_keeps_archived_and_noted_links() -> Result<()> {
+            let mut conn = test_conn()?;
+            let tx = conn.transaction()?;
+            let primary = insert_test_link(&tx, "https://p.example/", true)?;
+            let archived = insert_test_link(&tx, "https://x.example/", false)?;
+            let noted = insert_test_link(&tx, "https://y.example/", false)?;
+            let orphan = insert_test_link(&tx, "https://z.example/", false)?;
+            upsert_note(&tx, "A note", "y", Some(&noted), NOW, None)?;
+            archive_link(&tx, &archived, NOW)?;
+            for related in [archived, noted, orphan] {
+                relate_links(&tx, primary, related, None)?;
+                delete_related_links(&tx, Some(&primary), Some(&related))?;
+                delete_orphaned_related_link(&tx, &related)?;
+            }
+            let exists = |id| -> Result<bool> {
+                Ok(get_link(&tx, TermOrId::Id(id), IsPrimary::Either)?.is_some())
+            };
+            assert!(exists(archived)?);
+            assert!(exists(noted)?);
+            assert!(!exists(orphan)?);
+            Ok(())
+        }
+
+        #[test]
+        fn test_reslug_merges_transliterated_tags() -> Result<()> {
+            let mut conn = test_conn()?;
+            let tx = conn.transaction()?;
+            let first = insert_test_link(&tx, "https://a.example/", true)?;
+            let second = insert_test_link(&tx, "https://b.example/", true)?;
+            // Slugs as they were made before transliteration.
+            let accented = require_tag(&tx, "Café", "café", "2025-01-01T00:00:00Z")?;
+            let plain = require_tag(&tx, "cafe", "cafe", "2025-02-01T00:00:00Z")?;
+            let child = require_tag(&tx, "Espresso", "espresso", "2025-03-01T00:00:00Z")?;
+            set_tag_parent(&tx, &child, Some(&plain))?;
+            tag_link(&tx, first, accented)?;
+            tag_link(&tx, first, plain)?;
+            tag_link(&tx, second, plain)?;
+            crate::db_migrations::reslug_tags(&tx)?;
+            let tag = get_tag_by_slug(&tx, "cafe")?.unwrap();
+            assert_eq!(tag.id, accented);
+            assert!(get_tag_by_slug(&tx, "café")?.is_none());
+            for item in [first, second] {
+                let tags = tags_for_item(&tx, &item)?;
+                assert_eq!(tags.iter().map(|t| t.id).collect::<Vec<_>>(), [accented]);
+            }
+            let child = get_tag_by_slug(&tx, "espresso")?.unwrap();
+            assert_eq!(child.parent_id, Some(accented));
+            Ok(())
+        }
+    }
+}
+
+mod util {
+    use anyhow::{anyhow, Result};
+    use jiff::civil::Date;
+    use jiff::tz::TimeZone;
+    use jiff::{Span, Timestamp, Zoned};
+    use meowpad::MeowpadError;
+    use sha2::{Digest, Sha256};
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::sync::OnceLock;
+    use unidecode::unidecode;
+    use url::Url;
+
+    const LOG_LEVELS: [&str; 4] = ["DEBUG", "INFO", "WARN", "ERROR"];
+
+    /// Writes a JSON log line to stderr if `MEOWPAD_LOG` is set to a level
+    /// (DEBUG, INFO, WARN, or ERROR) at or below `level`; any other value,
+    /// such as 1, means INFO.
+    pub fn log_event(level: &str, msg: &str, fields: &[(&str, &str)]) {
+        static THRESHOLD: OnceLock<Option<usize>> = OnceLock::new();
+        let threshold = THRESHOLD.get_or_init(|| {
+            let value = std::env::var("MEOWPAD_LOG").ok()?;
+            let value = value.trim().to_uppercase();
+            match value.as_str() {
+                "" | "0" | "FALSE" | "OFF" => None,
+                _ => Some(log_level_rank(&value).unwrap_or(1)),
+            }
+        });
+        let Some(threshold) = threshold else {
+            return;
+        };
+        if log_level_rank(level).unwrap_or(0) >= *threshold {
+            let ts = Timestamp::now().to_string();
+            eprintln!("{}", format_log_event(level, msg, fields, &ts));
+        }
+    }
+
+    fn log_level_rank(level: &str) -> Option<usize> {
+        LOG_LEVELS.iter().position(|l| *l == level)
+    }
+
+    // Built by hand, rather than via a map, to keep the keys in order.
+    fn format_log_event(level: &str, msg: &str, fields: &[(&str, &str)], ts: &str) -> String {
+        let pairs = [("level", level), ("msg", msg)]
+            .iter()
+            .chain(fields)
+            .chain(&[("ts", ts)])
+            .map(|(k, v)| format!("{}:{}", serde_json::json!(k), serde_json::json!(v)))
+            .collect::<Vec<_>>();
+        format!("{{{}}}", pairs.join(","))
+    }
+
+    pub fn validate_url(url_str: &str, allow_file: bool) -> meowpad::Result<Url> {
+        let invalid = |reason: String| MeowpadError::InvalidUrl(url_str.to_string(), reason);
+        let url = Url::parse(url_str.trim()).map_err(|e| match e {
+            url::ParseError::RelativeUrlWithoutBase => {
+                invalid("did you forget the scheme (e.g. https://)?".to_string())
+            }
+            _ => invalid(e.to_string()),
+        })?;
+        match url.scheme() {
+            "http" | "https" => {
+                if url.host_str().is_none_or(|h| h.is_empty()) {
+                    return Err(invalid("it is missing a host name".to_string()));
+                }
+            }
+            "file" if allow_file => {}
+            _ => {
+                return Err(invalid(
+                    "only http and https links can be added".to_string(),
+                ))
+            }
+        }
+        // `Url::parse` has already normalized the scheme, host, and path; we
+        // additionally drop empty fragments, which never change the target.
+        let mut url = url;
+        if url.fragment() == Some("") {
+            url.set_fragment(None);
+        }
+        Ok(url)
+    }
+
+    // Replaces `{{name}}` placeholders with their values; unknown placeholders
+    // are left alone.
+    pub fn render_template(template: &str, vars: &HashMap<&str, &str>) -> String {
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start..].find("}}") else {
+                break;
+            };
+            let placeholder = &rest[start..start + len + 2];
+            rendered.push_str(&rest[..start]);
+            match vars.get(placeholder[2..len].trim()) {
+                Some(value) => rendered.push_str(value),
+                None => rendered.push_str(placeholder),
+            }
+            rest = &rest[start + len + 2..];
+        }
+        rendered.push_str(rest);
+        rendered
+    }
+
+    // The database given on the command line, or else in MEOWPAD_DB, or else
+    // the one from the config file (or its default).
+    pub fn database_path(
+        cli_db: Option<PathBuf>,
+        env_db: Option<PathBuf>,
+        config_db: PathBuf,
+    ) -> PathBuf {
+        cli_db.or(env_db).unwrap_or(config_db)
+    }
+
+    // Replaces a leading `~` component with the given home directory. Paths
+    // like `~other/x` (another user's home) are left untouched.
+    pub fn expand_tilde(path: &Path, home: &Path) -> PathBuf {
+        match path.strip_prefix("~") {
+            Ok(rest) => home.join(rest),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+
+    // A quick scan for the <title> and <meta name="description"> of a page,
+    // for when running readability over the whole thing isn't worth it.
+    pub fn extract_title_and_description(html: &str) -> (String, Option<String>) {
+        // ASCII lowercasing keeps byte offsets the same, so positions found
+        // in `lower` can be used to slice `html`.
+        let lower = html.to_ascii_lowercase();
+        let title = lower
+            .find("<title")
+            .and_then(|start| {
+                let open_end = start + lower[start..].find('>')? + 1;
+                let close = open_end + lower[open_end..].find("</title")?;
+                Some(unescape_html(&html[open_end..close]))
+            })
+            .map(|t| t.split_whitespace().collect::<Vec<_>>().join(" "))
+            .unwrap_or_default();
+        let mut description = None;
+        let mut rest = 0;
+        while let Some(offset) = lower[rest..].find("<meta") {
+            let start = rest + offset;
+            let Some(len) = lower[start..].find('>') else {
+                break;
+            };
+            let tag = &html[start..start + len];
+            if html_attr(tag, "name").is_some_and(|n| n.eq_ignore_ascii_case("description")) {
+                description = html_attr(tag, "content")
+                    .map(|c| unescape_html(c).trim().to_string())
+                    .filter(|c| !c.is_empty());
+                break;
+            }
+            rest = start + len;
+        }
+        (title, description)
+    }
+
+    fn html_attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+        let lower = tag.to_ascii_lowercase();
+        let mut rest = 0;
+        while let Some(offset) = lower[rest..].find(name) {
+            let start = rest + offset;
+            rest = start + name.len();
+            // Make sure we matched a whole attribute name, not the end of
+            // `data-name` or the start of `names`.
+            let preceded = lower[..start].ends_with(|c: char| c.is_ascii_whitespace());
+            let value = lower[rest..].trim_start();
+            if !preceded || !value.starts_with('=') {
+                continue;
+            }
+            let value_start = tag.len() - value.len() + 1;
+            let value = tag[value_start..].trim_start();
+            let value_start = tag.len() - value.len();
+            return match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = value[1..].find(quote)?;
+                    Some(&tag[value_start + 1..value_start + 1 + end])
+                }
+                _ => {
+                    let end = value
+                        .find(|c: char| c.is_ascii_whitespace() || c == '/')
+                        .unwrap_or(value.len());
+                    Some(&tag[value_start..value_start + end])
+                }
+            };
+        }
+        None
+    }
+
+    /// Pulls a DOI out of `doi:10.1000/xyz`, a doi.org URL, or a bare
+    /// `10.1000/xyz`.
+    pub fn parse_doi(s: &str) -> Option<String> {
+        let s = s.trim();
+        let doi = if s.get(..4).is_some_and(|p| p.eq_ignore_ascii_case("doi:")) {
+            s[4..].trim().to_string()
+        } else if let Ok(url) = Url::parse(s) {
+            let host = url.host_str()?;
+            if !matches!(url.scheme(), "http" | "https")
+                || !matches!(host, "doi.org" | "dx.doi.org" | "www.doi.org")
+            {
+                return None;
+            }
+            url.path().trim_start_matches('/').to_string()
+        } else {
+            s.to_string()
+        };
+        // Every DOI is a "10." directory prefix, a slash, and a suffix.
+        let (prefix, suffix) = doi.split_once('/')?;
+        (prefix.starts_with("10.") && prefix.len() > 3 && !suffix.is_empty()).then_some(doi)
+    }
+
+    /// arXiv's DOIs look like 10.48550/arXiv.2101.00001.
+    pub fn arxiv_id_from_doi(doi: &str) -> Option<String> {
+        let (prefix, suffix) = doi.split_once('/')?;
+        let is_arxiv = suffix
+            .get(..6)
+            .is_some_and(|p| p.eq_ignore_ascii_case("arxiv."));
+        if prefix != "10.48550" || !is_arxiv || suffix.len() == 6 {
+            return None;
+        }
+        Some(suffix[6..].to_string())
+    }
+
+    /// The URL of the closest snapshot in a Wayback Machine availability API
+    /// response, if it has one.
+    pub fn extract_wayback_snapshot(json: &str) -> Option<String> {
+        let response: serde_json::Value = serde_json::from_str(json).ok()?;
+        let closest = &response["archived_snapshots"]["closest"];
+        if closest["available"].as_bool() != Some(true) {
+            return None;
+        }
+        closest["url"].as_str().map(|u| u.to_string())
     }
 
-    // SEARCH
-    pub fn search_links(tx: &Transaction, term: &str) -> Result<Vec<super::Link>> {
-        get_links(tx, vec![], Some(term))
+    /// The title and abstract of the first entry in an arXiv API response.
+    pub fn extract_arxiv_info(xml: &str) -> Option<(String, String)> {
+        let entry = &xml[xml.find("<entry>")?..];
+        let element = |name: &str| {
+            let open = format!("<{name}>");
+            let start = entry.find(&open)? + open.len();
+            let end = start + entry[start..].find(&format!("</{name}>"))?;
+            let text = unescape_html(&entry[start..end]);
+            Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+        };
+        Some((element("title")?, element("summary")?))
     }
-}
 
-mod util {
-    use anyhow::{anyhow, Result};
+    fn unescape_html(s: &str) -> String {
+        s.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&#x27;", "'")
+            .replace("&amp;", "&")
+    }
+
+    /// An SVG QR code of the URL, or None if it's too long to encode.
+    pub fn url_to_qr_svg(url: &str) -> Option<String> {
+        use qrcodegen::{QrCode, QrCodeEcc};
+        let qr = QrCode::encode_text(url, QrCodeEcc::Low).ok()?;
+        // The standard quiet zone around the code is four modules wide.
+        let border = 4;
+        let size = qr.size() + border * 2;
+        let mut path = String::new();
+        for y in 0..qr.size() {
+            for x in 0..qr.size() {
+                if qr.get_module(x, y) {
+                    path.push_str(&format!("M{},{}h1v1h-1z", x + border, y + border));
+                }
+            }
+        }
+        Some(format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {size} {size}\" \
+            shape-rendering=\"crispEdges\"><rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>\
+            <path d=\"{path}\" fill=\"#000\"/></svg>"
+        ))
+    }
+
+    pub fn escape_html(s: &str) -> String {
+        let mut escaped = String::with_capacity(s.len());
+        for c in s.chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&#39;"),
+                _ => escaped.push(c),
+            }
+        }
+        escaped
+    }
 
     pub fn slugify(tag: &str) -> Result<String> {
         let mut is_sep = true;
         let mut slug: String = "".to_string();
-        tag.to_lowercase().trim().chars().for_each(|c| {
+        // Transliterate first, so that e.g. "Å" becomes "a" rather than
+        // surviving (or being dropped) as-is.
+        unidecode(tag).to_lowercase().trim().chars().for_each(|c| {
             if c.is_alphanumeric() {
                 is_sep = false;
                 slug.push(c);
@@ -1419,6 +9526,636 @@ mod util {
         Ok(valid_pieces.join(":"))
     }
 
+    // Words left lowercase in title case, unless they start or end the title.
+    const MINOR_WORDS: &[&str] = &[
+        "a", "an", "and", "as", "at", "but", "by", "for", "from", "in", "into", "nor", "of", "on",
+        "or", "over", "per", "so", "the", "to", "up", "via", "vs", "with", "yet",
+    ];
+
+    // Uppercases the first letter, skipping any leading punctuation.
+    fn capitalize(word: &str) -> String {
+        match word.char_indices().find(|(_, c)| c.is_alphabetic()) {
+            Some((i, c)) => {
+                let mut capitalized = word[..i].to_string();
+                capitalized.extend(c.to_uppercase());
+                capitalized.push_str(&word[i + c.len_utf8()..]);
+                capitalized
+            }
+            None => word.to_string(),
+        }
+    }
+
+    fn is_all_uppercase(s: &str) -> bool {
+        s.chars().any(char::is_uppercase) && !s.chars().any(char::is_lowercase)
+    }
+
+    // Shouted titles are lowercased first; otherwise words keep their case, so
+    // that names like "GitHub" and acronyms like "API" survive.
+    fn unshout(s: &str) -> String {
+        let s = s.trim();
+        if is_all_uppercase(s) {
+            s.to_lowercase()
+        } else {
+            s.to_string()
+        }
+    }
+
+    // Basic English title case. Minor words are lowercased, unless they're in
+    // capitals (and so may be acronyms); other words only gain a capital.
+    pub fn to_title_case(s: &str) -> String {
+        let title = unshout(s);
+        let words: Vec<&str> = title.split_whitespace().collect();
+        let last = words.len().saturating_sub(1);
+        words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                let bare = word
+                    .trim_matches(|c: char| !c.is_alphanumeric())
+                    .to_lowercase();
+                if i != 0 && i != last && MINOR_WORDS.contains(&bare.as_str()) {
+                    if is_all_uppercase(word) {
+                        word.to_string()
+                    } else {
+                        word.to_lowercase()
+                    }
+                } else {
+                    capitalize(word)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn to_sentence_case(s: &str) -> String {
+        capitalize(&unshout(s))
+    }
+
+    // Translates our search syntax into an FTS5 query: bare terms are ANDed
+    // together and a leading `-` excludes a term (`rust -async` becomes
+    // `rust NOT async`). Quoted phrases and explicit operators pass through.
+    // A lone `-` is dropped, there must be at least one term to exclude
+    // from, and each operator needs a term on either side.
+    pub fn parse_fts_query(term: &str) -> Result<String> {
+        let mut tokens: Vec<String> = vec![];
+        let mut current = String::new();
+        let mut in_quotes = false;
+        for c in term.chars() {
+            if c == '"' {
+                in_quotes = !in_quotes;
+                current.push(c);
+            } else if c.is_whitespace() && !in_quotes {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            } else {
+                current.push(c);
+            }
+        }
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+
+        let mut query: Vec<String> = vec![];
+        let mut excluded: Vec<String> = vec![];
+        let mut after_operator = true;
+        for token in tokens {
+            if matches!(token.as_str(), "AND" | "OR" | "NOT") {
+                query.push(token);
+                after_operator = true;
+            } else if token == "-" {
+                continue;
+            } else if let Some(negated) = token.strip_prefix('-') {
+                excluded.push(negated.to_string());
+            } else {
+                if !after_operator {
+                    query.push("AND".to_string());
+                }
+                query.push(token);
+                after_operator = false;
+            }
+        }
+        if query.is_empty() {
+            return Err(anyhow!(
+                "`{term}` has nothing to search for; add a term that isn't excluded with `-`"
+            ));
+        }
+        let is_operator = |t: &String| matches!(t.as_str(), "AND" | "OR" | "NOT");
+        let dangling = query
+            .iter()
+            .enumerate()
+            .find(|(i, t)| is_operator(t) && (*i == 0 || query.get(i + 1).is_none_or(is_operator)))
+            .map(|(_, t)| t);
+        if let Some(operator) = dangling {
+            return Err(anyhow!(
+                "`{term}` has nothing to search for on one side of `{operator}`; add a term there"
+            ));
+        }
+        // FTS5's NOT is a binary operator that binds tighter than AND and OR,
+        // so the positive terms are grouped and the exclusions go after them.
+        let mut query = if query.len() > 1 && !excluded.is_empty() {
+            vec![format!("({})", query.join(" "))]
+        } else {
+            query
+        };
+        for negated in excluded {
+            query.push("NOT".to_string());
+            query.push(negated);
+        }
+        Ok(query.join(" "))
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub struct TextStats {
+        pub words: usize,
+        pub chars: usize,
+        pub lines: usize,
+    }
+
+    // Hex-encoded SHA-256 digest, used to tell whether stored content changed.
+    pub fn sha256(s: &str) -> String {
+        format!("{:x}", Sha256::digest(s.as_bytes()))
+    }
+
+    /// Make `s` safe to use as a single TSV field: tabs and line breaks
+    /// would split the field or the row, so they become spaces.
+    pub fn tsv_field(s: &str) -> String {
+        s.replace("\r\n", " ").replace(['\t', '\n', '\r'], " ")
+    }
+
+    pub fn text_stats(s: &str) -> TextStats {
+        TextStats {
+            words: s.split_whitespace().count(),
+            chars: s.chars().count(),
+            lines: s.lines().count(),
+        }
+    }
+
+    /// A rough, human-scale rendering of a number of seconds, e.g. "3d 4h".
+    pub fn format_duration(secs: i64) -> String {
+        let minutes = secs.max(0) / 60;
+        let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+        match (days, hours) {
+            (0, 0) if minutes == 0 => "less than a minute".to_string(),
+            (0, 0) => format!("{minutes}m"),
+            (0, _) => format!("{hours}h {minutes}m"),
+            _ => format!("{days}d {hours}h"),
+        }
+    }
+
+    /// The string cut to at most `max_chars` characters, ending with an
+    /// ellipsis if anything was cut.
+    pub fn truncate_chars(s: &str, max_chars: usize) -> String {
+        if s.chars().count() <= max_chars {
+            return s.to_string();
+        }
+        let mut truncated: String = s.chars().take(max_chars.saturating_sub(1)).collect();
+        truncated.push('…');
+        truncated
+    }
+
+    // One of six colors that read on both light and dark backgrounds, picked
+    // by an FNV-1a hash so a namespace gets the same color on every run.
+    pub fn tag_color(namespace: &str) -> comfy_table::Color {
+        use comfy_table::Color;
+        const COLORS: [Color; 6] = [
+            Color::Red,
+            Color::Green,
+            Color::Yellow,
+            Color::Blue,
+            Color::Magenta,
+            Color::Cyan,
+        ];
+        let hash = namespace.bytes().fold(0xcbf29ce484222325_u64, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+        });
+        COLORS[(hash % COLORS.len() as u64) as usize]
+    }
+
+    pub fn format_bytes(n: usize) -> String {
+        const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+        if n < 1024 {
+            return format!("{n} B");
+        }
+        let mut size = n as f64 / 1024.0;
+        let mut unit = UNITS[0];
+        for next_unit in &UNITS[1..] {
+            // Compare the size as it will be shown, so that just under 1 MB
+            // is "1.0 MB" rather than "1024.0 KB".
+            if (size * 10.0).round() < 10240.0 {
+                break;
+            }
+            size /= 1024.0;
+            unit = next_unit;
+        }
+        format!("{size:.1} {unit}")
+    }
+
+    // The wait before retry number `attempt` (from 0): the base delay,
+    // doubled for each earlier attempt.
+    pub fn backoff_delay(base_delay_ms: u64, attempt: u8) -> std::time::Duration {
+        let factor = 1u64.checked_shl(u32::from(attempt)).unwrap_or(u64::MAX);
+        std::time::Duration::from_millis(base_delay_ms.saturating_mul(factor))
+    }
+
+    // Accepts a calendar date (midnight, local time) or a full RFC 3339 timestamp.
+    pub fn parse_date(s: &str) -> Result<Timestamp> {
+        if let Ok(timestamp) = s.parse::<Timestamp>() {
+            return Ok(timestamp);
+        }
+        let date: Date = s
+            .parse()
+            .map_err(|_| anyhow!("`{}` is not a valid date; expected e.g. 2024-06-01", s))?;
+        Ok(date.to_zoned(TimeZone::system())?.timestamp())
+    }
+
+    // Accepts a duration such as "2 weeks", "30d", or "P1M" and returns the
+    // moment that long before now.
+    pub fn parse_since(s: &str) -> Result<Timestamp> {
+        let span: Span = s.parse().map_err(|_| {
+            anyhow!(
+                "`{}` is not a valid duration; expected e.g. \"2 weeks\" or 30d",
+                s
+            )
+        })?;
+        Ok(Zoned::now().checked_sub(span)?.timestamp())
+    }
+
+    #[test]
+    fn test_validate_url() -> Result<()> {
+        let normalized = validate_url("HTTPS://Example.COM", false)?;
+        assert_eq!(normalized.as_str(), "https://example.com/");
+
+        let empty_fragment = validate_url("https://example.com/page#", false)?;
+        assert_eq!(empty_fragment.as_str(), "https://example.com/page");
+
+        assert!(validate_url("example.com", false).is_err());
+        assert!(validate_url("ftp://example.com/file", false).is_err());
+        assert!(validate_url("file:///tmp/notes.txt", false).is_err());
+        assert!(validate_url("file:///tmp/notes.txt", true).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_fts_query() -> Result<()> {
+        assert_eq!(parse_fts_query("rust")?, "rust".to_string());
+        assert_eq!(parse_fts_query("rust web")?, "rust AND web".to_string());
+        assert_eq!(
+            parse_fts_query("rust -async")?,
+            "rust NOT async".to_string()
+        );
+        assert_eq!(
+            parse_fts_query("-async rust -tokio web")?,
+            "(rust AND web) NOT async NOT tokio".to_string()
+        );
+        assert_eq!(
+            parse_fts_query("\"async rust\" -\"web server\"")?,
+            "\"async rust\" NOT \"web server\"".to_string()
+        );
+        assert_eq!(parse_fts_query("rust OR go")?, "rust OR go".to_string());
+        assert_eq!(
+            parse_fts_query("  rust   web ")?,
+            "rust AND web".to_string()
+        );
+        assert_eq!(
+            parse_fts_query("rust OR go -async")?,
+            "(rust OR go) NOT async".to_string()
+        );
+        assert_eq!(parse_fts_query("rust -")?, "rust".to_string());
+        assert!(parse_fts_query("rust OR").is_err());
+        assert!(parse_fts_query("AND rust").is_err());
+        assert!(parse_fts_query("rust OR AND go").is_err());
+        assert!(parse_fts_query("rust OR -async").is_err());
+        assert!(parse_fts_query("-async").is_err());
+        assert!(parse_fts_query("-async -tokio").is_err());
+        assert!(parse_fts_query("-").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            sha256(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".to_string()
+        );
+        assert_eq!(
+            sha256("meow"),
+            "404cdd7bc109c432f8cc2443b45bcfe95980f5107215c645236e577929ac3e52".to_string()
+        );
+    }
+
+    #[test]
+    fn test_text_stats() {
+        assert_eq!(
+            text_stats(""),
+            TextStats {
+                words: 0,
+                chars: 0,
+                lines: 0
+            }
+        );
+        assert_eq!(
+            text_stats("Ångström units\nare  small"),
+            TextStats {
+                words: 4,
+                chars: 25,
+                lines: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_tsv_field() {
+        assert_eq!(tsv_field("plain title"), "plain title");
+        assert_eq!(tsv_field("a\tb\nc\r\nd"), "a b c d");
+    }
+
+    #[test]
+    fn test_format_log_event() {
+        assert_eq!(
+            format_log_event(
+                "INFO",
+                "fetching url",
+                &[("url", "https://example.com/\"q\"")],
+                "2025-01-01T00:00:00Z"
+            ),
+            r#"{"level":"INFO","msg":"fetching url","url":"https://example.com/\"q\"","ts":"2025-01-01T00:00:00Z"}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_doi() {
+        let doi = Some("10.1000/xyz".to_string());
+        assert_eq!(parse_doi("doi:10.1000/xyz"), doi);
+        assert_eq!(parse_doi("DOI: 10.1000/xyz"), doi);
+        assert_eq!(parse_doi("https://doi.org/10.1000/xyz"), doi);
+        assert_eq!(parse_doi("http://dx.doi.org/10.1000/xyz"), doi);
+        assert_eq!(parse_doi("10.1000/xyz"), doi);
+        assert_eq!(parse_doi("https://example.com/10.1000/xyz"), None);
+        assert_eq!(parse_doi("https://doi.org/"), None);
+        assert_eq!(parse_doi("doi:11.1000/xyz"), None);
+        assert_eq!(parse_doi("日本語"), None);
+
+        assert_eq!(
+            arxiv_id_from_doi("10.48550/arXiv.2101.00001"),
+            Some("2101.00001".to_string())
+        );
+        assert_eq!(arxiv_id_from_doi("10.1000/xyz"), None);
+    }
+
+    #[test]
+    fn test_extract_wayback_snapshot() {
+        let json = r#"{"url": "example.com", "archived_snapshots": {"closest": {
+            "status": "200", "available": true,
+            "url": "http://web.archive.org/web/20130919044612/http://example.com/",
+            "timestamp": "20130919044612"}}}"#;
+        assert_eq!(
+            extract_wayback_snapshot(json),
+            Some("http://web.archive.org/web/20130919044612/http://example.com/".to_string())
+        );
+        let none = r#"{"url": "example.com/nope", "archived_snapshots": {}}"#;
+        assert_eq!(extract_wayback_snapshot(none), None);
+        assert_eq!(extract_wayback_snapshot("not json"), None);
+    }
+
+    #[test]
+    fn test_extract_arxiv_info() {
+        let xml = "<feed><title>ArXiv Query</title><entry><id>x</id>
+            <title>Attention Is All
+              You Need</title><summary>  The dominant &amp; sequence
+              models.</summary></entry></feed>";
+        assert_eq!(
+            extract_arxiv_info(xml),
+            Some((
+                "Attention Is All You Need".to_string(),
+                "The dominant & sequence models.".to_string()
+            ))
+        );
+        assert_eq!(extract_arxiv_info("<feed></feed>"), None);
+    }
+
+    #[test]
+    fn test_parse_date() -> Result<()> {
+        let timestamp = "2024-06-01T12:30:00Z";
+        assert_eq!(parse_date(timestamp)?, timestamp.parse::<Timestamp>()?);
+
+        let midnight = Date::new(2024, 6, 1)?.to_zoned(TimeZone::system())?;
+        assert_eq!(parse_date("2024-06-01")?, midnight.timestamp());
+
+        assert!(parse_date("last tuesday").is_err());
+        assert!(parse_date("2024-13-01").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_since() -> Result<()> {
+        let before = Zoned::now().checked_sub(Span::new().days(30))?;
+        let since = parse_since("30d")?;
+        assert!(since >= before.timestamp());
+        assert!(since < Timestamp::now());
+
+        assert!(parse_since("2 weeks").is_ok());
+        assert!(parse_since("forever").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        use std::time::Duration;
+        assert_eq!(backoff_delay(1000, 0), Duration::from_secs(1));
+        assert_eq!(backoff_delay(1000, 1), Duration::from_secs(2));
+        assert_eq!(backoff_delay(1000, 2), Duration::from_secs(4));
+        assert_eq!(backoff_delay(0, 5), Duration::ZERO);
+        assert_eq!(backoff_delay(1000, 255), Duration::from_millis(u64::MAX));
+    }
+
+    #[test]
+    fn test_tag_color() {
+        assert_eq!(tag_color("lang"), tag_color("lang"));
+        let colors: std::collections::HashSet<_> =
+            ["lang", "topic", "status", "person", "place", "year", "tool"]
+                .iter()
+                .map(|ns| format!("{:?}", tag_color(ns)))
+                .collect();
+        assert!(colors.len() > 1);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B".to_string());
+        assert_eq!(format_bytes(1023), "1023 B".to_string());
+        assert_eq!(format_bytes(1024), "1.0 KB".to_string());
+        assert_eq!(format_bytes(12 * 1024), "12.0 KB".to_string());
+        assert_eq!(format_bytes(1048575), "1.0 MB".to_string());
+        assert_eq!(format_bytes(1048524), "1023.9 KB".to_string());
+        assert_eq!(format_bytes(1048576), "1.0 MB".to_string());
+        assert_eq!(format_bytes(3565158), "3.4 MB".to_string());
+        assert_eq!(format_bytes(1073741824), "1.0 GB".to_string());
+        assert_eq!(format_bytes(5 * 1073741824), "5.0 GB".to_string());
+    }
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("short", 10), "short".to_string());
+        assert_eq!(truncate_chars("exactly10!", 10), "exactly10!".to_string());
+        assert_eq!(
+            truncate_chars("a bit too long", 10),
+            "a bit too…".to_string()
+        );
+        assert_eq!(truncate_chars("ééééé", 3), "éé…".to_string());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(-5), "less than a minute".to_string());
+        assert_eq!(format_duration(59), "less than a minute".to_string());
+        assert_eq!(format_duration(60 * 45), "45m".to_string());
+        assert_eq!(format_duration(3600 * 2 + 60 * 5), "2h 5m".to_string());
+        assert_eq!(
+            format_duration(86400 * 3 + 3600 * 4 + 60),
+            "3d 4h".to_string()
+        );
+    }
+
+    #[test]
+    fn test_render_template() {
+        let vars = HashMap::from([("title", "Dune"), ("date", "2025-01-01")]);
+        assert_eq!(
+            render_template("# {{title}}\nRead on {{ date }}", &vars),
+            "# Dune\nRead on 2025-01-01".to_string()
+        );
+        assert_eq!(
+            render_template("{{url}} and {{title", &vars),
+            "{{url}} and {{title".to_string()
+        );
+        assert_eq!(render_template("", &vars), "".to_string());
+    }
+
+    #[test]
+    fn test_url_to_qr_svg() {
+        let svg = url_to_qr_svg("https://example.com/").unwrap();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>"));
+        // Version 2 at the lowest error correction: 25 modules plus borders.
+        assert!(svg.contains("viewBox=\"0 0 33 33\""));
+        let too_long = format!("https://example.com/{}", "a".repeat(3000));
+        assert_eq!(url_to_qr_svg(&too_long), None);
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(escape_html("plain text"), "plain text".to_string());
+        assert_eq!(
+            escape_html("<a href=\"x?a=1&b='2'\">"),
+            "&lt;a href=&quot;x?a=1&amp;b=&#39;2&#39;&quot;&gt;".to_string()
+        );
+    }
+
+    #[test]
+    fn test_expand_tilde() {
+        let home = Path::new("/home/meow");
+        assert_eq!(
+            expand_tilde(Path::new("~/.local/share/meowpad.db"), home),
+            PathBuf::from("/home/meow/.local/share/meowpad.db")
+        );
+        assert_eq!(
+            expand_tilde(Path::new("~"), home),
+            PathBuf::from("/home/meow")
+        );
+        assert_eq!(
+            expand_tilde(Path::new("/tmp/~/meowpad.db"), home),
+            PathBuf::from("/tmp/~/meowpad.db")
+        );
+        assert_eq!(
+            expand_tilde(Path::new("~other/meowpad.db"), home),
+            PathBuf::from("~other/meowpad.db")
+        );
+        assert_eq!(
+            expand_tilde(Path::new("meowpad.db"), home),
+            PathBuf::from("meowpad.db")
+        );
+    }
+
+    #[test]
+    fn test_database_path() {
+        let cli = || Some(PathBuf::from("/cli.db"));
+        let env = || Some(PathBuf::from("~/env.db"));
+        let config = || PathBuf::from("/config.db");
+        assert_eq!(
+            database_path(cli(), env(), config()),
+            PathBuf::from("/cli.db")
+        );
+        assert_eq!(
+            database_path(None, env(), config()),
+            PathBuf::from("~/env.db")
+        );
+        assert_eq!(
+            database_path(None, None, config()),
+            PathBuf::from("/config.db")
+        );
+        assert_eq!(
+            database_path(None, None, super::Config::default().database),
+            super::default_db_location()
+        );
+    }
+
+    #[test]
+    fn test_extract_title_and_description() {
+        let html = r#"<html><head>
+            <TITLE>
+              Cats &amp; Dogs
+            </TITLE>
+            <meta charset="utf-8">
+            <meta property="og:description" content="Not this one">
+            <meta content='All about pets' name=description>
+            </head><body><title>Ignored</title></body></html>"#;
+        assert_eq!(
+            extract_title_and_description(html),
+            (
+                "Cats & Dogs".to_string(),
+                Some("All about pets".to_string())
+            )
+        );
+        assert_eq!(
+            extract_title_and_description("<p>No head here</p>"),
+            ("".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn test_to_title_case() {
+        assert_eq!(
+            to_title_case("THE LORD OF THE RINGS"),
+            "The Lord of the Rings".to_string()
+        );
+        assert_eq!(
+            to_title_case("what the web is made of"),
+            "What the Web Is Made Of".to_string()
+        );
+        assert_eq!(
+            to_title_case("\"quoted\" words  and   spaces"),
+            "\"Quoted\" Words and Spaces".to_string()
+        );
+        assert_eq!(
+            to_title_case("ÉCOLE DES ÉLÈVES"),
+            "École Des Élèves".to_string()
+        );
+        assert_eq!(to_title_case(""), "".to_string());
+        assert_eq!(
+            to_title_case("GitHub APIs Of THE Week"),
+            "GitHub APIs of THE Week".to_string()
+        );
+        assert_eq!(
+            to_sentence_case("ÜBER THE MOON"),
+            "Über the moon".to_string()
+        );
+        assert_eq!(
+            to_sentence_case("the GitHub API"),
+            "The GitHub API".to_string()
+        );
+    }
+
     #[test]
     fn test_slugify() -> Result<()> {
         let base_case = "Jacques Torneur";
@@ -1442,6 +10179,12 @@ mod util {
             "ns1:ns2:actual-term".to_string()
         );
 
+        let accented = "Ångström";
+        assert_eq!(slugify(accented)?, "angstrom".to_string());
+
+        let non_latin = "日本語";
+        assert_eq!(slugify(non_latin)?, "ri-ben-yu".to_string());
+
         let invalid_empty = "";
         assert!(slugify(invalid_empty).is_err());
 